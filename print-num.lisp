@@ -1,5 +1,5 @@
 (define print-num
-  (lambda n 
+  (lambda (n) 
     (println (string n))))
 
 (print-num 2)
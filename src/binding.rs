@@ -0,0 +1,277 @@
+use crate::env::{Binding, Env, Frame, Scope};
+use crate::machine::{self, Cont, Pending, State};
+use crate::params::Params;
+use crate::syntax::{self, Atom, Expr, LambdaDef};
+use std::rc::Rc;
+
+// Names and initial expressions of a binding form, in order.
+pub type Bindings = Vec<(String, Expr)>;
+
+pub trait BindingForms {
+    fn let_op(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn let_star(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn letrec(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn letrec_star(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+}
+
+// Every form leaves its body in tail position, evaluated in a new frame
+// so that the bindings are gone once the form returns.
+impl BindingForms for Env {
+    fn let_op(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if let Some(Expr::Atom(atom)) = args.first() {
+            if let Atom::Symbol(name) = &**atom {
+                return named_let(name, &args[1..], scope, k);
+            }
+        }
+
+        let (bindings, body) = parse_bindings(args, syntax::LET_OP)?;
+        let (names, inits): (Vec<String>, Vec<Expr>) = bindings.into_iter().unzip();
+
+        // Initial values can't see any of the new names.
+        Ok(resume_let(names, Vec::new(), reversed(inits), body, scope, k))
+    }
+
+    fn let_star(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let (mut bindings, body) = parse_bindings(args, syntax::LET_STAR_OP)?;
+        let scope = Frame::new_scope(Some(&scope));
+
+        bindings.reverse();
+
+        // Each binding gets its own frame inside the previous one.
+        match bindings.pop() {
+            Some((name, init)) => {
+                let k = k.push(Pending::LetStar {
+                    name,
+                    rest: bindings,
+                    body,
+                    scope: scope.clone(),
+                });
+
+                Ok(State::Eval(init, scope, k))
+            }
+            None => Ok(machine::sequence(&body, scope, k)),
+        }
+    }
+
+    fn letrec(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let (bindings, body) = parse_bindings(args, syntax::LETREC_OP)?;
+
+        // All values are computed before any of them is assigned.
+        Ok(enter_recursive_scope(bindings, body, false, &scope, k))
+    }
+
+    fn letrec_star(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let (bindings, body) = parse_bindings(args, syntax::LETREC_STAR_OP)?;
+
+        Ok(enter_recursive_scope(bindings, body, true, &scope, k))
+    }
+}
+
+// Evaluates the next initial value of a 'let', or enters its body once
+// all of them are known.
+pub fn resume_let(
+    names: Vec<String>,
+    values: Vec<Expr>,
+    mut rest: Vec<Expr>,
+    body: Vec<Expr>,
+    scope: Scope,
+    k: Cont,
+) -> State {
+    match rest.pop() {
+        Some(init) => {
+            let k = k.push(Pending::Let {
+                names,
+                values,
+                rest,
+                body,
+                scope: scope.clone(),
+            });
+
+            State::Eval(init, scope, k)
+        }
+        None => {
+            let scope = Frame::new_scope(Some(&scope));
+
+            for (name, value) in names.iter().zip(values) {
+                Frame::insert(&scope, name, Binding::Value(value));
+            }
+
+            machine::sequence(&body, scope, k)
+        }
+    }
+}
+
+pub fn resume_let_star(
+    value: Expr,
+    name: String,
+    mut rest: Bindings,
+    body: Vec<Expr>,
+    scope: Scope,
+    k: Cont,
+) -> State {
+    let scope = Frame::new_scope(Some(&scope));
+    Frame::insert(&scope, &name, Binding::Value(value));
+
+    match rest.pop() {
+        Some((name, init)) => {
+            let k = k.push(Pending::LetStar {
+                name,
+                rest,
+                body,
+                scope: scope.clone(),
+            });
+
+            State::Eval(init, scope, k)
+        }
+        None => machine::sequence(&body, scope, k),
+    }
+}
+
+/**
+ * Takes the next value of a 'letrec' or 'letrec*' and evaluates the next
+ * initial expression, or the body once all of them are done.
+ *
+ * @param names Every name of the form, in order.
+ * @param values Values computed so far.
+ * @param rest Initial expressions left, in reverse.
+ * @param body Body of the form.
+ * @param star Whether each value is assigned as soon as it is computed.
+ * @param scope Frame holding the names.
+ * @param k Continuation of the form.
+ * @return Next step of the evaluator.
+ */
+pub fn resume_letrec(
+    names: Vec<String>,
+    values: Vec<Expr>,
+    mut rest: Vec<Expr>,
+    body: Vec<Expr>,
+    star: bool,
+    scope: Scope,
+    k: Cont,
+) -> State {
+    if star {
+        if let Some(value) = values.last() {
+            Frame::insert(&scope, &names[values.len() - 1], Binding::Value(value.to_owned()));
+        }
+    }
+
+    match rest.pop() {
+        Some(init) => {
+            let k = k.push(Pending::Letrec {
+                names,
+                values,
+                rest,
+                body,
+                star,
+                scope: scope.clone(),
+            });
+
+            State::Eval(init, scope, k)
+        }
+        None => {
+            if !star {
+                for (name, value) in names.iter().zip(values) {
+                    Frame::insert(&scope, name, Binding::Value(value));
+                }
+            }
+
+            machine::sequence(&body, scope, k)
+        }
+    }
+}
+
+// Binds the name of a named 'let' to a function over its variables,
+// then calls it with the initial values.
+fn named_let(name: &str, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+    let (bindings, body) = parse_bindings(args, syntax::LET_OP)?;
+    let (names, inits): (Vec<String>, Vec<Expr>) = bindings.into_iter().unzip();
+
+    let loop_scope = Frame::new_scope(Some(&scope));
+    let lambda = Expr::Atom(Box::new(Atom::Lambda(LambdaDef {
+        name: Some(name.to_string()),
+        params: Params {
+            required: names,
+            ..Default::default()
+        },
+        body: Rc::new(body),
+        scope: loop_scope.clone(),
+    })));
+
+    Frame::insert(&loop_scope, name, Binding::Value(lambda.to_owned()));
+
+    Ok(machine::next_operand(lambda, Vec::new(), reversed(inits), scope, k))
+}
+
+// Moves into a frame where every name is bound but not yet assigned.
+fn enter_recursive_scope(
+    bindings: Bindings,
+    body: Vec<Expr>,
+    star: bool,
+    scope: &Scope,
+    k: Cont,
+) -> State {
+    let scope = Frame::new_scope(Some(scope));
+    let (names, inits): (Vec<String>, Vec<Expr>) = bindings.into_iter().unzip();
+
+    for name in names.iter() {
+        Frame::insert(&scope, name, Binding::Unassigned);
+    }
+
+    // Nothing is assigned before the first value is computed.
+    let mut rest = reversed(inits);
+
+    match rest.pop() {
+        Some(init) => {
+            let k = k.push(Pending::Letrec {
+                names,
+                values: Vec::new(),
+                rest,
+                body,
+                star,
+                scope: scope.clone(),
+            });
+
+            State::Eval(init, scope, k)
+        }
+        None => machine::sequence(&body, scope, k),
+    }
+}
+
+fn reversed(exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs.into_iter().rev().collect()
+}
+
+/**
+ * Splits the arguments of a binding form into its bindings and body.
+ *
+ * @param args Arguments of the form, a list of (name init) pairs and a body.
+ * @param name Name of the form, used in error messages.
+ * @return Name and initial expression of every binding, and the body
+ *         expressions.
+ */
+fn parse_bindings(args: &[Expr], name: &str) -> Result<(Bindings, Vec<Expr>), String> {
+    if args.len() < 2 {
+        return Err(format!("Incorrect number of arguments for '{}' operator.", name));
+    }
+
+    let bindings = match &args[0] {
+        Expr::List(list) => list,
+        Expr::Atom(_) => return Err(format!("Bindings of '{}' operator must be a list.", name)),
+    };
+
+    let bindings = bindings
+        .iter()
+        .map(|binding| match binding {
+            Expr::List(pair) if pair.len() == 2 => match &pair[0] {
+                Expr::Atom(atom) => match &**atom {
+                    Atom::Symbol(s) => Ok((s.to_owned(), pair[1].to_owned())),
+                    _ => Err(format!("Invalid binding in '{}' operator.", name)),
+                },
+                Expr::List(_) => Err(format!("Invalid binding in '{}' operator.", name)),
+            },
+            _ => Err(format!("Invalid binding in '{}' operator.", name)),
+        })
+        .collect::<Result<Bindings, String>>()?;
+
+    Ok((bindings, args[1..].to_vec()))
+}
@@ -1,107 +1,410 @@
-use crate::env::{Env, Eval};
-use crate::syntax::{Atom, Expr};
+use crate::env::{self, Env, Scope};
+use crate::equality::{self, Equivalence};
+use crate::machine::{self, Cont, Pending, State};
+use crate::syntax::{self, Atom, Expr};
 
 pub trait LogicOps {
-    fn and(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn or(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn not(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn atom(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn if_op(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
+    fn and(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn or(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn not(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn atom(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_number(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_integer(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_rational(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_real(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_char(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_symbol(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_boolean(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_procedure(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_null(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_list(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_pair(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_vector(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn boolean_eq(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn if_op(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn cond(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn case(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn when(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn unless(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
 }
 
 impl LogicOps for Env {
-    // Returns true if no arguments.
-    fn and(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        let mut bool_result = true;
+    // Returns the last value, or the first false one without
+    // evaluating the rest. Returns true if no arguments.
+    fn and(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let rest = args.iter().rev().cloned().collect();
 
-        for expr in args.iter() {
-            let tree = self.simplify(&expr);
+        Ok(resume_and(Expr::Atom(Box::new(Atom::Boolean(true))), rest, scope, k))
+    }
 
-            let result = tree.and_then(|x| try_get_bool(&x));
+    // Returns the first true value without evaluating the rest.
+    // Returns false if no arguments.
+    fn or(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let rest = args.iter().rev().cloned().collect();
 
-            match result {
-                Ok(b) => {
-                    bool_result = bool_result && b;
-                }
-                Err(msg) => {
-                    return Err(msg);
-                }
-            }
+        Ok(resume_or(Expr::Atom(Box::new(Atom::Boolean(false))), rest, scope, k))
+    }
+
+    fn not(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() != 1 {
+            Err("Incorrect number of args for 'not' operator.".to_string())
+        } else {
+            Ok(Expr::Atom(Box::new(Atom::Boolean(!is_truthy(&args[0])))))
         }
+    }
 
-        Ok(Expr::Atom(Box::new(Atom::Boolean(bool_result))))
+    fn atom(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() != 1 {
+            Err("Incorrect number of args for 'atom' operator.".to_string())
+        } else {
+            let is_atom = matches!(args[0], Expr::Atom(_));
+
+            Ok(Expr::Atom(Box::new(Atom::Boolean(is_atom))))
+        }
     }
 
-    fn or(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        let mut bool_result = false;
+    // Numbers are exact decimals, so every number is also rational and real.
+    fn is_number(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::NUMBER_P_FN, |atom| matches!(atom, Atom::Number(_)))
+    }
 
-        for expr in args.iter() {
-            let tree = self.simplify(&expr);
+    fn is_integer(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::INTEGER_P_FN, |atom| {
+            matches!(atom, Atom::Number(n) if n.is_integer())
+        })
+    }
 
-            let result = tree.and_then(|x| try_get_bool(&x));
+    fn is_rational(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::RATIONAL_P_FN, |atom| matches!(atom, Atom::Number(_)))
+    }
 
-            match result {
-                Ok(b) => {
-                    bool_result = bool_result || b;
-                }
-                Err(msg) => {
-                    return Err(msg);
+    fn is_real(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::REAL_P_FN, |atom| matches!(atom, Atom::Number(_)))
+    }
+
+    fn is_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::STRING_P_FN, |atom| {
+            matches!(atom, Atom::StringLiteral(_) | Atom::MutableString(_))
+        })
+    }
+
+    fn is_char(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::CHAR_P_FN, |atom| matches!(atom, Atom::Char(_)))
+    }
+
+    fn is_symbol(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::SYMBOL_P_FN, |atom| matches!(atom, Atom::Symbol(_)))
+    }
+
+    fn is_boolean(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::BOOLEAN_P_FN, |atom| matches!(atom, Atom::Boolean(_)))
+    }
+
+    // Anything that can be called, including continuations and generators.
+    fn is_procedure(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::PROCEDURE_P_FN, |atom| {
+            matches!(
+                atom,
+                Atom::Lambda(_)
+                    | Atom::CaseLambda(_)
+                    | Atom::Primitive(_)
+                    | Atom::Continuation(_)
+                    | Atom::Generator(_)
+                    | Atom::Yield(_)
+                    | Atom::Parameter(_)
+                    | Atom::RecordProcedure(_)
+            )
+        })
+    }
+
+    fn is_null(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        list_test(args, syntax::NULL_P_FN, |list| list.is_empty())
+    }
+
+    fn is_list(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        list_test(args, syntax::LIST_P_FN, |_| true)
+    }
+
+    fn is_pair(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        list_test(args, syntax::PAIR_P_FN, |list| !list.is_empty())
+    }
+
+    // There is no vector type yet, so nothing is a vector.
+    fn is_vector(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        type_test(args, syntax::VECTOR_P_FN, |_| false)
+    }
+
+    fn boolean_eq(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() < 2 {
+            return Err("Incorrect argument count for 'boolean=?' function.".to_string());
+        }
+
+        let values = args
+            .iter()
+            .map(|x| match x {
+                Expr::Atom(atom) => match **atom {
+                    Atom::Boolean(b) => Ok(b),
+                    _ => Err("Arguments of 'boolean=?' must be booleans.".to_string()),
+                },
+                Expr::List(_) => Err("Arguments of 'boolean=?' must be booleans.".to_string()),
+            })
+            .collect::<Result<Vec<bool>, String>>()?;
+
+        let is_eq = values.iter().all(|x| *x == values[0]);
+
+        Ok(Expr::Atom(Box::new(Atom::Boolean(is_eq))))
+    }
+
+    // The alternative is optional, without it a false test gives nil.
+    fn if_op(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() == 2 || args.len() == 3 {
+            let k = k.push(Pending::If {
+                consequent: args[1].to_owned(),
+                alternative: args.get(2).cloned(),
+                scope: scope.clone(),
+            });
+
+            Ok(State::Eval(args[0].to_owned(), scope, k))
+        } else {
+            Err("Incorrect number of arguments for 'if' operator.".to_string())
+        }
+    }
+
+    fn cond(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        for (i, clause) in args.iter().enumerate() {
+            match clause {
+                Expr::List(list) if !list.is_empty() => {
+                    if env::expr_is_string(&list[0], syntax::ELSE_KW) && i != args.len() - 1 {
+                        return Err("'else' clause must be last in 'cond' operator.".to_string());
+                    }
                 }
+                _ => return Err("Invalid clause for 'cond' operator.".to_string()),
             }
         }
 
-        Ok(Expr::Atom(Box::new(Atom::Boolean(bool_result))))
+        let rest = args.iter().rev().cloned().collect();
+        next_clause(rest, scope, k)
     }
 
-    fn not(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        if args.len() != 1 {
-            Err("Incorrect number of args for 'not' operator.".to_string())
-        } else {
-            let expr = &args[0];
-            let tree = self.simplify(expr);
+    // Keys are compared with the datums with 'eqv?'.
+    fn case(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.is_empty() {
+            return Err("Incorrect number of arguments for 'case' operator.".to_string());
+        }
+
+        let k = k.push(Pending::Case {
+            clauses: args[1..].to_vec(),
+            scope: scope.clone(),
+        });
 
-            let result = tree
-                .and_then(|x| try_get_bool(&x))
-                .map(|x| Expr::Atom(Box::new(Atom::Boolean(!x))));
+        Ok(State::Eval(args[0].to_owned(), scope, k))
+    }
 
-            result
+    fn when(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() < 2 {
+            return Err("Incorrect number of arguments for 'when' operator.".to_string());
         }
+
+        let k = k.push(Pending::When {
+            body: args[1..].to_vec(),
+            negate: false,
+            scope: scope.clone(),
+        });
+
+        Ok(State::Eval(args[0].to_owned(), scope, k))
     }
 
-    fn atom(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        if args.len() != 1 {
-            Err("Incorrect number of args for 'atom' operator.".to_string())
-        } else {
-            let expr = &args[0];
-            let result = self.simplify(expr).map(|x| match x {
-                Expr::Atom(_) => Expr::Atom(Box::new(Atom::Boolean(true))),
-                Expr::List(_) => Expr::Atom(Box::new(Atom::Boolean(false))),
-            });
+    fn unless(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() < 2 {
+            return Err("Incorrect number of arguments for 'unless' operator.".to_string());
+        }
+
+        let k = k.push(Pending::When {
+            body: args[1..].to_vec(),
+            negate: true,
+            scope: scope.clone(),
+        });
+
+        Ok(State::Eval(args[0].to_owned(), scope, k))
+    }
+}
+
+pub fn resume_if(
+    test: Expr,
+    consequent: Expr,
+    alternative: Option<Expr>,
+    scope: Scope,
+    k: Cont,
+) -> State {
+    if is_truthy(&test) {
+        State::Eval(consequent, scope, k)
+    } else if let Some(alternative) = alternative {
+        State::Eval(alternative, scope, k)
+    } else {
+        State::Return(nil(), k)
+    }
+}
 
-            result
+// The last expression is evaluated in tail position.
+pub fn resume_and(value: Expr, mut rest: Vec<Expr>, scope: Scope, k: Cont) -> State {
+    if !is_truthy(&value) {
+        return State::Return(value, k);
+    }
+
+    match rest.pop() {
+        Some(next) if rest.is_empty() => State::Eval(next, scope, k),
+        Some(next) => {
+            let k = k.push(Pending::And { rest, scope: scope.clone() });
+            State::Eval(next, scope, k)
         }
+        None => State::Return(value, k),
     }
+}
 
-    fn if_op(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        if args.len() == 3 {
-            let exp0 = self.simplify(&args[0]);
+pub fn resume_or(value: Expr, mut rest: Vec<Expr>, scope: Scope, k: Cont) -> State {
+    if is_truthy(&value) {
+        return State::Return(value, k);
+    }
 
-            exp0.and_then(|x| try_get_bool(&x)).and_then(|x| match x {
-                true => self.simplify(&args[1]),
-                false => self.simplify(&args[2]),
-            })
-        } else {
-            Err("Incorrect number of arguments for 'if' operator.".to_string())
+    match rest.pop() {
+        Some(next) if rest.is_empty() => State::Eval(next, scope, k),
+        Some(next) => {
+            let k = k.push(Pending::Or { rest, scope: scope.clone() });
+            State::Eval(next, scope, k)
+        }
+        None => State::Return(value, k),
+    }
+}
+
+pub fn resume_when(test: Expr, body: &[Expr], negate: bool, scope: Scope, k: Cont) -> State {
+    if is_truthy(&test) != negate {
+        machine::sequence(body, scope, k)
+    } else {
+        State::Return(nil(), k)
+    }
+}
+
+pub fn resume_cond(
+    test: Expr,
+    body: &[Expr],
+    rest: Vec<Expr>,
+    scope: Scope,
+    k: Cont,
+) -> Result<State, String> {
+    if is_truthy(&test) {
+        clause_body(body, test, syntax::COND_OP, scope, k)
+    } else {
+        next_clause(rest, scope, k)
+    }
+}
+
+pub fn resume_case(key: Expr, clauses: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+    for (i, clause) in clauses.iter().enumerate() {
+        let clause = match clause {
+            Expr::List(list) if !list.is_empty() => list,
+            _ => return Err("Invalid clause for 'case' operator.".to_string()),
+        };
+
+        let is_match = match &clause[0] {
+            // Datums are compared with 'eqv?'.
+            Expr::List(datums) => datums
+                .iter()
+                .any(|x| equality::equivalent(x, &key, Equivalence::Eqv)),
+            datum if env::expr_is_string(datum, syntax::ELSE_KW) => {
+                if i != clauses.len() - 1 {
+                    return Err("'else' clause must be last in 'case' operator.".to_string());
+                }
+
+                true
+            }
+            _ => return Err("Invalid clause for 'case' operator.".to_string()),
+        };
+
+        if is_match {
+            return clause_body(&clause[1..], key, syntax::CASE_OP, scope, k);
         }
     }
+
+    Ok(State::Return(nil(), k))
+}
+
+// Evaluates the test of the next 'cond' clause, clauses are in reverse.
+fn next_clause(mut rest: Vec<Expr>, scope: Scope, k: Cont) -> Result<State, String> {
+    let clause = match rest.pop() {
+        Some(Expr::List(clause)) => clause,
+        _ => return Ok(State::Return(nil(), k)),
+    };
+
+    if env::expr_is_string(&clause[0], syntax::ELSE_KW) {
+        let test = Expr::Atom(Box::new(Atom::Boolean(true)));
+        return clause_body(&clause[1..], test, syntax::COND_OP, scope, k);
+    }
+
+    let k = k.push(Pending::Cond {
+        body: clause[1..].to_vec(),
+        rest,
+        scope: scope.clone(),
+    });
+
+    Ok(State::Eval(clause[0].to_owned(), scope, k))
+}
+
+// Body of a selected 'cond' or 'case' clause. An empty body gives back the
+// value that selected the clause and '=>' passes it on to a procedure.
+fn clause_body(
+    body: &[Expr],
+    value: Expr,
+    name: &str,
+    scope: Scope,
+    k: Cont,
+) -> Result<State, String> {
+    match body.first() {
+        None => Ok(State::Return(value, k)),
+        Some(first) if env::expr_is_string(first, syntax::ARROW_KW) => {
+            if body.len() != 2 {
+                return Err(format!("Invalid clause for '{}' operator.", name));
+            }
+
+            let k = k.push(Pending::ApplyTo { values: vec![value] });
+            Ok(State::Eval(body[1].to_owned(), scope, k))
+        }
+        Some(_) => Ok(machine::sequence(body, scope, k)),
+    }
+}
+
+// Applies a test to the single argument of a type predicate, lists
+// never pass it.
+fn type_test(args: &[Expr], name: &str, test: fn(&Atom) -> bool) -> Result<Expr, String> {
+    match args {
+        [Expr::Atom(atom)] => Ok(Expr::Atom(Box::new(Atom::Boolean(test(atom))))),
+        [Expr::List(_)] => Ok(Expr::Atom(Box::new(Atom::Boolean(false)))),
+        _ => Err(format!("Incorrect argument count for '{}' function.", name)),
+    }
+}
+
+// Like 'type_test', for predicates that only lists can pass.
+fn list_test(args: &[Expr], name: &str, test: fn(&[Expr]) -> bool) -> Result<Expr, String> {
+    match args {
+        [Expr::List(list)] => Ok(Expr::Atom(Box::new(Atom::Boolean(test(list))))),
+        [Expr::Atom(_)] => Ok(Expr::Atom(Box::new(Atom::Boolean(false)))),
+        _ => Err(format!("Incorrect argument count for '{}' function.", name)),
+    }
+}
+
+fn nil() -> Expr {
+    Expr::Atom(Box::new(Atom::Nil))
 }
 
-fn try_get_bool(expr: &Expr) -> Result<bool, String> {
+/**
+ * Every value except '#f' counts as true in a condition.
+ *
+ * @param expr Simplified expression used as a condition.
+ * @return False only for the boolean false literal.
+ */
+pub fn is_truthy(expr: &Expr) -> bool {
     match expr {
-        Expr::Atom(atom) => match **atom {
-            Atom::Boolean(b) => Ok(b.to_owned()),
-            _ => Err("Cannot perform operation on non-boolean value.".to_string()),
-        },
-        Expr::List(_) => Err("Cannot perform operation on non-boolean value.".to_string()),
+        Expr::Atom(atom) => !matches!(**atom, Atom::Boolean(false)),
+        Expr::List(_) => true,
     }
 }
@@ -1,137 +1,252 @@
-use crate::env::{Env, Eval};
+use crate::env::{Binding, Env, Frame, Scope};
 use crate::env;
+use crate::machine::{Cont, Pending, State};
+use crate::params::Params;
 use crate::syntax::{Atom, Expr, LambdaDef};
+use crate::string;
 use crate::syntax;
+use std::rc::Rc;
 
 pub trait EnvPrimitives {
-    fn define(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn load(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn quote(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn string(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn car(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn cdr(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn lambda(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
+    fn define(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn set(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn load(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn quote(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn string(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn car(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn cdr(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn define_star(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn lambda(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn lambda_star(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn case_lambda(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
 }
 
 impl EnvPrimitives for Env {
-    fn define(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
+    fn define(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if let Some(Expr::List(target)) = expr.first() {
+            // Procedure shorthand, (define (f x) body...) is turned into
+            // (define f (lambda (x) body...)). The name can itself be a
+            // list for curried definitions like (define ((f a) b) ...).
+            let (name, lambda) = procedure_shorthand(target, &expr[1..], syntax::FUN_OP)?;
+
+            return self.define(&[name, lambda], scope, k);
+        }
+
         if expr.len() != 2 {
             Err("Incorrect number of arguments for 'define' operator.".to_string())
         } else {
-            // Add new symbol definition to environment.
-            let current_expr = &expr[0];
-            let symbol_def = &expr[1];
-            let symbol = try_get_symbol_string(current_expr);
-
+            // Add new symbol definition to environment, the value is
+            // computed once here rather than on every reference.
+            let name = try_get_symbol_string(&expr[0])?;
+            let k = k.push(Pending::Define { name, scope: scope.clone() });
 
-            let result = symbol.map(|x| {
-                // Ignore people redefining variables as what they already are...
-                if !env::expr_is_string(&symbol_def, x.as_str()) {
-                    self.insert(x.to_owned(), Box::new(symbol_def.to_owned()));
-                }
+            Ok(State::Eval(expr[1].to_owned(), scope, k))
+        }
+    }
 
-                Expr::Atom(Box::new(Atom::Symbol(x)))
-            });
+    // Assigns to the binding in the nearest scope that has one.
+    fn set(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if expr.len() != 2 {
+            Err("Incorrect number of arguments for 'set!' operator.".to_string())
+        } else {
+            let name = try_get_symbol_string(&expr[0])?;
+            let k = k.push(Pending::Set { name, scope: scope.clone() });
 
-            result
+            Ok(State::Eval(expr[1].to_owned(), scope, k))
         }
     }
 
-    fn load(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
+    fn load(&mut self, expr: &[Expr]) -> Result<Expr, String> {
         if expr.len() != 1 {
             Err("Incorrect number of arguments for 'load' operator.".to_string())
         } else {
-            let file = &expr[0];
-            let fileName = string_or_else(self, file);
+            let file_name = string_arg(&expr[0])?;
 
-            fileName.and_then(|x| env::interpret_file(self, &trim_quotes(&x)))
+            env::interpret_file(self, &file_name)
                 .map(|x| Expr::Atom(Box::new(Atom::StringLiteral(x))))
         }
     }
 
-    fn quote(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
+    fn quote(&mut self, expr: &[Expr], _scope: Scope, k: Cont) -> Result<State, String> {
         if expr.len() != 1 {
-            return Err("Incorrect argument count for 'quote' operator.".to_string());
+            Err("Incorrect argument count for 'quote' operator.".to_string())
         } else {
-            return Ok(expr[0].to_owned());
+            Ok(State::Return(expr[0].to_owned(), k))
         }
     }
 
-    fn string(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
+    fn string(&mut self, expr: &[Expr]) -> Result<Expr, String> {
         if expr.len() != 1 {
-            return Err("Incorrect argument count for 'string' operator.".to_string());
+            Err("Incorrect argument count for 'string' operator.".to_string())
         } else {
-            let tree = self.simplify(&expr[0]);
-
-            let result = match tree {
-                Ok(v) => string_exp(&v),
-                Err(msg) => Err(msg),
-            };
-
-            return result;
+            string_exp(&expr[0])
         }
     }
 
     // Functions that don't require access to environment.
-    fn car(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
+    fn car(&mut self, expr: &[Expr]) -> Result<Expr, String> {
         if expr.len() != 1 {
-            return Err("Incorrect argument count for 'car' operator.".to_string());
+            Err("Incorrect argument count for 'car' operator.".to_string())
         } else {
-            let tree = self.simplify(&expr[0]);
-
-            let result = match tree {
-                Ok(v) => car_exp(&v),
-                Err(msg) => Err(msg),
-            };
-
-            return result;
+            car_exp(&expr[0])
         }
     }
 
-    fn cdr(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
+    fn cdr(&mut self, expr: &[Expr]) -> Result<Expr, String> {
         // If no elements in list throw error.
         // If more than one arg throw error.
         // If first arg is not list throw error.
         if expr.len() != 1 {
-            return Err("Incorrect argument count for 'cdr' operator.".to_string());
+            Err("Incorrect argument count for 'cdr' operator.".to_string())
         } else {
-            let tree = self.simplify(&expr[0]);
-            let result = match tree {
-                Ok(v) => cdr_exp(&v),
-                Err(msg) => Err(msg),
-            };
+            cdr_exp(&expr[0])
+        }
+    }
+
+    // Like 'define', but the procedure shorthand creates a 'lambda*'.
+    fn define_star(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        match expr.first() {
+            Some(Expr::List(target)) => {
+                let (name, lambda) = procedure_shorthand(target, &expr[1..], syntax::FUN_STAR_OP)?;
 
-            return result;
+                self.define(&[name, lambda], scope, k)
+            }
+            _ => self.define(expr, scope, k),
         }
     }
 
-    fn lambda(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
-        if expr.len() != 2 {
-            return Err("Incorrect argument count for 'lambda' operator.".to_string());
+    // The body can hold several expressions, the value of the last one
+    // is returned.
+    fn lambda(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if expr.len() < 2 {
+            Err("Incorrect argument count for 'lambda' operator.".to_string())
         } else {
-            let param_expr = &expr[0];
-            let params = match param_expr {
-                Expr::Atom(v) => vec![Expr::Atom(v.to_owned())],
-                Expr::List(l) => l.to_vec(),
+            let result = LambdaDef {
+                name: None,
+                params: Params::parse(&expr[0], false)?,
+                body: Rc::new(expr[1..].to_vec()),
+                scope,
             };
 
-            let body = Box::new(expr[1].to_owned());
+            Ok(State::Return(Expr::Atom(Box::new(Atom::Lambda(result))), k))
+        }
+    }
 
+    // Also takes optional parameters as (name default) and keyword
+    // parameters after '#!key'.
+    fn lambda_star(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if expr.len() < 2 {
+            Err("Incorrect argument count for 'lambda*' operator.".to_string())
+        } else {
             let result = LambdaDef {
-                params: params,
-                body: body,
+                name: None,
+                params: Params::parse(&expr[0], true)?,
+                body: Rc::new(expr[1..].to_vec()),
+                scope,
             };
 
-            Ok(Expr::Atom(Box::new(Atom::Lambda(result))))
+            Ok(State::Return(Expr::Atom(Box::new(Atom::Lambda(result))), k))
+        }
+    }
+
+    // Every clause is a parameter list followed by a body, the first one
+    // that takes the number of arguments is used for a call.
+    fn case_lambda(&mut self, expr: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if expr.is_empty() {
+            return Err("Incorrect argument count for 'case-lambda' operator.".to_string());
+        }
+
+        let clauses = expr
+            .iter()
+            .map(|clause| match clause {
+                Expr::List(list) if list.len() >= 2 => Ok(LambdaDef {
+                    name: None,
+                    params: Params::parse(&list[0], false)?,
+                    body: Rc::new(list[1..].to_vec()),
+                    scope: scope.clone(),
+                }),
+                _ => Err("Invalid clause for 'case-lambda' operator.".to_string()),
+            })
+            .collect::<Result<Vec<LambdaDef>, String>>()?;
+
+        Ok(State::Return(Expr::Atom(Box::new(Atom::CaseLambda(clauses))), k))
+    }
+}
+
+// Binds the value of a 'define' in the frame it appeared in.
+pub fn resume_define(value: Expr, name: &str, scope: &Scope, k: Cont) -> State {
+    Frame::insert(scope, name, Binding::Value(name_procedure(value, name)));
+
+    State::Return(Expr::Atom(Box::new(Atom::Symbol(name.to_string()))), k)
+}
+
+pub fn resume_set(value: Expr, name: &str, scope: &Scope, k: Cont) -> Result<State, String> {
+    Frame::assign(scope, name, Binding::Value(value))?;
+
+    Ok(State::Return(Expr::Atom(Box::new(Atom::Nil)), k))
+}
+
+/**
+ * Turns the target and body of a procedure definition into a name and the
+ * lambda expression bound to it.
+ *
+ * @param target Name and parameters, as in (f x y).
+ * @param body Body expressions of the definition.
+ * @param operator Lambda operator the procedure is created with.
+ * @return Name, possibly itself a curried target, and lambda expression.
+ */
+fn procedure_shorthand(
+    target: &[Expr],
+    body: &[Expr],
+    operator: &str,
+) -> Result<(Expr, Expr), String> {
+    if body.is_empty() {
+        return Err("Incorrect number of arguments for 'define' operator.".to_string());
+    }
+
+    let (name, params) = target
+        .split_first()
+        .ok_or_else(|| "Definition is missing a function name.".to_string())?;
+
+    let mut lambda = vec![
+        Expr::Atom(Box::new(Atom::Symbol(operator.to_string()))),
+        Expr::List(params.to_vec()),
+    ];
+    lambda.extend_from_slice(body);
+
+    Ok((name.to_owned(), Expr::List(lambda)))
+}
+
+// Gives an anonymous procedure the name it is defined as, for error messages.
+fn name_procedure(value: Expr, name: &str) -> Expr {
+    let named = |mut def: LambdaDef| {
+        if def.name.is_none() {
+            def.name = Some(name.to_string());
         }
+
+        def
+    };
+
+    match value {
+        Expr::Atom(atom) => match *atom {
+            Atom::Lambda(def) => Expr::Atom(Box::new(Atom::Lambda(named(def)))),
+            Atom::CaseLambda(clauses) => Expr::Atom(Box::new(Atom::CaseLambda(
+                clauses.into_iter().map(named).collect(),
+            ))),
+            other => Expr::Atom(Box::new(other)),
+        },
+        list => list,
     }
 }
 
 fn string_exp(expr: &Expr) -> Result<Expr, String> {
     match expr {
+        Expr::Atom(atom) if matches!(**atom, Atom::StringLiteral(_)) => Ok(expr.to_owned()),
+        Expr::Atom(atom) if matches!(**atom, Atom::MutableString(_)) => Ok(Expr::Atom(
+                Box::new(Atom::StringLiteral(string::text_of(atom).unwrap_or_default())))),
         Expr::Atom(_) => Ok(Expr::Atom(
                 Box::new(Atom::StringLiteral(syntax::print_tree(expr, &false))))),
-        Expr::List(list) => Err(String::from("'string' operator can only be applied to atomic types...")),
+        Expr::List(_) => Err(String::from("'string' operator can only be applied to atomic types...")),
     }
 }
 
@@ -164,30 +279,12 @@ fn try_get_symbol_string(expr: &Expr) -> Result<String, String> {
     }
 }
 
-fn string_or_else(env: &mut Env, expr: &Expr) -> Result<String, String>
-{
-    let maybeString = env.simplify(expr);
-
-    maybeString
-        .and_then(|x| match x {
-            Expr::Atom(a) => Ok(a),
-            _ => Err(String::from("List cannot be used in place of string..."))
-        })
-        .and_then(|x| match *x {
-            Atom::StringLiteral(s) => Ok(s),
-            _ => Err(String::from("String must be string!!!"))
-        })
-}
-
-fn trim_quotes(s: &String) -> String {
-    let mut trimmed = s.clone();
-
-    if trimmed.ends_with('\'') {
-        trimmed.pop();
-        if trimmed.starts_with('\'') {
-            trimmed.remove(0);
-        }
+fn string_arg(expr: &Expr) -> Result<String, String> {
+    match expr {
+        Expr::Atom(a) => match &**a {
+            Atom::StringLiteral(s) => Ok(s.to_owned()),
+            _ => Err(String::from("String must be string!!!")),
+        },
+        _ => Err(String::from("List cannot be used in place of string...")),
     }
-
-    trimmed
 }
@@ -0,0 +1,274 @@
+use crate::env::Env;
+use crate::exception::Handlers;
+use crate::parameter::Parameterization;
+use crate::machine::{Cont, Pending, State};
+use crate::syntax::{Atom, Expr};
+use std::fmt;
+use std::rc::Rc;
+
+pub trait ControlOps {
+    fn call_cc(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn dynamic_wind(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+}
+
+/**
+ * 'dynamic-wind' blocks that control is currently inside of, innermost
+ * first. Like continuations, the list is shared rather than copied.
+ */
+pub type Winders = Option<Rc<Winder>>;
+
+pub struct Winder {
+    before: Expr,
+    after: Expr,
+    parent: Winders,
+    depth: usize,
+}
+
+// Thunk to call on the way into or out of a 'dynamic-wind' block, along
+// with the blocks control is inside of while it runs.
+pub type WindStep = (Winders, Expr);
+
+/**
+ * Continuation captured by 'call/cc'. Calling it abandons the current
+ * continuation and returns to the point where it was captured.
+ */
+#[derive(Clone)]
+pub struct Continuation {
+    pub cont: Cont,
+    pub winders: Winders,
+    pub handlers: Handlers,
+    pub parameters: Parameterization,
+}
+
+impl fmt::Debug for Continuation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Continuation")
+    }
+}
+
+// Two continuations are only equal if they were captured at the same point.
+impl PartialEq for Continuation {
+    fn eq(&self, other: &Continuation) -> bool {
+        self.cont.ptr_eq(&other.cont) && same_winders(&self.winders, &other.winders)
+    }
+}
+
+impl Eq for Continuation {}
+
+impl ControlOps for Env {
+    fn call_cc(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        if args.len() != 1 {
+            return Err("Incorrect argument count for 'call/cc' function.".to_string());
+        }
+
+        let continuation = Continuation {
+            cont: k.clone(),
+            winders: self.winders(),
+            handlers: self.handlers(),
+            parameters: self.parameters(),
+        };
+        let value = Expr::Atom(Box::new(Atom::Continuation(continuation)));
+
+        Ok(State::Apply(args[0].to_owned(), vec![value], k))
+    }
+
+    // Calls 'before', the thunk and 'after' in turn. Leaving or entering
+    // the thunk through a continuation calls 'after' or 'before' again.
+    fn dynamic_wind(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        if args.len() != 3 {
+            return Err("Incorrect argument count for 'dynamic-wind' function.".to_string());
+        }
+
+        let mut args = args.into_iter();
+        let (before, thunk, after) = (
+            args.next().unwrap(),
+            args.next().unwrap(),
+            args.next().unwrap(),
+        );
+
+        let k = k.push(Pending::WindBefore {
+            before: before.to_owned(),
+            thunk,
+            after,
+        });
+
+        Ok(State::Apply(before, Vec::new(), k))
+    }
+}
+
+// Runs the thunk once 'before' has returned.
+pub fn enter_wind(env: &mut Env, before: Expr, thunk: Expr, after: Expr, k: Cont) -> State {
+    let parent = env.winders();
+    let depth = parent.as_ref().map_or(0, |x| x.depth) + 1;
+
+    env.set_winders(Some(Rc::new(Winder {
+        before,
+        after,
+        parent,
+        depth,
+    })));
+
+    State::Apply(thunk, Vec::new(), k.push(Pending::WindBody))
+}
+
+// Runs 'after' once the thunk has returned, then hands on its value.
+pub fn leave_wind(env: &mut Env, value: Expr, k: Cont) -> State {
+    match env.winders() {
+        Some(winder) => {
+            env.set_winders(winder.parent.clone());
+
+            State::Apply(winder.after.to_owned(), Vec::new(), k.push(Pending::Restore { value }))
+        }
+        None => State::Return(value, k),
+    }
+}
+
+/**
+ * Returns to a captured continuation, running the 'after' thunks of the
+ * blocks being left and the 'before' thunks of the blocks being entered.
+ *
+ * @param env Environment holding the current dynamic state.
+ * @param target Continuation being called.
+ * @param values Arguments of the call, the value to return.
+ * @return Next step of the evaluator.
+ */
+pub fn resume_continuation(
+    env: &mut Env,
+    target: Continuation,
+    values: Vec<Expr>,
+) -> Result<State, String> {
+    let value = match values.len() {
+        0 => Expr::Atom(Box::new(Atom::Nil)),
+        1 => values.into_iter().next().unwrap(),
+        _ => return Err("Continuation takes a single value.".to_string()),
+    };
+
+    let from = env.winders();
+    let common = common_ancestor(&from, &target.winders);
+    let mut steps: Vec<WindStep> = Vec::new();
+
+    // Leaving from the innermost block out.
+    let mut current = from;
+    while !same_winders(&current, &common) {
+        let winder = current.unwrap();
+        steps.push((winder.parent.clone(), winder.after.to_owned()));
+        current = winder.parent.clone();
+    }
+
+    // Entering from the outermost block in.
+    let mut entering: Vec<WindStep> = Vec::new();
+    let mut current = target.winders.clone();
+    while !same_winders(&current, &common) {
+        let winder = current.unwrap();
+        entering.push((winder.parent.clone(), winder.before.to_owned()));
+        current = winder.parent.clone();
+    }
+
+    steps.extend(entering.into_iter().rev());
+    steps.reverse();
+
+    Ok(rewind(env, steps, target, value))
+}
+
+/**
+ * Calls the next wind thunk on the way to a continuation, or returns the
+ * value to it once there are none left.
+ *
+ * @param env Environment holding the current dynamic state.
+ * @param steps Thunks left to call, the next one last.
+ * @param target Continuation being called.
+ * @param value Value to return to it.
+ * @return Next step of the evaluator.
+ */
+pub fn rewind(env: &mut Env, mut steps: Vec<WindStep>, target: Continuation, value: Expr) -> State {
+    match steps.pop() {
+        Some((winders, thunk)) => {
+            env.set_winders(winders);
+
+            let k = Cont::default().push(Pending::Rewind {
+                steps,
+                target,
+                value,
+            });
+
+            State::Apply(thunk, Vec::new(), k)
+        }
+        None => {
+            env.set_winders(target.winders);
+            env.set_handlers(target.handlers);
+            env.set_parameters(target.parameters);
+            State::Return(value, target.cont)
+        }
+    }
+}
+
+fn common_ancestor(a: &Winders, b: &Winders) -> Winders {
+    let depth = |w: &Winders| w.as_ref().map_or(0, |x| x.depth);
+    let parent = |w: &Winders| w.as_ref().and_then(|x| x.parent.clone());
+
+    let mut a = a.clone();
+    let mut b = b.clone();
+
+    while depth(&a) > depth(&b) {
+        a = parent(&a);
+    }
+
+    while depth(&b) > depth(&a) {
+        b = parent(&b);
+    }
+
+    while !same_winders(&a, &b) {
+        a = parent(&a);
+        b = parent(&b);
+    }
+
+    a
+}
+
+fn same_winders(a: &Winders, b: &Winders) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/**
+ * Node of a list of dynamic state that is shared rather than copied, like
+ * exception handlers or 'parameterize' bindings.
+ */
+pub trait Chained: Sized {
+    fn parent(&self) -> &Option<Rc<Self>>;
+    fn with_parent(&self, parent: Option<Rc<Self>>) -> Self;
+}
+
+/**
+ * Moves the nodes that a generator body added to a list onto the list of
+ * the caller resuming it.
+ *
+ * @param list List of the body when it yielded.
+ * @param base List of the caller the body last ran under.
+ * @param onto List of the caller resuming the body.
+ * @return Nodes of the body on top of the ones of the new caller.
+ */
+pub fn rebase<T: Chained>(
+    list: &Option<Rc<T>>,
+    base: &Option<Rc<T>>,
+    onto: Option<Rc<T>>,
+) -> Option<Rc<T>> {
+    let mut own = Vec::new();
+    let mut current = list.clone();
+
+    while let Some(node) = current {
+        if base.as_ref().is_some_and(|x| Rc::ptr_eq(x, &node)) {
+            break;
+        }
+
+        current = node.parent().clone();
+        own.push(node);
+    }
+
+    own.into_iter()
+        .rev()
+        .fold(onto, |parent, node| Some(Rc::new(node.with_parent(parent))))
+}
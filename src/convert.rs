@@ -0,0 +1,120 @@
+use crate::env::Env;
+use crate::string;
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::ToPrimitive;
+
+pub trait ConvertOps {
+    fn symbol_to_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_to_symbol(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn list_to_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_to_list(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn number_to_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+impl ConvertOps for Env {
+    fn symbol_to_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match single_atom(args, syntax::SYMBOL_TO_STRING_FN)? {
+            Atom::Symbol(s) => Ok(string_value(s.to_owned())),
+            _ => Err("Argument of 'symbol->string' must be a symbol.".to_string()),
+        }
+    }
+
+    fn string_to_symbol(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match string::text_of(single_atom(args, syntax::STRING_TO_SYMBOL_FN)?) {
+            Some(s) => Ok(Expr::Atom(Box::new(Atom::Symbol(s)))),
+            None => Err("Argument of 'string->symbol' must be a string.".to_string()),
+        }
+    }
+
+    fn list_to_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let list = match args {
+            [Expr::List(list)] => list,
+            [_] => return Err("Argument of 'list->string' must be a list.".to_string()),
+            _ => return Err("Incorrect argument count for 'list->string' function.".to_string()),
+        };
+
+        let text = list
+            .iter()
+            .map(|x| match x {
+                Expr::Atom(atom) => match **atom {
+                    Atom::Char(c) => Ok(c),
+                    _ => Err("Elements of the list given to 'list->string' must be characters."
+                        .to_string()),
+                },
+                Expr::List(_) => {
+                    Err("Elements of the list given to 'list->string' must be characters."
+                        .to_string())
+                }
+            })
+            .collect::<Result<String, String>>()?;
+
+        Ok(string_value(text))
+    }
+
+    fn string_to_list(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match string::text_of(single_atom(args, syntax::STRING_TO_LIST_FN)?) {
+            Some(s) => Ok(Expr::List(
+                s.chars().map(|c| Expr::Atom(Box::new(Atom::Char(c)))).collect(),
+            )),
+            None => Err("Argument of 'string->list' must be a string.".to_string()),
+        }
+    }
+
+    // The optional radix is 2, 8, 10 or 16, other radixes than 10 only
+    // work on integers.
+    fn number_to_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (number, radix) = match args {
+            [Expr::Atom(n)] => (&**n, 10),
+            [Expr::Atom(n), Expr::Atom(r)] => (&**n, radix_arg(r)?),
+            [_] | [_, _] => {
+                return Err("Arguments of 'number->string' must be numbers.".to_string())
+            }
+            _ => {
+                return Err("Incorrect argument count for 'number->string' function.".to_string())
+            }
+        };
+
+        let number = match number {
+            Atom::Number(n) => n,
+            _ => return Err("First argument of 'number->string' must be a number.".to_string()),
+        };
+
+        if radix == 10 {
+            return Ok(string_value(number.to_string()));
+        }
+
+        if !number.is_integer() {
+            return Err(format!(
+                "Only integers can be written in radix {} by 'number->string'.",
+                radix
+            ));
+        }
+
+        let (digits, scale) = number.with_scale(0).as_bigint_and_exponent();
+        debug_assert_eq!(scale, 0);
+
+        Ok(string_value(digits.to_str_radix(radix)))
+    }
+}
+
+fn single_atom<'a>(args: &'a [Expr], name: &str) -> Result<&'a Atom, String> {
+    match args {
+        [Expr::Atom(atom)] => Ok(atom),
+        [Expr::List(_)] => Err(format!("Argument of '{}' cannot be a list.", name)),
+        _ => Err(format!("Incorrect argument count for '{}' function.", name)),
+    }
+}
+
+fn radix_arg(atom: &Atom) -> Result<u32, String> {
+    match atom {
+        Atom::Number(n) => match n.to_u32() {
+            Some(radix) if n.is_integer() && [2, 8, 10, 16].contains(&radix) => Ok(radix),
+            _ => Err("Radix of 'number->string' must be 2, 8, 10 or 16.".to_string()),
+        },
+        _ => Err("Radix of 'number->string' must be 2, 8, 10 or 16.".to_string()),
+    }
+}
+
+fn string_value(text: String) -> Expr {
+    Expr::Atom(Box::new(Atom::StringLiteral(text)))
+}
@@ -1,224 +1,413 @@
-use crate::boolean::LogicOps;
-use crate::built_in::EnvPrimitives;
+use crate::control::Winders;
+use crate::exception::{self, Handlers};
 use crate::lex;
-use crate::math::MathOps;
+use crate::machine::{self, Cont, Pending, State};
+use crate::parameter::{Parameterization, Settings};
+use crate::params::{self, Params};
+use crate::primitive;
+use crate::random::RandomSource;
 use crate::syntax;
 use crate::syntax::{Atom, Expr, LambdaDef};
-use crate::sys::EnvSys;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::boxed::Box;
 use std::fs;
+use std::rc::Rc;
+
+pub type Scope = Rc<RefCell<Frame>>;
+
+/**
+ * Value bound to a name in a frame.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    Value(Expr),
+    // Introduced by 'letrec' but not computed yet.
+    Unassigned,
+}
+
+/**
+ * One level of lexical scope. Names that are not found in a frame are
+ * looked up in its parent, up to the global frame which has none.
+ */
+#[derive(Default)]
+pub struct Frame {
+    bindings: HashMap<String, Binding>,
+    parent: Option<Scope>,
+}
+
+impl Frame {
+    pub fn new_scope(parent: Option<&Scope>) -> Scope {
+        Rc::new(RefCell::new(Frame {
+            bindings: HashMap::new(),
+            parent: parent.cloned(),
+        }))
+    }
+
+    pub fn insert(scope: &Scope, name: &str, binding: Binding) {
+        scope.borrow_mut().bindings.insert(name.to_string(), binding);
+    }
 
-pub type Env = Box<HashMap<String, Box<Expr>>>;
+    /**
+     * Finds the innermost binding of a name.
+     *
+     * @param scope Frame to start searching from.
+     * @param name Name of the variable.
+     * @return The binding along with the frame it was found in.
+     */
+    pub fn lookup(scope: &Scope, name: &str) -> Option<(Binding, Scope)> {
+        let mut current = scope.clone();
+
+        loop {
+            let parent = {
+                let frame = current.borrow();
+
+                if let Some(binding) = frame.bindings.get(name) {
+                    return Some((binding.to_owned(), current.clone()));
+                }
+
+                frame.parent.clone()
+            };
+
+            current = parent?;
+        }
+    }
+
+    // Value of a variable as seen from a frame.
+    pub fn get(scope: &Scope, name: &str) -> Result<Expr, String> {
+        match Frame::lookup(scope, name) {
+            Some((Binding::Value(value), _)) => Ok(value),
+            Some((Binding::Unassigned, _)) => Err(format!(
+                "Symbol of name '{}' is used before it is assigned.",
+                name
+            )),
+            None => Err(format!("Symbol of name '{}' is undefined.", name)),
+        }
+    }
+
+    // Changes the innermost existing binding of a name.
+    pub fn assign(scope: &Scope, name: &str, binding: Binding) -> Result<(), String> {
+        match Frame::lookup(scope, name) {
+            Some((_, frame)) => {
+                Frame::insert(&frame, name, binding);
+                Ok(())
+            }
+            None => Err(format!("Cannot assign undefined symbol '{}'.", name)),
+        }
+    }
+}
+
+/**
+ * Interpreter session, holds the global frame, the frame that expressions
+ * are currently evaluated in and the dynamic state of the evaluator.
+ */
+#[derive(Clone)]
+pub struct Env {
+    scope: Scope,
+    global: Scope,
+    winders: Winders,
+    handlers: Handlers,
+    parameters: Parameterization,
+    settings: Settings,
+    random_source: RandomSource,
+    // Condition for the error a builtin is returning, if it is more than
+    // a message.
+    condition: Option<Expr>,
+}
+
+impl Env {
+    pub fn new() -> Env {
+        let global = Frame::new_scope(None);
+        let mut env = Env {
+            scope: global.clone(),
+            global: global.clone(),
+            winders: None,
+            handlers: None,
+            parameters: None,
+            settings: Settings::new(),
+            random_source: RandomSource::default(),
+            condition: None,
+        };
+
+        primitive::install(&mut env, &global);
+        env
+    }
+
+    pub fn scope(&self) -> Scope {
+        self.scope.clone()
+    }
+
+    pub fn global(&self) -> Scope {
+        self.global.clone()
+    }
+
+    pub fn winders(&self) -> Winders {
+        self.winders.clone()
+    }
+
+    pub fn set_winders(&mut self, winders: Winders) {
+        self.winders = winders;
+    }
+
+    pub fn handlers(&self) -> Handlers {
+        self.handlers.clone()
+    }
+
+    pub fn set_handlers(&mut self, handlers: Handlers) {
+        self.handlers = handlers;
+    }
+
+    pub fn parameters(&self) -> Parameterization {
+        self.parameters.clone()
+    }
+
+    pub fn set_parameters(&mut self, parameters: Parameterization) {
+        self.parameters = parameters;
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    // Source drawn from when no other is passed in. Copies share its state.
+    pub fn random_source(&self) -> RandomSource {
+        self.random_source.clone()
+    }
+
+    pub fn set_condition(&mut self, condition: Option<Expr>) {
+        self.condition = condition;
+    }
+
+    pub fn take_condition(&mut self) -> Option<Expr> {
+        self.condition.take()
+    }
+
+    // Simplifies an expression in another frame than the current one.
+    pub fn simplify_in(&mut self, expr: &Expr, scope: Scope) -> Result<Expr, String> {
+        machine::run(self, State::Eval(expr.to_owned(), scope, Cont::default()))
+    }
+}
+
+impl Default for Env {
+    fn default() -> Env {
+        Env::new()
+    }
+}
 
 pub trait Eval {
-    fn eval(&mut self, input: &String) -> Result<Expr, String>;
-    fn eval_list(&mut self, list: &Vec<Expr>) -> Result<Expr, String>;
-    fn eval_car_cdr(&mut self, car: Atom, cdr: &Vec<Expr>) -> Result<Expr, String>;
-    fn apply(&mut self, func: &String, args: &Vec<Expr>) -> Result<Expr, String>;
+    fn eval(&mut self, input: &str) -> Result<Expr, String>;
     fn simplify(&mut self, expr: &Expr) -> Result<Expr, String>;
-    fn get_symbol(&mut self, s: &String) -> Result<Expr, String>;
-    fn execute_lambda(&mut self, lambda_def: &LambdaDef, args: &Vec<Expr>) -> Result<Expr, String>;
 }
 
 impl Eval for Env {
-    fn eval(&mut self, input: &String) -> Result<Expr, String> {
-        let ast = lex::lexical_analysis(input)
+    fn eval(&mut self, input: &str) -> Result<Expr, String> {
+        lex::lexical_analysis(&input.to_string())
             .map(|x| lex::parse_tokens(&x))
-            .and_then(|x| self.simplify(&x));
-
-        ast
-    }
-
-    fn eval_list(&mut self, list: &Vec<Expr>) -> Result<Expr, String> {
-        let car = list
-            .first()
-            .map(|x| match x {
-                Expr::List(_) => self.simplify(x),
-                Expr::Atom(_) => Ok(x.to_owned()),
-            })
-            .and_then(|x| match x {
-                Ok(v) => Some(v),
-                Err(_) => None,
-            });
-
-        match car {
-            Some(expr) => match expr {
-                Expr::Atom(atom) => self.eval_car_cdr(*atom.to_owned(), &list[1..].to_vec()),
-                Expr::List(_) => {
-                    Result::Err("First token in list must be function name.".to_string())
-                }
-            },
-            None => Result::Err("Empty list is not a valid token.".to_string()),
-        }
+            .and_then(|x| self.simplify(&x))
     }
 
-    fn eval_car_cdr(&mut self, car: Atom, cdr: &Vec<Expr>) -> Result<Expr, String> {
-        match car {
-            Atom::Symbol(name) => self.apply(&name, cdr),
-            _ => Result::Err("First token in list must be function name.".to_string()),
+    fn simplify(&mut self, expr: &Expr) -> Result<Expr, String> {
+        let scope = self.scope();
+        self.simplify_in(expr, scope)
+    }
+}
+
+/**
+ * Value a parameter gets once the frame of a call exists.
+ */
+#[derive(Clone)]
+pub enum Argument {
+    // Argument given in the call.
+    Given(Expr),
+    // Default of a missing optional or keyword argument, evaluated in the
+    // new frame so it can refer to earlier parameters.
+    Default(Expr),
+}
+
+/**
+ * Creates the frame a function body is evaluated in and matches the
+ * arguments of a call to the parameters.
+ *
+ * Required parameters are bound right away. The others are bound by
+ * 'next_argument', since their defaults are evaluated by the machine.
+ *
+ * @param lambda_def Function being called.
+ * @param values Evaluated arguments of the call.
+ * @return New frame inside the one the function was created in, and the
+ * parameters left to bind in reverse.
+ */
+pub fn bind_arguments(
+    lambda_def: &LambdaDef,
+    values: Vec<Expr>,
+) -> Result<(Scope, Vec<(String, Argument)>), String> {
+    let params = &lambda_def.params;
+    let count = values.len();
+
+    if !params.accepts(count) {
+        return Err(format!(
+            "{} accepts {} arguments, got {}.",
+            procedure_name(lambda_def),
+            params.arity(),
+            count
+        ));
+    }
+
+    let scope = Frame::new_scope(Some(&lambda_def.scope));
+    let mut arguments: Vec<(String, Argument)> = Vec::new();
+    let mut values = values.into_iter().peekable();
+
+    for name in params.required.iter() {
+        if let Some(value) = values.next() {
+            Frame::insert(&scope, name, Binding::Value(value));
         }
     }
 
-    fn apply(&mut self, func: &String, args: &Vec<Expr>) -> Result<Expr, String> {
-        // Match functions to their name and return a function not found error
-        // if it doesn't exist in the environment or in built in functions.
-        #[allow(non_snake_case)]
-        let ERROR_MESSAGE = "Symbol cannot be used as function.";
-
-        if !self.contains_key(func) {
-            match func.as_str() {
-                syntax::EQ_OP => self.eq(args),
-                syntax::ADD_OP => self.add(args),
-                syntax::SUB_OP => self.sub(args),
-                syntax::MUL_OP => self.mul(args),
-                syntax::DIV_OP => self.div(args),
-                syntax::MOD_OP => self.modulo(args),
-                syntax::CAR_OP => self.car(args),
-                syntax::CDR_OP => self.cdr(args),
-                syntax::QT_OP => self.quote(args),
-                syntax::NOT_OP => self.not(args),
-                syntax::AND_OP => self.and(args),
-                syntax::OR_OP => self.or(args),
-                syntax::ATM_OP => self.atom(args),
-                syntax::IF_OP => self.if_op(args),
-                syntax::DEF_OP => self.define(args),
-                syntax::SLURP_FN => self.slurp(args),
-                syntax::WRITE_FILE_FN => self.write(args),
-                syntax::PRINT_FN => self.print(args),
-                syntax::PRINTLN_FN => self.println(args),
-                syntax::FUN_OP => self.lambda(args),
-                syntax::STR_OP => self.string(args),
-                syntax::LOAD_FN => self.load(args),
-                _ => Result::Err(format!("Function name '{}' not recognized.", func.as_str())),
-            }
-        } else {
-            // Probably will have to remove this once we start implementing the LAMBDA.
-            // return Result::Err("Value cannot be used as function.".to_string());
-
-            // Grab possibly lambda function from local environment.
-            let copy_env = self.clone();
-            let maybe_lambda = copy_env
-                .get(func)
-                .map(|x| self.simplify(x))
-                .unwrap_or(Err(ERROR_MESSAGE.to_string()))
-                .and_then(|x| match x {
-                    Expr::Atom(v) => Ok(v),
-                    Expr::List(_) => Err(ERROR_MESSAGE.to_string()),
-                })
-                .and_then(|x| match *x {
-                    Atom::Lambda(def) => Ok(def),
-                    _ => Err(ERROR_MESSAGE.to_string()),
-                });
+    for (name, default) in params.optional.iter() {
+        let value = match values.peek() {
+            Some(value) if params.keys.is_empty() || !is_keyword(value) => values.next(),
+            _ => None,
+        };
+
+        let argument = match value {
+            Some(value) => Argument::Given(value),
+            None => Argument::Default(default.to_owned()),
+        };
+
+        arguments.push((name.to_owned(), argument));
+    }
 
-            match maybe_lambda {
-                Ok(def) => self.execute_lambda(&def, args),
-                Err(msg) => Err(msg),
+    if !params.keys.is_empty() {
+        let mut supplied: HashMap<String, Expr> = HashMap::new();
+
+        while let Some(key) = values.next_if(is_keyword) {
+            let key = match key {
+                Expr::Atom(atom) => match *atom {
+                    Atom::Keyword(k) => k,
+                    _ => unreachable!(),
+                },
+                Expr::List(_) => unreachable!(),
+            };
+
+            if !params.keys.iter().any(|(name, _)| name == &key) {
+                return Err(format!(
+                    "{} has no keyword parameter '{}:'.",
+                    procedure_name(lambda_def),
+                    key
+                ));
             }
+
+            let value = values
+                .next()
+                .ok_or_else(|| format!("Keyword '{}:' is missing a value.", key))?;
+
+            supplied.insert(key, value);
+        }
+
+        for (name, default) in params.keys.iter() {
+            let argument = match supplied.remove(name) {
+                Some(value) => Argument::Given(value),
+                None => Argument::Default(default.to_owned()),
+            };
+
+            arguments.push((name.to_owned(), argument));
         }
     }
 
-    fn simplify(&mut self, expr: &Expr) -> Result<Expr, String> {
-        match expr {
-            Expr::List(list) => {
-                self.eval_list(list)
-            },
-            // Don't worry about atoms right now.
-            Expr::Atom(atom) => match &**atom {
-                Atom::Symbol(s) => {
-                    let result = self.get_symbol(&s);
-                    result
-                },
-                _ => {
-                    Ok(Expr::Atom(atom.to_owned()))
-                },
-            },
+    let rest: Vec<Expr> = values.collect();
+
+    match &params.rest {
+        Some(name) => arguments.push((name.to_owned(), Argument::Given(Expr::List(rest)))),
+        None if !rest.is_empty() => {
+            return Err(format!(
+                "{} accepts {} arguments, got {}.",
+                procedure_name(lambda_def),
+                params.arity(),
+                count
+            ))
         }
+        None => {}
     }
 
-    fn get_symbol(&mut self, s: &String) -> Result<Expr, String> {
-        let result = self
-            .to_owned()
-            .get(s)
-            .map(|x| {
-                // dbg!(&x);
-                self.simplify(&x)
-            })
-            .unwrap_or(Err(
-                format!("Symbol of name '{}' is undefined.", s)
-            ));
-
-        result
-    }
-
-    fn execute_lambda(
-        &mut self,
-        lambda_def: &LambdaDef,
-        args: &Vec<Expr>) -> Result<Expr, String> {
-        // Create local session for evaluating a function.
-        // let mut local_env = self.clone();
-
-        let body = &*lambda_def.body;
-        let params = &lambda_def.params;
-        let mut shadowed_vars: Box<HashMap<String, Box<Expr>>>
-            = Box::new(HashMap::new());
-
-        if params.len() != args.len() {
-            Err("Incorrect argument count for function call.".to_string())
-        } else {
-            // Take both vectors of args and params and combine them
-            // in an ordered fashion.
-            // Then after that insert each one into the local environment.
-            for it in args.iter().zip(params.iter()) {
-                // Deconstruct into separate parts.
-                let (arg, name_expr) = it;
-
-                let name = Result::Ok(name_expr)
-                    .and_then(|x| match &*x {
-                        Expr::Atom(v) => Ok(v),
-                        Expr::List(_) => Err("List cannot be parameter name.".to_string()),
-                    })
-                    .and_then(|x| match &**x {
-                        Atom::Symbol(s) => Ok(s),
-                        _ => Err("Parameter name must be valid symbol.".to_string()),
-                    });
-
-                match name {
-                    Ok(name) => {
-                        if !expr_is_string(arg, name.as_str()) {
-                            let evaluated = self.simplify(&arg);
-
-                            if let Ok(arg_value) = evaluated {
-                                let old = self.insert(
-                                    name.to_owned(),
-                                    Box::new(arg_value.to_owned()));
-
-                                if let Some(old) = old {
-                                    shadowed_vars.insert(name.to_owned(), old);
-                                }
-                            } else {
-                                return Err(evaluated.err().unwrap())
-                            }
-                        }
-                    },
-                    Err(msg) => {
-                        return Err(msg);
-                    }
-                }
-            }
+    arguments.reverse();
+    Ok((scope, arguments))
+}
 
-            // dbg!("SIMPLIFY LAMBDA");
-            let result = self.simplify(&body);
+/**
+ * Binds the parameters of a call in order, evaluating the default of each
+ * one that wasn't given, then evaluates the body.
+ *
+ * @param scope Frame of the call.
+ * @param arguments Parameters left to bind, in reverse.
+ * @param body Body of the function.
+ * @param k Continuation of the call.
+ * @return Next step of the evaluator.
+ */
+pub fn next_argument(
+    scope: Scope,
+    mut arguments: Vec<(String, Argument)>,
+    body: Rc<Vec<Expr>>,
+    k: Cont,
+) -> State {
+    while let Some((name, argument)) = arguments.pop() {
+        match argument {
+            Argument::Given(value) => Frame::insert(&scope, &name, Binding::Value(value)),
+            Argument::Default(default) => {
+                let k = k.push(Pending::Argument {
+                    name,
+                    rest: arguments,
+                    body,
+                    scope: scope.clone(),
+                });
 
-            for (name, value) in shadowed_vars.iter() {
-                self.insert(name.to_owned(), value.to_owned());
+                return State::Eval(default, scope, k);
             }
+        }
+    }
+
+    machine::sequence(&body, scope, k)
+}
 
-            result
+// Picks the first clause of a 'case-lambda' that takes this many arguments.
+pub fn select_clause(clauses: &[LambdaDef], count: usize) -> Result<LambdaDef, String> {
+    match clauses.iter().find(|x| x.params.accepts(count)) {
+        Some(clause) => Ok(clause.to_owned()),
+        None => {
+            let params: Vec<&Params> = clauses.iter().map(|x| &x.params).collect();
+
+            Err(format!(
+                "{} accepts {} arguments, got {}.",
+                clauses
+                    .first()
+                    .map(procedure_name)
+                    .unwrap_or_else(|| "Procedure".to_string()),
+                params::arities(&params),
+                count
+            ))
         }
     }
 }
 
+// Describes a function for error messages.
+pub fn procedure_name(lambda_def: &LambdaDef) -> String {
+    match &lambda_def.name {
+        Some(name) => format!("Procedure '{}'", name),
+        None => "Anonymous procedure".to_string(),
+    }
+}
+
+pub fn not_procedure(value: &Expr) -> String {
+    format!(
+        "Value '{}' is not a procedure and cannot be called.",
+        syntax::print_tree(value, &false)
+    )
+}
+
+fn is_keyword(expr: &Expr) -> bool {
+    match expr {
+        Expr::Atom(atom) => matches!(**atom, Atom::Keyword(_)),
+        Expr::List(_) => false,
+    }
+}
+
 pub fn expr_is_string(expr: &Expr, string: &str) -> bool {
     match expr {
         Expr::Atom(atom) => match &**atom {
@@ -229,52 +418,30 @@ pub fn expr_is_string(expr: &Expr, string: &str) -> bool {
     }
 }
 
-pub fn interpret_file(env: &mut Env, path: &String) -> Result<String, String> {
-    let text = fs::read_to_string(path.as_str())
-        .unwrap()
-        .parse()
-        .unwrap();
+pub fn interpret_file(env: &mut Env, path: &str) -> Result<String, String> {
+    let text = fs::read_to_string(path).map_err(|_| {
+        exception::file_error(env, format!("'load' could not read file '{}'.", path))
+    })?;
 
     interpret_raw_text(env, &text)
 }
 
-pub fn interpret_raw_text(env: &mut Env, text: &String) -> Result<String, String> {
-    let mut failed = false;
-
-    let lines = lex::chunk_file(&text);
-
-    for line in lines {
-        let interpreter_failed = interpret_line(&line, env);
-        if interpreter_failed {
-            failed = true;
-            break;
-        }
+// Evaluates every expression of a text, stopping at the first error.
+pub fn interpret_raw_text(env: &mut Env, text: &str) -> Result<String, String> {
+    for line in lex::chunk_file(text) {
+        interpret_line(&line, env)?;
     }
 
-    // failed
-
     Ok(String::from("hello"))
 }
 
-fn interpret_line(input: &String, env: &mut Env) -> bool {
-    let mut failed = false;
-
-    if input.len() > 0 {
-        let first_char = input.chars().next().unwrap();
-
-        if first_char != ';' {
-            let result = env.eval(input);
-
-            match result {
-                Ok(expr) => {
-                    failed = false;
-                }
-                Err(msg) => {
-                    failed = true;
-                }
-            };
-        }
+fn interpret_line(input: &str, env: &mut Env) -> Result<(), String> {
+    if input.is_empty() || input.starts_with(';') {
+        return Ok(());
     }
 
-    failed
+    let tokens = lex::lexical_analysis(&input.to_string())
+        .map_err(|msg| exception::read_error(env, msg))?;
+
+    env.simplify(&lex::parse_tokens(&tokens)).map(|_| ())
 }
@@ -0,0 +1,120 @@
+use crate::env::{Env, Frame, Scope};
+use crate::machine::{Cont, State};
+use crate::primitive;
+use crate::syntax::{Atom, Expr};
+use std::fmt;
+use std::rc::Rc;
+
+pub trait EnvironmentOps {
+    fn apply_op(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn eval_op(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn interaction_environment(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn environment(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn make_empty_environment(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+/**
+ * Environment as a value that Scheme code can hold and pass to 'eval'.
+ *
+ * It is the frame expressions are evaluated in, top level definitions made
+ * through 'eval' are added to it.
+ */
+#[derive(Clone)]
+pub struct Environment {
+    pub scope: Scope,
+}
+
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Environment")
+    }
+}
+
+// Two environments are only equal if they are the same frame.
+impl PartialEq for Environment {
+    fn eq(&self, other: &Environment) -> bool {
+        Rc::ptr_eq(&self.scope, &other.scope)
+    }
+}
+
+impl Eq for Environment {}
+
+impl EnvironmentOps for Env {
+    // The last argument is a list that is spread out after the others.
+    fn apply_op(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        if args.len() < 2 {
+            return Err("Incorrect argument count for 'apply' function.".to_string());
+        }
+
+        let last = args.pop().unwrap();
+        let mut values = args.split_off(1);
+
+        match last {
+            Expr::List(list) => values.extend(list),
+            Expr::Atom(_) => {
+                return Err("The last argument of 'apply' must be a list.".to_string())
+            }
+        }
+
+        Ok(State::Apply(args.pop().unwrap(), values, k))
+    }
+
+    // Without an environment the expression is evaluated at top level.
+    fn eval_op(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let scope = match args.as_slice() {
+            [_] => self.global(),
+            [_, env] => environment_arg(env)?,
+            _ => return Err("Incorrect argument count for 'eval' function.".to_string()),
+        };
+
+        Ok(State::Eval(args[0].to_owned(), scope, k))
+    }
+
+    fn interaction_environment(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if !args.is_empty() {
+            return Err(
+                "Incorrect argument count for 'interaction-environment' function.".to_string(),
+            );
+        }
+
+        Ok(environment_value(self.global()))
+    }
+
+    // Every import set gives the builtin procedures, without any of the
+    // definitions made so far.
+    fn environment(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.iter().any(|x| matches!(x, Expr::Atom(_))) {
+            return Err("Import sets of 'environment' must be lists.".to_string());
+        }
+
+        let scope = Frame::new_scope(None);
+        primitive::install(self, &scope);
+
+        Ok(environment_value(scope))
+    }
+
+    // Only special forms are available in an empty environment.
+    fn make_empty_environment(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if !args.is_empty() {
+            return Err(
+                "Incorrect argument count for 'make-empty-environment' function.".to_string(),
+            );
+        }
+
+        Ok(environment_value(Frame::new_scope(None)))
+    }
+}
+
+fn environment_value(scope: Scope) -> Expr {
+    Expr::Atom(Box::new(Atom::Environment(Environment { scope })))
+}
+
+fn environment_arg(expr: &Expr) -> Result<Scope, String> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Environment(env) => Ok(env.scope.clone()),
+            _ => Err("Value is not an environment.".to_string()),
+        },
+        Expr::List(_) => Err("Value is not an environment.".to_string()),
+    }
+}
@@ -0,0 +1,285 @@
+use crate::boolean;
+use crate::env::Env;
+use crate::machine::{Cont, Pending, State};
+use crate::record::Record;
+use crate::string;
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::BigDecimal;
+
+pub trait EqualityOps {
+    fn is_eq(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_eqv(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_equal(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn memq(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn memv(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn member(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn assq(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn assv(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn assoc(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+}
+
+/**
+ * How two values are compared.
+ *
+ * Objects that can change or be called, like records and procedures, are
+ * only the same as themselves. Lists and strings are copied rather than
+ * shared, so they have no identity to compare and are compared by what they
+ * hold under every equivalence, which keeps a value the same as itself.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Equivalence {
+    // Numbers must also be written with the same digits, so 2 and 2.0
+    // differ.
+    Eq,
+    // Numbers are compared by value, but one written with decimals is
+    // inexact and differs from an exact one, so 2 and 2.0 still differ.
+    Eqv,
+    // Records of the same type are also compared field by field, and
+    // numbers like with 'eqv?'.
+    Equal,
+}
+
+impl EqualityOps for Env {
+    fn is_eq(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_pair(args, Equivalence::Eq, syntax::EQ_P_FN)
+    }
+
+    fn is_eqv(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_pair(args, Equivalence::Eqv, syntax::EQV_P_FN)
+    }
+
+    fn is_equal(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_pair(args, Equivalence::Equal, syntax::EQUAL_P_FN)
+    }
+
+    fn memq(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (item, list) = item_and_list(args, syntax::MEMQ_FN)?;
+
+        Ok(found(item, list, Equivalence::Eq, false))
+    }
+
+    fn memv(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (item, list) = item_and_list(args, syntax::MEMV_FN)?;
+
+        Ok(found(item, list, Equivalence::Eqv, false))
+    }
+
+    // Compares with 'equal?', or with the procedure given after the list.
+    fn member(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        search(args, false, syntax::MEMBER_FN, k)
+    }
+
+    fn assq(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (key, list) = item_and_list(args, syntax::ASSQ_FN)?;
+        check_entries(list, syntax::ASSQ_FN)?;
+
+        Ok(found(key, list, Equivalence::Eq, true))
+    }
+
+    fn assv(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (key, list) = item_and_list(args, syntax::ASSV_FN)?;
+        check_entries(list, syntax::ASSV_FN)?;
+
+        Ok(found(key, list, Equivalence::Eqv, true))
+    }
+
+    fn assoc(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        search(args, true, syntax::ASSOC_FN, k)
+    }
+}
+
+/**
+ * Compares two values.
+ *
+ * @param a First value.
+ * @param b Second value.
+ * @param how Which equivalence to use.
+ * @return Whether the values are equivalent.
+ */
+pub fn equivalent(a: &Expr, b: &Expr, how: Equivalence) -> bool {
+    compare(a, b, how, &mut Vec::new())
+}
+
+// Pairs of records being compared further up are assumed equal, which
+// makes the comparison of cyclic records stop.
+fn compare(a: &Expr, b: &Expr, how: Equivalence, visiting: &mut Vec<(Record, Record)>) -> bool {
+    match (a, b) {
+        (Expr::List(a), Expr::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| compare(x, y, how, visiting))
+        }
+        (Expr::Atom(a), Expr::Atom(b)) => match (&**a, &**b) {
+            (Atom::Number(x), Atom::Number(y)) if how == Equivalence::Eq => {
+                x.as_bigint_and_exponent() == y.as_bigint_and_exponent()
+            }
+            (Atom::Number(x), Atom::Number(y)) => x == y && is_inexact(x) == is_inexact(y),
+            (Atom::Record(x), Atom::Record(y)) if how == Equivalence::Equal && x != y => {
+                if visiting.iter().any(|(p, q)| p == x && q == y) {
+                    return true;
+                }
+
+                visiting.push((x.clone(), y.clone()));
+                let equal = x.fields_equal(y, |p, q| compare(p, q, how, visiting));
+                visiting.pop();
+
+                equal
+            }
+            // Strings are compared by their text, whether they can be
+            // changed or not.
+            (Atom::MutableString(_), _) | (_, Atom::MutableString(_))
+                if how == Equivalence::Equal =>
+            {
+                let (x, y) = (string::text_of(a), string::text_of(b));
+
+                x.is_some() && x == y
+            }
+            (Atom::Values(x), Atom::Values(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(p, q)| compare(p, q, how, visiting))
+            }
+            (x, y) => x == y,
+        },
+        _ => false,
+    }
+}
+
+// Numbers written with decimals, or computed from ones that were, are
+// inexact.
+fn is_inexact(n: &BigDecimal) -> bool {
+    n.as_bigint_and_exponent().1 > 0
+}
+
+/**
+ * Finds the first element of a list that matches an item, for the
+ * 'member' and 'assoc' families.
+ *
+ * @param item Value searched for.
+ * @param list Elements to search.
+ * @param how Which equivalence to use.
+ * @param assoc Whether the key of each entry is compared, not the entry.
+ * @return The rest of the list from the match, or the matching entry, or
+ * false if there is none.
+ */
+fn found(item: &Expr, list: &[Expr], how: Equivalence, assoc: bool) -> Expr {
+    for (i, element) in list.iter().enumerate() {
+        let is_match = match element {
+            Expr::List(entry) if assoc => entry.first().is_some_and(|x| equivalent(item, x, how)),
+            _ => !assoc && equivalent(item, element, how),
+        };
+
+        if is_match {
+            return match_value(list, i, assoc);
+        }
+    }
+
+    Expr::Atom(Box::new(Atom::Boolean(false)))
+}
+
+fn match_value(list: &[Expr], index: usize, assoc: bool) -> Expr {
+    if assoc {
+        list[index].to_owned()
+    } else {
+        Expr::List(list[index..].to_vec())
+    }
+}
+
+// 'member' or 'assoc', with an optional procedure to compare with.
+fn search(args: Vec<Expr>, assoc: bool, name: &str, k: Cont) -> Result<State, String> {
+    let (item, list) = item_and_list(&args[..args.len().min(2)], name)?;
+
+    if assoc {
+        check_entries(list, name)?;
+    }
+
+    match args.get(2) {
+        None => Ok(State::Return(found(item, list, Equivalence::Equal, assoc), k)),
+        Some(compare) if args.len() == 3 => Ok(next_candidate(
+            item.to_owned(),
+            list.to_vec(),
+            0,
+            compare.to_owned(),
+            assoc,
+            k,
+        )),
+        Some(_) => Err(format!("Incorrect argument count for '{}' function.", name)),
+    }
+}
+
+/**
+ * Calls the procedure of 'member' or 'assoc' on the next element, or
+ * returns false once there are none left.
+ *
+ * @param item Value searched for.
+ * @param list Elements to search.
+ * @param index Position of the next element.
+ * @param compare Procedure that takes the item and an element or key.
+ * @param assoc Whether the key of each entry is compared, not the entry.
+ * @param k Continuation of the search.
+ * @return Next step of the evaluator.
+ */
+pub fn next_candidate(
+    item: Expr,
+    list: Vec<Expr>,
+    index: usize,
+    compare: Expr,
+    assoc: bool,
+    k: Cont,
+) -> State {
+    let candidate = match list.get(index) {
+        Some(Expr::List(entry)) if assoc => match entry.first() {
+            Some(key) => key.to_owned(),
+            None => return State::Return(Expr::Atom(Box::new(Atom::Boolean(false))), k),
+        },
+        Some(element) => element.to_owned(),
+        None => return State::Return(Expr::Atom(Box::new(Atom::Boolean(false))), k),
+    };
+
+    let k = k.push(Pending::Member {
+        item: item.to_owned(),
+        list,
+        index,
+        compare: compare.to_owned(),
+        assoc,
+    });
+
+    State::Apply(compare, vec![item, candidate], k)
+}
+
+// Result of the procedure of 'member' or 'assoc' for one element.
+pub fn resume_member(
+    value: Expr,
+    item: Expr,
+    list: Vec<Expr>,
+    index: usize,
+    compare: Expr,
+    assoc: bool,
+    k: Cont,
+) -> State {
+    if boolean::is_truthy(&value) {
+        return State::Return(match_value(&list, index, assoc), k);
+    }
+
+    next_candidate(item, list, index + 1, compare, assoc, k)
+}
+
+fn compare_pair(args: &[Expr], how: Equivalence, name: &str) -> Result<Expr, String> {
+    match args {
+        [a, b] => Ok(Expr::Atom(Box::new(Atom::Boolean(equivalent(a, b, how))))),
+        _ => Err(format!("Incorrect argument count for '{}' function.", name)),
+    }
+}
+
+fn item_and_list<'a>(args: &'a [Expr], name: &str) -> Result<(&'a Expr, &'a [Expr]), String> {
+    match args {
+        [item, Expr::List(list)] => Ok((item, list)),
+        [_, _] => Err(format!("The second argument of '{}' must be a list.", name)),
+        _ => Err(format!("Incorrect argument count for '{}' function.", name)),
+    }
+}
+
+// Entries of an association list are lists that start with their key.
+fn check_entries(list: &[Expr], name: &str) -> Result<(), String> {
+    if list.iter().all(|x| matches!(x, Expr::List(entry) if !entry.is_empty())) {
+        Ok(())
+    } else {
+        Err(format!("Every entry of the list given to '{}' must be a non-empty list.", name))
+    }
+}
@@ -0,0 +1,412 @@
+use crate::boolean::LogicOps;
+use crate::control::{self, Chained, Continuation, Winders};
+use crate::env::{self, Binding, Env, Frame, Scope};
+use crate::machine::{self, Cont, Pending, State};
+use crate::parameter::Parameterization;
+use crate::syntax::{self, Atom, Expr};
+use std::rc::Rc;
+
+pub trait ExceptionOps {
+    fn raise(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn raise_continuable(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn error(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn with_exception_handler(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn guard(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn is_error_object(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn error_object_message(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn error_object_irritants(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_file_error(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_read_error(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+/**
+ * Condition made by 'error', or by the interpreter when an expression
+ * can't be evaluated.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorObject {
+    pub message: String,
+    pub irritants: Vec<Expr>,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    General,
+    // A file could not be opened or read.
+    File,
+    // Source text could not be parsed.
+    Read,
+}
+
+/**
+ * Exception handlers currently installed, innermost first. Like the
+ * 'dynamic-wind' blocks, the list is shared rather than copied.
+ */
+pub type Handlers = Option<Rc<Handler>>;
+
+pub struct Handler {
+    kind: HandlerKind,
+    parent: Handlers,
+}
+
+impl Chained for Handler {
+    fn parent(&self) -> &Handlers {
+        &self.parent
+    }
+
+    fn with_parent(&self, parent: Handlers) -> Self {
+        Handler {
+            kind: self.kind.clone(),
+            parent,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum HandlerKind {
+    // Procedure installed by 'with-exception-handler'.
+    Procedure(Expr),
+    // Clauses of a 'guard', evaluated after returning to it.
+    Guard {
+        var: String,
+        clauses: Vec<Expr>,
+        scope: Scope,
+        k: Cont,
+        winders: Winders,
+        parameters: Parameterization,
+    },
+}
+
+impl ExceptionOps for Env {
+    fn raise(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let condition = single_arg(args, syntax::RAISE_FN)?;
+
+        raise(self, condition, false, k)
+    }
+
+    // The value of the handler is returned from 'raise-continuable'.
+    fn raise_continuable(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let condition = single_arg(args, syntax::RAISE_CONTINUABLE_FN)?;
+
+        raise(self, condition, true, k)
+    }
+
+    fn error(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        if args.is_empty() {
+            return Err("Incorrect argument count for 'error' function.".to_string());
+        }
+
+        let irritants = args.split_off(1);
+        let message = match args.pop() {
+            Some(Expr::Atom(atom)) => match *atom {
+                Atom::StringLiteral(s) => s,
+                _ => return Err("The message of 'error' must be a string.".to_string()),
+            },
+            _ => return Err("The message of 'error' must be a string.".to_string()),
+        };
+
+        let condition = error_object(message, irritants, ErrorKind::General);
+        raise(self, condition, false, k)
+    }
+
+    // The handler is called for conditions raised while the thunk runs.
+    fn with_exception_handler(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        if args.len() != 2 {
+            return Err(
+                "Incorrect argument count for 'with-exception-handler' function.".to_string(),
+            );
+        }
+
+        let thunk = args.pop().unwrap();
+        let handler = args.pop().unwrap();
+        let outer = self.handlers();
+
+        self.set_handlers(Some(Rc::new(Handler {
+            kind: HandlerKind::Procedure(handler),
+            parent: outer.clone(),
+        })));
+
+        Ok(State::Apply(thunk, Vec::new(), k.push(Pending::RestoreHandlers { handlers: outer })))
+    }
+
+    // (guard (var clause...) body...), the clauses are those of 'cond'.
+    // A condition no clause accepts is raised again.
+    fn guard(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let (var, clauses) = match args.first() {
+            Some(Expr::List(spec)) if !spec.is_empty() => match &spec[0] {
+                Expr::Atom(atom) => match &**atom {
+                    Atom::Symbol(s) => (s.to_owned(), spec[1..].to_vec()),
+                    _ => return Err("Invalid clause for 'guard' operator.".to_string()),
+                },
+                Expr::List(_) => return Err("Invalid clause for 'guard' operator.".to_string()),
+            },
+            _ => return Err("Incorrect number of arguments for 'guard' operator.".to_string()),
+        };
+
+        if clauses.iter().any(|x| !matches!(x, Expr::List(list) if !list.is_empty())) {
+            return Err("Invalid clause for 'guard' operator.".to_string());
+        }
+
+        let outer = self.handlers();
+
+        self.set_handlers(Some(Rc::new(Handler {
+            kind: HandlerKind::Guard {
+                var,
+                clauses,
+                scope: scope.clone(),
+                k: k.clone(),
+                winders: self.winders(),
+                parameters: self.parameters(),
+            },
+            parent: outer.clone(),
+        })));
+
+        let body_scope = Frame::new_scope(Some(&scope));
+        let k = k.push(Pending::RestoreHandlers { handlers: outer });
+
+        Ok(machine::sequence(&args[1..], body_scope, k))
+    }
+
+    fn is_error_object(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let is_error = error_arg(args, syntax::ERROR_OBJECT_P_FN)?.is_some();
+
+        Ok(Expr::Atom(Box::new(Atom::Boolean(is_error))))
+    }
+
+    fn error_object_message(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match error_arg(args, syntax::ERROR_MESSAGE_FN)? {
+            Some(error) => Ok(Expr::Atom(Box::new(Atom::StringLiteral(error.message.to_owned())))),
+            None => Err("Value is not an error object.".to_string()),
+        }
+    }
+
+    fn error_object_irritants(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match error_arg(args, syntax::ERROR_IRRITANTS_FN)? {
+            Some(error) => Ok(Expr::List(error.irritants.to_owned())),
+            None => Err("Value is not an error object.".to_string()),
+        }
+    }
+
+    fn is_file_error(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let error = error_arg(args, syntax::FILE_ERROR_P_FN)?;
+        let is_file = matches!(error, Some(e) if e.kind == ErrorKind::File);
+
+        Ok(Expr::Atom(Box::new(Atom::Boolean(is_file))))
+    }
+
+    fn is_read_error(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let error = error_arg(args, syntax::READ_ERROR_P_FN)?;
+        let is_read = matches!(error, Some(e) if e.kind == ErrorKind::Read);
+
+        Ok(Expr::Atom(Box::new(Atom::Boolean(is_read))))
+    }
+}
+
+/**
+ * Hands a condition to the innermost exception handler. The handler runs
+ * with the handlers outside of it installed.
+ *
+ * @param env Environment holding the current dynamic state.
+ * @param condition Value being raised.
+ * @param continuable Whether the value of the handler is returned to 'k'.
+ * @param k Continuation of the raise.
+ * @return Next step of the evaluator, or the error message if no handler
+ * is installed.
+ */
+pub fn raise(env: &mut Env, condition: Expr, continuable: bool, k: Cont) -> Result<State, String> {
+    let handler = match env.handlers() {
+        Some(handler) => handler,
+        None => {
+            let msg = uncaught_message(&condition);
+            env.set_condition(Some(condition));
+
+            return Err(msg);
+        }
+    };
+
+    env.set_handlers(handler.parent.clone());
+
+    let k = k.push(Pending::Handled {
+        handlers: Some(handler.clone()),
+        continuable,
+    });
+
+    match &handler.kind {
+        HandlerKind::Procedure(procedure) => {
+            Ok(State::Apply(procedure.to_owned(), vec![condition], k))
+        }
+        HandlerKind::Guard {
+            var,
+            clauses,
+            scope,
+            k: guard_k,
+            winders,
+            parameters,
+        } => {
+            // Raising again happens where the condition was first raised.
+            let reraise = Continuation {
+                cont: k.push(Pending::Reraise),
+                winders: env.winders(),
+                handlers: env.handlers(),
+                parameters: env.parameters(),
+            };
+
+            let target = Continuation {
+                cont: guard_k.push(Pending::GuardClauses {
+                    var: var.to_owned(),
+                    clauses: clauses.to_owned(),
+                    scope: scope.clone(),
+                    reraise,
+                }),
+                winders: winders.clone(),
+                handlers: handler.parent.clone(),
+                parameters: parameters.clone(),
+            };
+
+            control::resume_continuation(env, target, vec![condition])
+        }
+    }
+}
+
+/**
+ * Turns an error of the interpreter into a condition for the innermost
+ * exception handler. Errors are only caught this way when a handler is
+ * installed, otherwise the message is kept as it is.
+ *
+ * @param env Environment holding the current dynamic state.
+ * @param msg Message of the error.
+ * @return Next step of the evaluator, or the message when uncaught.
+ */
+pub fn recover(env: &mut Env, msg: String) -> Result<State, String> {
+    if env.handlers().is_none() {
+        return Err(msg);
+    }
+
+    let condition = env
+        .take_condition()
+        .unwrap_or_else(|| error_object(msg, Vec::new(), ErrorKind::General));
+
+    raise(env, condition, false, Cont::default())
+}
+
+// Value of a handler called by 'raise' or 'raise-continuable'.
+pub fn resume_handled(
+    env: &mut Env,
+    value: Expr,
+    handlers: Handlers,
+    continuable: bool,
+    k: Cont,
+) -> Result<State, String> {
+    if !continuable {
+        return Err("Exception handler returned from non-continuable 'raise'.".to_string());
+    }
+
+    env.set_handlers(handlers);
+    Ok(State::Return(value, k))
+}
+
+/**
+ * Evaluates the clauses of a 'guard' once control is back at it.
+ *
+ * @param env Environment the clauses are evaluated in.
+ * @param condition Value that was raised.
+ * @param var Name the condition is bound to.
+ * @param clauses Clauses of the 'guard'.
+ * @param scope Frame the 'guard' was evaluated in.
+ * @param reraise Continuation that raises the condition again.
+ * @param k Continuation of the 'guard'.
+ * @return Next step of the evaluator.
+ */
+pub fn resume_guard(
+    env: &mut Env,
+    condition: Expr,
+    var: &str,
+    mut clauses: Vec<Expr>,
+    scope: &Scope,
+    reraise: Continuation,
+    k: Cont,
+) -> Result<State, String> {
+    let scope = Frame::new_scope(Some(scope));
+    Frame::insert(&scope, var, Binding::Value(condition));
+
+    let has_else = match clauses.last() {
+        Some(Expr::List(list)) => env::expr_is_string(&list[0], syntax::ELSE_KW),
+        _ => false,
+    };
+
+    if !has_else {
+        let reraise = Expr::Atom(Box::new(Atom::Continuation(reraise)));
+        let var = Expr::Atom(Box::new(Atom::Symbol(var.to_string())));
+        let else_kw = Expr::Atom(Box::new(Atom::Symbol(syntax::ELSE_KW.to_string())));
+
+        clauses.push(Expr::List(vec![else_kw, Expr::List(vec![reraise, var])]));
+    }
+
+    env.cond(&clauses, scope, k)
+}
+
+pub fn error_object(message: String, irritants: Vec<Expr>, kind: ErrorKind) -> Expr {
+    Expr::Atom(Box::new(Atom::ErrorObject(ErrorObject {
+        message,
+        irritants,
+        kind,
+    })))
+}
+
+/**
+ * Marks the error a builtin is about to return as a file error.
+ *
+ * @param env Environment the error is raised in.
+ * @param message Message of the error.
+ * @return The message, to be returned as the error.
+ */
+pub fn file_error(env: &mut Env, message: String) -> String {
+    env.set_condition(Some(error_object(message.to_owned(), Vec::new(), ErrorKind::File)));
+    message
+}
+
+// Like 'file_error', for text that could not be parsed.
+pub fn read_error(env: &mut Env, message: String) -> String {
+    env.set_condition(Some(error_object(message.to_owned(), Vec::new(), ErrorKind::Read)));
+    message
+}
+
+// Message of a condition that no handler caught.
+fn uncaught_message(condition: &Expr) -> String {
+    match condition {
+        Expr::Atom(atom) => match &**atom {
+            Atom::ErrorObject(error) if error.irritants.is_empty() => error.message.to_owned(),
+            Atom::ErrorObject(error) => {
+                let irritants: Vec<String> = error
+                    .irritants
+                    .iter()
+                    .map(|x| syntax::print_tree(x, &false))
+                    .collect();
+
+                format!("{} {}", error.message, irritants.join(" "))
+            }
+            _ => format!("Uncaught exception '{}'.", syntax::print_tree(condition, &false)),
+        },
+        Expr::List(_) => format!("Uncaught exception '{}'.", syntax::print_tree(condition, &false)),
+    }
+}
+
+fn single_arg(args: Vec<Expr>, name: &str) -> Result<Expr, String> {
+    if args.len() != 1 {
+        return Err(format!("Incorrect argument count for '{}' function.", name));
+    }
+
+    Ok(args.into_iter().next().unwrap())
+}
+
+fn error_arg<'a>(args: &'a [Expr], name: &str) -> Result<Option<&'a ErrorObject>, String> {
+    match args {
+        [Expr::Atom(atom)] => match &**atom {
+            Atom::ErrorObject(error) => Ok(Some(error)),
+            _ => Ok(None),
+        },
+        [_] => Ok(None),
+        _ => Err(format!("Incorrect argument count for '{}' function.", name)),
+    }
+}
@@ -0,0 +1,211 @@
+use crate::env::Env;
+use crate::port;
+use crate::string;
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::BigDecimal;
+use std::iter::Peekable;
+use std::str::Chars;
+
+const DIRECTIVE_C: char = '~';
+
+// Widths and precisions are written out in full, so they are kept small.
+const MAX_FIELD: usize = 1000;
+
+pub trait FormatOps {
+    fn format(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+/**
+ * Directive of a control string, with the width and precision written
+ * between the tilde and its letter, as in "~8,2d".
+ */
+struct Directive {
+    letter: char,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+impl FormatOps for Env {
+    // The destination is #f to return the text, #t for the current output
+    // port, or a port. It can also be left out, then the text is returned.
+    fn format(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::FORMAT_FN;
+
+        let (destination, rest) = match args.split_first() {
+            Some((first, _)) if string_text(first).is_some() => (None, args),
+            Some((Expr::Atom(atom), rest)) => match &**atom {
+                Atom::Boolean(false) => (None, rest),
+                Atom::Boolean(true) => (Some(port::current_output_port(self)), rest),
+                Atom::Port(port) => (Some(port.clone()), rest),
+                _ => return Err(format!("Destination of '{}' must be #f, #t or a port.", name)),
+            },
+            Some((Expr::List(_), _)) => {
+                return Err(format!("Destination of '{}' must be #f, #t or a port.", name))
+            }
+            None => return Err(format!("Incorrect argument count for '{}' function.", name)),
+        };
+
+        let control = match rest.first().and_then(string_text) {
+            Some(control) => control,
+            None => return Err(format!("Control string of '{}' must be a string.", name)),
+        };
+
+        let text = format_text(&control, &rest[1..])?;
+
+        match destination {
+            Some(port) => {
+                port.write_str(&text);
+                Ok(Expr::Atom(Box::new(Atom::Nil)))
+            }
+            None => Ok(Expr::Atom(Box::new(Atom::StringLiteral(text)))),
+        }
+    }
+}
+
+/**
+ * Fills in the directives of a control string.
+ *
+ * @param control Text with directives that start with a tilde.
+ * @param args Values used by the directives, in order.
+ * @return Formatted text.
+ */
+pub fn format_text(control: &str, args: &[Expr]) -> Result<String, String> {
+    let mut result = String::new();
+    let mut args = args.iter();
+    let mut chars = control.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != DIRECTIVE_C {
+            result.push(c);
+            continue;
+        }
+
+        let directive = parse_directive(&mut chars)?;
+
+        let text = match directive.letter {
+            '%' => "\n".to_string(),
+            '~' => DIRECTIVE_C.to_string(),
+            letter => {
+                let arg = args.next().ok_or_else(|| {
+                    format!("Missing argument for '~{}' in '{}'.", letter, syntax::FORMAT_FN)
+                })?;
+
+                format_arg(&directive, arg)?
+            }
+        };
+
+        result.push_str(&text);
+    }
+
+    if args.next().is_some() {
+        return Err(format!(
+            "Too many arguments for the control string of '{}'.",
+            syntax::FORMAT_FN
+        ));
+    }
+
+    Ok(result)
+}
+
+// Reads the width, precision and letter that follow a tilde.
+fn parse_directive(chars: &mut Peekable<Chars>) -> Result<Directive, String> {
+    let width = parse_number(chars, "Field width")?;
+    let precision = match chars.peek() {
+        Some(',') => {
+            chars.next();
+            Some(parse_number(chars, "Precision")?.unwrap_or(0))
+        }
+        _ => None,
+    };
+
+    match chars.next() {
+        Some(letter) => Ok(Directive { letter: letter.to_ascii_lowercase(), width, precision }),
+        None => Err(format!("Control string of '{}' ends in a directive.", syntax::FORMAT_FN)),
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars>, what: &str) -> Result<Option<usize>, String> {
+    let mut number: Option<usize> = None;
+
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        chars.next();
+
+        number = match number.unwrap_or(0).checked_mul(10).map(|n| n + digit as usize) {
+            Some(n) if n <= MAX_FIELD => Some(n),
+            _ => return Err(format!("{} of '{}' is too large.", what, syntax::FORMAT_FN)),
+        };
+    }
+
+    Ok(number)
+}
+
+// Text of one argument. Numbers are aligned to the right of the width,
+// other values to the left.
+fn format_arg(directive: &Directive, arg: &Expr) -> Result<String, String> {
+    let letter = directive.letter;
+
+    if directive.precision.is_some() && letter != 'd' {
+        return Err(format!("Only '~d' of '{}' takes a precision.", syntax::FORMAT_FN));
+    }
+
+    let (text, right) = match letter {
+        'a' => (syntax::print_display(arg), false),
+        's' => (syntax::print_write(arg), false),
+        'd' => {
+            let n = number_arg(arg, letter)?;
+
+            match directive.precision {
+                Some(p) => (n.round(p as i64).with_scale(p as i64).to_string(), true),
+                None => (n.to_string(), true),
+            }
+        }
+        'x' | 'b' | 'o' => {
+            let n = number_arg(arg, letter)?;
+
+            if !n.is_integer() {
+                return Err(format!(
+                    "Argument for '~{}' in '{}' must be an integer.",
+                    letter,
+                    syntax::FORMAT_FN
+                ));
+            }
+
+            let radix = match letter {
+                'x' => 16,
+                'o' => 8,
+                _ => 2,
+            };
+            let (digits, _) = n.with_scale(0).as_bigint_and_exponent();
+
+            (digits.to_str_radix(radix), true)
+        }
+        _ => {
+            return Err(format!(
+                "Unknown directive '~{}' in the control string of '{}'.",
+                letter,
+                syntax::FORMAT_FN
+            ))
+        }
+    };
+
+    let padding = " ".repeat(directive.width.unwrap_or(0).saturating_sub(text.chars().count()));
+
+    Ok(if right { padding + &text } else { text + &padding })
+}
+
+fn number_arg(arg: &Expr, letter: char) -> Result<BigDecimal, String> {
+    if let Expr::Atom(atom) = arg {
+        if let Atom::Number(n) = &**atom {
+            return Ok(n.to_owned());
+        }
+    }
+
+    Err(format!("Argument for '~{}' in '{}' must be a number.", letter, syntax::FORMAT_FN))
+}
+
+fn string_text(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Atom(atom) => string::text_of(atom),
+        Expr::List(_) => None,
+    }
+}
@@ -0,0 +1,332 @@
+use crate::control::{self, Winders};
+use crate::env::Env;
+use crate::exception::Handlers;
+use crate::machine::{Cont, Pending, State};
+use crate::parameter::Parameterization;
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::ToPrimitive;
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+
+pub trait GeneratorOps {
+    fn make_generator(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn eof_object(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_eof_object(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn generator_to_list(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+}
+
+/**
+ * Procedure whose body runs as a coroutine. Each call resumes the body
+ * until it calls its yield procedure, whose argument is returned from the
+ * call. Once the body returns, every call gives the end of file object.
+ *
+ * A value passed to the generator is returned from the call to yield
+ * that resumes, so the body and its caller can exchange values.
+ *
+ * The body runs with the exception handlers and parameter values of
+ * whoever called the generator last, plus the ones it set up itself. If
+ * an error or a continuation takes control out of the body, the generator
+ * is done.
+ */
+#[derive(Clone)]
+pub struct Generator(Rc<RefCell<Coroutine>>);
+
+struct Coroutine {
+    state: CoroutineState,
+    // Continuation of the call that resumed the body, and the dynamic
+    // state at that point. The handlers and parameter values of the body
+    // are built on top of the ones of the caller.
+    caller: Cont,
+    caller_winders: Winders,
+    caller_handlers: Handlers,
+    caller_parameters: Parameterization,
+}
+
+enum CoroutineState {
+    // Procedure that takes the yield procedure, not called yet.
+    Fresh(Expr),
+    // Stopped in a call to yield.
+    Suspended {
+        k: Cont,
+        winders: Winders,
+        handlers: Handlers,
+        parameters: Parameterization,
+    },
+    Running,
+    Done,
+}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Generator")
+    }
+}
+
+// Two generators are only equal if they are the same object.
+impl PartialEq for Generator {
+    fn eq(&self, other: &Generator) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Generator {}
+
+impl GeneratorOps for Env {
+    // (make-generator (lambda (yield) body...))
+    fn make_generator(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() != 1 {
+            return Err("Incorrect argument count for 'make-generator' function.".to_string());
+        }
+
+        let generator = Generator(Rc::new(RefCell::new(Coroutine {
+            state: CoroutineState::Fresh(args[0].to_owned()),
+            caller: Cont::default(),
+            caller_winders: None,
+            caller_handlers: None,
+            caller_parameters: None,
+        })));
+
+        Ok(Expr::Atom(Box::new(Atom::Generator(generator))))
+    }
+
+    fn eof_object(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if !args.is_empty() {
+            return Err("Incorrect argument count for 'eof-object' function.".to_string());
+        }
+
+        Ok(eof())
+    }
+
+    fn is_eof_object(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [value] => Ok(Expr::Atom(Box::new(Atom::Boolean(is_eof(value))))),
+            _ => Err("Incorrect argument count for 'eof-object?' function.".to_string()),
+        }
+    }
+
+    // (generator->list gen [n]) takes at most n values if n is given.
+    fn generator_to_list(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let limit = match args.len() {
+            1 => None,
+            2 => Some(count_arg(&args.pop().unwrap())?),
+            _ => {
+                return Err(
+                    "Incorrect argument count for 'generator->list' function.".to_string(),
+                )
+            }
+        };
+
+        Ok(next_generated(args.pop().unwrap(), Vec::new(), limit, k))
+    }
+}
+
+/**
+ * Resumes the body of a generator.
+ *
+ * @param env Environment holding the current dynamic state.
+ * @param generator Generator being called.
+ * @param values Arguments of the call, at most a value for yield to return.
+ * @param k Continuation that receives the next value.
+ * @return Next step of the evaluator.
+ */
+pub fn resume(
+    env: &mut Env,
+    generator: Generator,
+    values: Vec<Expr>,
+    k: Cont,
+) -> Result<State, String> {
+    let value = single_value(values, "Generator")?;
+
+    if matches!(generator.0.borrow().state, CoroutineState::Running) {
+        if runs_inside(&k, &generator) {
+            return Err("Generator cannot be resumed while it is running.".to_string());
+        }
+
+        // The body was left through an error or a continuation, without
+        // yielding or returning.
+        abandon(&generator);
+    }
+
+    let mut coroutine = generator.0.borrow_mut();
+
+    match mem::replace(&mut coroutine.state, CoroutineState::Running) {
+        CoroutineState::Fresh(procedure) => {
+            coroutine.caller = k;
+            coroutine.caller_winders = env.winders();
+            coroutine.caller_handlers = env.handlers();
+            coroutine.caller_parameters = env.parameters();
+
+            let yield_fn = Expr::Atom(Box::new(Atom::Yield(generator.clone())));
+            let body_k = Cont::default().push(Pending::GeneratorDone {
+                generator: generator.clone(),
+            });
+
+            Ok(State::Apply(procedure, vec![yield_fn], body_k))
+        }
+        CoroutineState::Suspended {
+            k: body_k,
+            winders,
+            handlers,
+            parameters,
+        } => {
+            let handlers = control::rebase(&handlers, &coroutine.caller_handlers, env.handlers());
+            let parameters =
+                control::rebase(&parameters, &coroutine.caller_parameters, env.parameters());
+
+            coroutine.caller = k;
+            coroutine.caller_winders = env.winders();
+            coroutine.caller_handlers = env.handlers();
+            coroutine.caller_parameters = env.parameters();
+
+            env.set_winders(winders);
+            env.set_handlers(handlers);
+            env.set_parameters(parameters);
+
+            Ok(State::Return(value, body_k))
+        }
+        CoroutineState::Running => unreachable!(),
+        CoroutineState::Done => {
+            coroutine.state = CoroutineState::Done;
+            Ok(State::Return(eof(), k))
+        }
+    }
+}
+
+/**
+ * Tells whether a continuation returns into the body of a generator. The
+ * body of another generator returns to the caller of that generator, so
+ * the search goes on from there.
+ *
+ * @param k Continuation of a call to the generator.
+ * @param generator Generator whose body is running.
+ * @return Whether the call is made from inside the body.
+ */
+fn runs_inside(k: &Cont, generator: &Generator) -> bool {
+    let mut current = k.clone();
+    let mut seen: Vec<Generator> = Vec::new();
+
+    loop {
+        let inner = current.frames().find_map(|pending| match pending {
+            Pending::GeneratorDone { generator } => Some(generator.clone()),
+            _ => None,
+        });
+
+        match inner {
+            Some(inner) if inner == *generator => return true,
+            Some(inner) if !seen.contains(&inner) => {
+                current = inner.0.borrow().caller.clone();
+                seen.push(inner);
+            }
+            _ => return false,
+        }
+    }
+}
+
+fn abandon(generator: &Generator) {
+    let mut coroutine = generator.0.borrow_mut();
+
+    coroutine.state = CoroutineState::Done;
+    coroutine.caller = Cont::default();
+    coroutine.caller_winders = None;
+    coroutine.caller_handlers = None;
+    coroutine.caller_parameters = None;
+}
+
+// Suspends the body of a generator and returns a value to its caller.
+pub fn yield_value(
+    env: &mut Env,
+    generator: Generator,
+    values: Vec<Expr>,
+    k: Cont,
+) -> Result<State, String> {
+    let value = single_value(values, "Yield")?;
+    let mut coroutine = generator.0.borrow_mut();
+
+    if !matches!(coroutine.state, CoroutineState::Running) {
+        return Err("Cannot yield from a generator that is not running.".to_string());
+    }
+
+    coroutine.state = CoroutineState::Suspended {
+        k,
+        winders: env.winders(),
+        handlers: env.handlers(),
+        parameters: env.parameters(),
+    };
+
+    Ok(return_to_caller(env, &mut coroutine, value))
+}
+
+// The body of a generator has returned.
+pub fn finish(env: &mut Env, generator: Generator) -> State {
+    let mut coroutine = generator.0.borrow_mut();
+    coroutine.state = CoroutineState::Done;
+
+    return_to_caller(env, &mut coroutine, eof())
+}
+
+// The handlers and parameter values of the caller are kept, the next
+// resume moves the ones of the body from them onto those of its caller.
+fn return_to_caller(env: &mut Env, coroutine: &mut Coroutine, value: Expr) -> State {
+    env.set_winders(coroutine.caller_winders.take());
+    env.set_handlers(coroutine.caller_handlers.clone());
+    env.set_parameters(coroutine.caller_parameters.clone());
+
+    State::Return(value, mem::take(&mut coroutine.caller))
+}
+
+/**
+ * Calls a generator for the next value of 'generator->list', or returns
+ * the list once the generator is done or the limit is reached.
+ *
+ * @param generator Generator to call.
+ * @param items Values collected so far.
+ * @param limit Most values to collect, if any.
+ * @param k Continuation of 'generator->list'.
+ * @return Next step of the evaluator.
+ */
+pub fn next_generated(generator: Expr, items: Vec<Expr>, limit: Option<usize>, k: Cont) -> State {
+    if limit == Some(items.len()) {
+        return State::Return(Expr::List(items), k);
+    }
+
+    let k = k.push(Pending::GeneratorToList {
+        generator: generator.to_owned(),
+        items,
+        limit,
+    });
+
+    State::Apply(generator, Vec::new(), k)
+}
+
+pub fn eof() -> Expr {
+    Expr::Atom(Box::new(Atom::Eof))
+}
+
+pub fn is_eof(expr: &Expr) -> bool {
+    matches!(expr, Expr::Atom(atom) if **atom == Atom::Eof)
+}
+
+fn single_value(mut values: Vec<Expr>, name: &str) -> Result<Expr, String> {
+    match values.len() {
+        0 => Ok(Expr::Atom(Box::new(Atom::Nil))),
+        1 => Ok(values.pop().unwrap()),
+        _ => Err(format!("{} takes at most one value.", name)),
+    }
+}
+
+fn count_arg(expr: &Expr) -> Result<usize, String> {
+    if let Expr::Atom(atom) = expr {
+        if let Atom::Number(n) = &**atom {
+            if let (true, Some(count)) = (n.is_integer(), n.to_usize()) {
+                return Ok(count);
+            }
+        }
+    }
+
+    Err(format!(
+        "Count of '{}' must be a non-negative integer.",
+        syntax::GENERATOR_TO_LIST_FN
+    ))
+}
@@ -5,6 +5,11 @@ use std::result::Result;
 use std::str::FromStr;
 use std::vec::Vec;
 
+const STRING_DELIMITER: char = '"';
+const ESCAPE_C: char = '\\';
+const QUOTE_TOKEN: &str = "'";
+const CHAR_PREFIX: &str = "#\\";
+
 /**
  * Returns all tokens in code separated into a list of strings.
  *
@@ -12,10 +17,11 @@ use std::vec::Vec;
  * @return All tokens in text separated into a list of strings.
  */
 pub fn lexical_analysis(code: &String) -> Result<Vec<String>, String> {
-    let is_matching = parens_match_and_exist(code);
+    let tokens = tokenize(code)?;
+    let is_matching = parens_match_and_exist(&tokens);
 
     match is_matching {
-        true => Result::Ok(tokenize(code)),
+        true => Result::Ok(tokens),
         false => Result::Err("Missing closing or opening parenthesis.".to_string()),
     }
 }
@@ -27,86 +33,81 @@ pub fn lexical_analysis(code: &String) -> Result<Vec<String>, String> {
  * @return Typed expression object containing value or symbolic name.
  */
 fn parse_atom(atom: &String) -> Expr {
-    if is_string(atom) {
-        Expr::Atom(Box::new(Atom::StringLiteral(atom.to_owned())))
+    if atom.starts_with(STRING_DELIMITER) {
+        Expr::Atom(Box::new(Atom::StringLiteral(unescape(atom))))
+    } else if let Some(c) = atom.strip_prefix(CHAR_PREFIX).and_then(syntax::char_named) {
+        Expr::Atom(Box::new(Atom::Char(c)))
     } else if BigDecimal::from_str(&atom).is_ok() {
         Expr::Atom(Box::new(Atom::Number(BigDecimal::from_str(&atom).unwrap())))
     } else if is_boolean(atom) {
         Expr::Atom(Box::new(Atom::Boolean(atom == "#t")))
     } else if atom == syntax::NIL_LIT {
         Expr::Atom(Box::new(Atom::Nil))
+    } else if atom.len() > 1 && atom.ends_with(':') {
+        Expr::Atom(Box::new(Atom::Keyword(atom[..atom.len() - 1].to_string())))
     } else {
         Expr::Atom(Box::new(Atom::Symbol(atom.to_owned())))
     }
 }
 
-fn parens_match_and_exist(code: &String) -> bool {
+fn parens_match_and_exist(tokens: &[String]) -> bool {
     let mut unclosed_opens = 0;
 
-    for c in code.chars() {
-        if c == '(' {
+    for token in tokens.iter() {
+        if token == "(" {
             unclosed_opens += 1;
-        } else if c == ')' {
+        } else if token == ")" {
             unclosed_opens -= 1;
         }
     }
 
-    return unclosed_opens == 0;
+    unclosed_opens == 0
 }
 
-fn tokens_match_parens(tokens: &Vec<String>) -> bool {
-    let mut unclosed_opens = 0;
-
-    for token in tokens {
-        if token == "(" {
-            unclosed_opens += 1;
-        } else if token == ")" {
-            unclosed_opens -= 1;
-        }
-    }
+/**
+ * Builds an expression tree from the first complete expression in a list
+ * of tokens.
+ *
+ * @param tokens Tokens produced by lexical analysis.
+ * @return Expression tree, lists with a single element are kept as lists.
+ */
+pub fn parse_tokens(tokens: &[String]) -> Expr {
+    let mut position: usize = 0;
 
-    return unclosed_opens == 0;
+    parse_expr(tokens, &mut position)
 }
 
-pub fn parse_tokens(tokens: &Vec<String>) -> Expr {
-    // recur for interior levels of expression
-    // then add them to the expression list.
-    let mut local_expr: Vec<Expr> = Vec::new();
-    let mut current_expr: Vec<String> = Vec::new();
-    let token_len = tokens.len();
-
-    for (i, token) in tokens.iter().enumerate() {
-        // check if end or start token.
-        if i != 0 && i != token_len {
-            // If parens are equally weighted append new expression into
-            if tokens_match_parens(&current_expr) && current_expr.len() > 0 {
-                let is_list = current_expr.len() > 1;
-
-                if is_list {
-                    // Add expression if it has been closed.
-                    let expr = parse_tokens(&current_expr);
-                    local_expr.push(expr);
-
-                    current_expr.clear();
-                } else if current_expr.len() == 1 {
-                    let expr = parse_atom(&current_expr[0]);
-                    local_expr.push(expr);
-
-                    current_expr.clear();
+fn parse_expr(tokens: &[String], position: &mut usize) -> Expr {
+    match tokens.get(*position) {
+        Some(token) if token == "(" => {
+            let mut list: Vec<Expr> = Vec::new();
+            *position += 1;
+
+            while let Some(token) = tokens.get(*position) {
+                if token == ")" {
+                    *position += 1;
+                    break;
                 }
+
+                list.push(parse_expr(tokens, position));
             }
 
-            current_expr.push(token.to_owned());
+            Expr::List(list)
         }
-    }
-
-    if local_expr.len() > 1 {
-        Expr::List(local_expr)
-    } else {
-        local_expr
-            .get(0)
-            .unwrap_or(&Expr::Atom(Box::new(Atom::Nil)))
-            .to_owned()
+        // 'x is read as (quote x).
+        Some(token) if token == QUOTE_TOKEN => {
+            *position += 1;
+
+            Expr::List(vec![
+                Expr::Atom(Box::new(Atom::Symbol(syntax::QT_OP.to_string()))),
+                parse_expr(tokens, position),
+            ])
+        }
+        Some(token) => {
+            *position += 1;
+            parse_atom(token)
+        }
+        None => Expr::Atom(Box::new(Atom::Nil)),
     }
 }
 
@@ -118,32 +119,27 @@ fn is_boolean(atom: &String) -> bool {
     return is_boolean;
 }
 
-fn is_string(atom: &String) -> bool {
-    const BACKSLASH_C: char = '\\';
-    const QUOTE_C: char = '\'';
-    let mut is_string: bool = true;
-
-    for (i, c) in atom.chars().enumerate() {
-        if i == 0 || i == atom.len() - 1 {
-            if c != QUOTE_C {
-                is_string = false;
-                break;
-            }
-        } else if c == QUOTE_C {
-            if i == 1 {
-                is_string = false;
-                break;
-            } else {
-                let next_c = atom.as_bytes()[i - 1] as char;
-                if next_c != BACKSLASH_C {
-                    is_string = false;
-                    break;
-                }
+// Replaces the escape sequences of a string token by the characters they
+// stand for, and drops the delimiters.
+fn unescape(token: &str) -> String {
+    let inner = &token[1..token.len() - 1];
+    let mut result = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c == ESCAPE_C {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => {}
             }
+        } else {
+            result.push(c);
         }
     }
 
-    return is_string;
+    result
 }
 
 /**
@@ -169,24 +165,42 @@ fn is_string(atom: &String) -> bool {
 //     return result;
 // }
 
-pub fn chunk_file(text: &String) -> Vec<String> {
+pub fn chunk_file(text: &str) -> Vec<String> {
     let mut chunks: Vec<String> = Vec::new();
     let mut chunk: String = String::new();
     let mut nesting_level: u32 = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut in_char = false;
 
     for c in text.chars() {
         if nesting_level > 0 || c == '(' {
-            if c == '\n' {
+            if c == '\n' && !in_string {
                 chunk.push(' ');
             } else {
                 chunk.push(c);
             }
-        } else if chunk.len() != 0 {
+        } else if !chunk.is_empty() {
             chunks.push(chunk.clone());
             chunk.clear();
         }
 
-        if c == '(' {
+        // Parentheses inside strings and character literals don't count.
+        if in_char {
+            in_char = false;
+        } else if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == ESCAPE_C {
+                escaped = true;
+            } else if c == STRING_DELIMITER {
+                in_string = false;
+            }
+        } else if c == ESCAPE_C && chunk.ends_with(CHAR_PREFIX) {
+            in_char = true;
+        } else if c == STRING_DELIMITER && nesting_level > 0 {
+            in_string = true;
+        } else if c == '(' {
             nesting_level += 1;
         } else if c == ')' {
             nesting_level -= 1;
@@ -198,94 +212,64 @@ pub fn chunk_file(text: &String) -> Vec<String> {
     chunks
 }
 
-fn tokenize(source: &String) -> Vec<String> {
-    // keep count of open parenthesis
-    // track whether we are in a string
-    let mut in_quotes: bool = false;
-    let char_list: Vec<char> = source.chars().collect::<Vec<char>>();
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
     let mut tokens: Vec<String> = Vec::new();
-    let mut position: usize = 0;
     let mut current_str: String = String::new();
+    let mut chars = source.chars();
 
-    // loop until we break out.
-    loop {
-        // grab until there aren't any more.
-        let c = char_list.get(position);
-
-        if let Some(v) = c {
-            if v.is_whitespace() {
-                position += 1;
-                if !in_quotes {
-                    if !current_str.is_empty() {
-                        tokens.push(current_str.clone());
-                        current_str.clear();
-                    }
-                } else {
-                    current_str.push(v.clone());
-                }
-            }
-            // We don't support double quotes, only single.
-            else if v == &'\'' {
-                position += 1;
-
-                if in_quotes {
-                    in_quotes = false;
-
-                    current_str.push(v.clone());
-                    tokens.push(current_str.clone());
-                    current_str.clear();
-                } else {
-                    if !current_str.is_empty() {
-                        tokens.push(current_str.clone());
-                        current_str.clear();
-                    }
-
-                    in_quotes = true;
-                    current_str.push(v.clone());
-                }
-            } else if v == &'(' {
-                position += 1;
+    // Ends the token being read, if any.
+    fn flush(tokens: &mut Vec<String>, current_str: &mut String) {
+        if !current_str.is_empty() {
+            tokens.push(current_str.clone());
+            current_str.clear();
+        }
+    }
 
-                if !in_quotes {
-                    if !current_str.is_empty() {
-                        tokens.push(current_str.clone());
-                        current_str.clear();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            flush(&mut tokens, &mut current_str);
+        } else if c == STRING_DELIMITER {
+            // Strings are kept whole, escapes included, and are
+            // unescaped when the token is parsed.
+            flush(&mut tokens, &mut current_str);
+            current_str.push(c);
+
+            loop {
+                match chars.next() {
+                    Some(ESCAPE_C) => {
+                        current_str.push(ESCAPE_C);
+
+                        if let Some(next) = chars.next() {
+                            current_str.push(next);
+                        }
                     }
-
-                    tokens.push(String::from("("));
-                } else {
-                    current_str.push(v.clone());
+                    Some(STRING_DELIMITER) => {
+                        current_str.push(STRING_DELIMITER);
+                        break;
+                    }
+                    Some(other) => current_str.push(other),
+                    None => return Err("Missing closing quote of string.".to_string()),
                 }
-            } else if v == &')' {
-                position += 1;
+            }
 
-                if !in_quotes {
-                    if !current_str.is_empty() {
-                        tokens.push(current_str.clone());
-                        current_str.clear();
-                    }
+            flush(&mut tokens, &mut current_str);
+        } else if c == ESCAPE_C && current_str == "#" {
+            // The character after #\ is part of the literal, even if it
+            // is a parenthesis or a space.
+            current_str.push(c);
 
-                    tokens.push(String::from(")"));
-                } else {
-                    current_str.push(v.clone());
-                }
-            } else {
-                position += 1;
-                current_str.push(v.clone());
+            if let Some(next) = chars.next() {
+                current_str.push(next);
             }
+        } else if c == '(' || c == ')' || (c == '\'' && current_str.is_empty()) {
+            flush(&mut tokens, &mut current_str);
+            tokens.push(c.to_string());
         } else {
-            if !current_str.is_empty() {
-                tokens.push(current_str.clone());
-                current_str.clear();
-            }
-            break;
+            current_str.push(c);
         }
     }
 
-    if tokens.len() == 1 {
-        tokens.insert(0, "(".to_string());
-        tokens.push(")".to_string());
-    }
+    flush(&mut tokens, &mut current_str);
 
-    tokens
+    Ok(tokens)
 }
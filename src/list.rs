@@ -0,0 +1,93 @@
+use crate::env::Env;
+use crate::machine::{Cont, Pending, State};
+use crate::syntax::{Atom, Expr};
+
+pub trait ListOps {
+    fn list(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn map(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn for_each(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+}
+
+impl ListOps for Env {
+    fn list(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        Ok(Expr::List(args.to_vec()))
+    }
+
+    // With several lists the procedure gets one element of each, and
+    // stops at the end of the shortest.
+    fn map(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let (procedure, lists) = procedure_and_lists(&args, "map")?;
+        let calls = zip_lists(&lists).into_iter().rev().collect();
+
+        Ok(next_call(procedure.to_owned(), calls, Vec::new(), true, k))
+    }
+
+    fn for_each(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let (procedure, lists) = procedure_and_lists(&args, "for-each")?;
+        let calls = zip_lists(&lists).into_iter().rev().collect();
+
+        Ok(next_call(procedure.to_owned(), calls, Vec::new(), false, k))
+    }
+}
+
+/**
+ * Makes the next call of 'map' or 'for-each', or returns once all of them
+ * are done.
+ *
+ * @param procedure Procedure being mapped.
+ * @param calls Arguments of the calls left, the next one last.
+ * @param results Values of the calls so far.
+ * @param collect Whether the values are collected into a list.
+ * @param k Continuation of the whole 'map' or 'for-each'.
+ * @return Next step of the evaluator.
+ */
+pub fn next_call(
+    procedure: Expr,
+    mut calls: Vec<Vec<Expr>>,
+    results: Vec<Expr>,
+    collect: bool,
+    k: Cont,
+) -> State {
+    match calls.pop() {
+        Some(values) => {
+            let k = k.push(Pending::Map {
+                procedure: procedure.to_owned(),
+                calls,
+                results,
+                collect,
+            });
+
+            State::Apply(procedure, values, k)
+        }
+        None if collect => State::Return(Expr::List(results), k),
+        None => State::Return(Expr::Atom(Box::new(Atom::Nil)), k),
+    }
+}
+
+fn procedure_and_lists<'a>(
+    args: &'a [Expr],
+    name: &str,
+) -> Result<(&'a Expr, Vec<&'a [Expr]>), String> {
+    if args.len() < 2 {
+        return Err(format!("Incorrect argument count for '{}' function.", name));
+    }
+
+    let lists = args[1..]
+        .iter()
+        .map(|x| match x {
+            Expr::List(list) => Ok(list.as_slice()),
+            Expr::Atom(_) => Err(format!("'{}' can only be applied to lists.", name)),
+        })
+        .collect::<Result<Vec<&[Expr]>, String>>()?;
+
+    Ok((&args[0], lists))
+}
+
+// Arguments for each call, taking the n-th element of every list.
+fn zip_lists(lists: &[&[Expr]]) -> Vec<Vec<Expr>> {
+    let len = lists.iter().map(|x| x.len()).min().unwrap_or(0);
+
+    (0..len)
+        .map(|i| lists.iter().map(|x| x[i].to_owned()).collect())
+        .collect()
+}
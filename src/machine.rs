@@ -0,0 +1,524 @@
+use crate::binding::{self, BindingForms, Bindings};
+use crate::boolean::{self, LogicOps};
+use crate::built_in::{self, EnvPrimitives};
+use crate::control::{self, Continuation};
+use crate::env::{self, Argument, Binding, Env, Frame, Scope};
+use crate::equality;
+use crate::exception::{self, ExceptionOps, Handlers};
+use crate::generator::{self, Generator};
+use crate::list;
+use crate::parameter::{self, Parameter, ParameterBindings, ParameterOps, Parameterization};
+use crate::primitive::PrimitiveFn;
+use crate::promise::{self, Promise, PromiseOps};
+use crate::record::{self, RecordOps};
+use crate::stream::{self, StreamOp, StreamOps};
+use crate::string;
+use crate::params::Params;
+use crate::syntax::{self, Atom, Expr};
+use crate::values::{self, ValueBindings, ValuesOps};
+use std::rc::Rc;
+
+/**
+ * Where the evaluator is at between two steps.
+ *
+ * Evaluation never recurses on the Rust stack. Work that waits for a value
+ * is pushed on the continuation instead, which makes the continuation an
+ * ordinary value that 'call/cc' can capture and resume any number of times.
+ */
+pub enum State {
+    // Expression to evaluate in a frame.
+    Eval(Expr, Scope, Cont),
+    // Value handed to the innermost pending work.
+    Return(Expr, Cont),
+    // Procedure called with evaluated arguments.
+    Apply(Expr, Vec<Expr>, Cont),
+}
+
+/**
+ * Work left to do once the value being computed is known, innermost first.
+ *
+ * Frames are shared between the continuations captured from them, so
+ * pushing a frame never copies the ones below it.
+ */
+#[derive(Clone, Default)]
+pub struct Cont(Option<Rc<ContFrame>>);
+
+pub struct ContFrame {
+    pending: Pending,
+    next: Cont,
+}
+
+/**
+ * One piece of work waiting for a value. Lists of expressions still to be
+ * evaluated are kept in reverse, so the next one is popped off the end.
+ */
+#[derive(Clone)]
+pub enum Pending {
+    // Operator of a call, the arguments come next.
+    Operator { args: Vec<Expr>, scope: Scope },
+    // Argument of a call, along with the ones already evaluated.
+    Operand { procedure: Expr, values: Vec<Expr>, rest: Vec<Expr>, scope: Scope },
+    // Procedure to call with arguments that are already known.
+    ApplyTo { values: Vec<Expr> },
+    // Default of a parameter, the parameters after it and the body of the
+    // function come next.
+    Argument { name: String, rest: Vec<(String, Argument)>, body: Rc<Vec<Expr>>, scope: Scope },
+    // Expression of a body whose value is dropped.
+    Sequence { rest: Vec<Expr>, scope: Scope },
+    If { consequent: Expr, alternative: Option<Expr>, scope: Scope },
+    And { rest: Vec<Expr>, scope: Scope },
+    Or { rest: Vec<Expr>, scope: Scope },
+    When { body: Vec<Expr>, negate: bool, scope: Scope },
+    Cond { body: Vec<Expr>, rest: Vec<Expr>, scope: Scope },
+    Case { clauses: Vec<Expr>, scope: Scope },
+    Define { name: String, scope: Scope },
+    Set { name: String, scope: Scope },
+    Let { names: Vec<String>, values: Vec<Expr>, rest: Vec<Expr>, body: Vec<Expr>, scope: Scope },
+    LetStar { name: String, rest: Bindings, body: Vec<Expr>, scope: Scope },
+    Letrec { names: Vec<String>, values: Vec<Expr>, rest: Vec<Expr>, body: Vec<Expr>, star: bool, scope: Scope },
+    LetValues {
+        formals: Params,
+        rest: ValueBindings,
+        bound: Vec<(Params, Expr)>,
+        body: Vec<Expr>,
+        star: bool,
+        scope: Scope,
+    },
+    DefineValues { formals: Params, scope: Scope },
+    // Procedure that gets the values returned as its arguments.
+    SpreadTo { consumer: Expr },
+    // Promise whose expression is being evaluated.
+    Force { promise: Promise, delay: bool },
+    // Stream being forced for 'stream-car' and the like.
+    StreamCell { op: StreamOp },
+    // Stages of 'stream->list'.
+    StreamToList { items: Vec<Expr>, limit: Option<usize> },
+    StreamElement { items: Vec<Expr>, rest: Expr, limit: Option<usize> },
+    // Body of a generator, which is done once it returns.
+    GeneratorDone { generator: Generator },
+    GeneratorToList { generator: Expr, items: Vec<Expr>, limit: Option<usize> },
+    // Element of a list being compared by the procedure given to 'member'
+    // or 'assoc'.
+    Member { item: Expr, list: Vec<Expr>, index: usize, compare: Expr, assoc: bool },
+    // Character being tested by the predicate given to 'string-index'.
+    StringIndex { chars: Vec<char>, index: usize, predicate: Expr },
+    // Characters returned by 'string-map', to turn into a string.
+    CharsToString,
+    // Calls of 'map' and 'for-each' left to make.
+    Map { procedure: Expr, calls: Vec<Vec<Expr>>, results: Vec<Expr>, collect: bool },
+    // Stages of 'dynamic-wind'.
+    WindBefore { before: Expr, thunk: Expr, after: Expr },
+    WindBody,
+    // Value to hand on once a thunk that is only run for effect returns.
+    Restore { value: Expr },
+    // Before and after thunks to run on the way into a continuation.
+    Rewind { steps: Vec<control::WindStep>, target: Continuation, value: Expr },
+    // Converter of a parameter being made.
+    MakeParameter { converter: Expr },
+    // Stages of 'parameterize'.
+    Parameterize { values: Vec<Expr>, rest: Vec<Expr>, body: Vec<Expr>, scope: Scope },
+    ParameterConvert {
+        parameter: Parameter,
+        pending: ParameterBindings,
+        bound: ParameterBindings,
+        body: Vec<Expr>,
+        scope: Scope,
+    },
+    // Parameter values to reinstall once the body of 'parameterize' returns.
+    RestoreParameters { parameters: Parameterization },
+    // Handlers to reinstall once a handler or the body it guards returns.
+    Handled { handlers: Handlers, continuable: bool },
+    RestoreHandlers { handlers: Handlers },
+    // Clauses of a 'guard' waiting for the condition.
+    GuardClauses { var: String, clauses: Vec<Expr>, scope: Scope, reraise: Continuation },
+    // Condition no clause of a 'guard' accepted.
+    Reraise,
+}
+
+impl Cont {
+    pub fn push(&self, pending: Pending) -> Cont {
+        Cont(Some(Rc::new(ContFrame {
+            pending,
+            next: self.clone(),
+        })))
+    }
+
+    // Takes the innermost frame, copying it only if it is shared.
+    fn pop(self) -> Option<(Pending, Cont)> {
+        let frame = self.0?;
+
+        match Rc::try_unwrap(frame) {
+            Ok(mut frame) => {
+                let next = std::mem::take(&mut frame.next);
+                let pending = std::mem::replace(&mut frame.pending, Pending::WindBody);
+
+                Some((pending, next))
+            }
+            Err(frame) => Some((frame.pending.clone(), frame.next.clone())),
+        }
+    }
+
+    // Pending work, from the innermost frame out.
+    pub fn frames(&self) -> impl Iterator<Item = &Pending> {
+        std::iter::successors(self.0.as_deref(), |frame| frame.next.0.as_deref())
+            .map(|frame| &frame.pending)
+    }
+
+    pub fn ptr_eq(&self, other: &Cont) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+// Long continuations are freed in a loop, dropping them recursively could
+// overflow the stack after deep non-tail recursion.
+impl Drop for ContFrame {
+    fn drop(&mut self) {
+        let mut next = self.next.0.take();
+
+        while let Some(frame) = next {
+            match Rc::try_unwrap(frame) {
+                Ok(mut frame) => next = frame.next.0.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/**
+ * Runs the evaluator until the continuation it started with is done.
+ *
+ * @param env Environment holding the global frame and dynamic state.
+ * @param state First step, usually an expression to evaluate.
+ * @return Value handed to the empty continuation.
+ */
+pub fn run(env: &mut Env, state: State) -> Result<Expr, String> {
+    let winders = env.winders();
+    let parameters = env.parameters();
+    let mut state = state;
+
+    // Handlers of an enclosing run are not called from this one, errors
+    // reach them once they come out of it.
+    let handlers = env.handlers();
+    env.set_handlers(None);
+    env.set_condition(None);
+
+    let result = loop {
+        let next = match state {
+            State::Eval(expr, scope, k) => eval(env, expr, scope, k),
+            State::Return(value, k) => match k.pop() {
+                Some((pending, k)) => resume(env, pending, value, k),
+                None => break Ok(value),
+            },
+            State::Apply(procedure, values, k) => apply(env, procedure, values, k),
+        };
+
+        state = match next.or_else(|msg| exception::recover(env, msg)) {
+            Ok(state) => state,
+            Err(msg) => break Err(msg),
+        };
+    };
+
+    // Errors leave every 'dynamic-wind' and 'parameterize' entered in
+    // this run.
+    env.set_winders(winders);
+    env.set_parameters(parameters);
+    env.set_handlers(handlers);
+
+    result
+}
+
+fn eval(env: &mut Env, expr: Expr, scope: Scope, k: Cont) -> Result<State, String> {
+    match expr {
+        Expr::Atom(atom) => match *atom {
+            Atom::Symbol(s) => Ok(State::Return(Frame::get(&scope, &s)?, k)),
+            other => Ok(State::Return(Expr::Atom(Box::new(other)), k)),
+        },
+        Expr::List(mut list) => {
+            if list.is_empty() {
+                return Err("Empty list is not a valid token.".to_string());
+            }
+
+            let args = list.split_off(1);
+            let head = list.pop().unwrap();
+
+            // Special forms get their arguments unevaluated. They are only
+            // used when the name isn't bound, builtin procedures are bound
+            // in the global frame like any other value.
+            if let Expr::Atom(atom) = &head {
+                if let Atom::Symbol(name) = &**atom {
+                    if Frame::lookup(&scope, name).is_none() {
+                        return special_form(env, name, &args, scope, k);
+                    }
+                }
+            }
+
+            let k = k.push(Pending::Operator { args, scope: scope.clone() });
+            Ok(State::Eval(head, scope, k))
+        }
+    }
+}
+
+fn special_form(
+    env: &mut Env,
+    name: &str,
+    args: &[Expr],
+    scope: Scope,
+    k: Cont,
+) -> Result<State, String> {
+    match name {
+        syntax::QT_OP => env.quote(args, scope, k),
+        syntax::IF_OP => env.if_op(args, scope, k),
+        syntax::AND_OP => env.and(args, scope, k),
+        syntax::OR_OP => env.or(args, scope, k),
+        syntax::COND_OP => env.cond(args, scope, k),
+        syntax::CASE_OP => env.case(args, scope, k),
+        syntax::WHEN_OP => env.when(args, scope, k),
+        syntax::UNLESS_OP => env.unless(args, scope, k),
+        syntax::BEGIN_OP => Ok(sequence(args, scope, k)),
+        syntax::LET_OP => env.let_op(args, scope, k),
+        syntax::LET_STAR_OP => env.let_star(args, scope, k),
+        syntax::LETREC_OP => env.letrec(args, scope, k),
+        syntax::LETREC_STAR_OP => env.letrec_star(args, scope, k),
+        syntax::DEF_OP => env.define(args, scope, k),
+        syntax::DEF_STAR_OP => env.define_star(args, scope, k),
+        syntax::SET_OP => env.set(args, scope, k),
+        syntax::FUN_OP => env.lambda(args, scope, k),
+        syntax::FUN_STAR_OP => env.lambda_star(args, scope, k),
+        syntax::CASE_FUN_OP => env.case_lambda(args, scope, k),
+        syntax::GUARD_OP => env.guard(args, scope, k),
+        syntax::LET_VALUES_OP => env.let_values(args, scope, k),
+        syntax::LET_STAR_VALUES_OP => env.let_star_values(args, scope, k),
+        syntax::DEFINE_VALUES_OP => env.define_values(args, scope, k),
+        syntax::RECEIVE_OP => env.receive(args, scope, k),
+        syntax::DELAY_OP => env.delay(args, scope, k),
+        syntax::DELAY_FORCE_OP => env.delay_force(args, scope, k),
+        syntax::STREAM_CONS_OP => env.stream_cons(args, scope, k),
+        syntax::PARAMETERIZE_OP => env.parameterize(args, scope, k),
+        syntax::DEFINE_RECORD_TYPE_OP => env.define_record_type(args, scope, k),
+        _ => Err(format!("Function name '{}' not recognized.", name)),
+    }
+}
+
+/**
+ * Calls a procedure with evaluated arguments.
+ *
+ * @param env Environment the call happens in.
+ * @param procedure Value in operator position.
+ * @param values Evaluated arguments.
+ * @param k Continuation that receives the result.
+ * @return Next step of the evaluator.
+ */
+pub fn apply(env: &mut Env, procedure: Expr, values: Vec<Expr>, k: Cont) -> Result<State, String> {
+    let lambda_def = match procedure {
+        Expr::Atom(atom) => match *atom {
+            Atom::Primitive(primitive) => {
+                return match primitive.func {
+                    PrimitiveFn::Plain(func) => Ok(State::Return(func(env, &values)?, k)),
+                    PrimitiveFn::Control(func) => func(env, values, k),
+                }
+            }
+            Atom::Lambda(def) => def,
+            Atom::CaseLambda(clauses) => env::select_clause(&clauses, values.len())?,
+            Atom::Continuation(target) => return control::resume_continuation(env, target, values),
+            Atom::Generator(generator) => return generator::resume(env, generator, values, k),
+            Atom::Yield(generator) => return generator::yield_value(env, generator, values, k),
+            Atom::RecordProcedure(procedure) => {
+                return Ok(State::Return(record::call(&procedure, values)?, k))
+            }
+            Atom::Parameter(parameter) => {
+                return Ok(State::Return(parameter::call(env, &parameter, &values)?, k))
+            }
+            other => return Err(env::not_procedure(&Expr::Atom(Box::new(other)))),
+        },
+        list => return Err(env::not_procedure(&list)),
+    };
+
+    let (scope, arguments) = env::bind_arguments(&lambda_def, values)?;
+    Ok(env::next_argument(scope, arguments, lambda_def.body, k))
+}
+
+/**
+ * Evaluates the expressions of a body in order, the last one in tail
+ * position. Definitions go into the given frame, so they are local to the
+ * body of a function or binding form.
+ *
+ * @param body Expressions of the body in order.
+ * @param scope Frame to evaluate the body in.
+ * @param k Continuation that receives the value of the last expression.
+ * @return Next step, nil for an empty body.
+ */
+pub fn sequence(body: &[Expr], scope: Scope, k: Cont) -> State {
+    match body.split_first() {
+        Some((first, [])) => State::Eval(first.to_owned(), scope, k),
+        Some((first, rest)) => {
+            let rest = rest.iter().rev().cloned().collect();
+            let k = k.push(Pending::Sequence { rest, scope: scope.clone() });
+
+            State::Eval(first.to_owned(), scope, k)
+        }
+        None => State::Return(Expr::Atom(Box::new(Atom::Nil)), k),
+    }
+}
+
+fn resume(env: &mut Env, pending: Pending, value: Expr, k: Cont) -> Result<State, String> {
+    match pending {
+        Pending::Operator { args, scope } => Ok(operands(value, Vec::new(), args, scope, k)),
+        Pending::Operand { procedure, mut values, rest, scope } => {
+            values.push(value);
+            Ok(next_operand(procedure, values, rest, scope, k))
+        }
+        Pending::ApplyTo { values } => Ok(State::Apply(value, values, k)),
+        Pending::Argument { name, rest, body, scope } => {
+            Frame::insert(&scope, &name, Binding::Value(value));
+            Ok(env::next_argument(scope, rest, body, k))
+        }
+        Pending::Sequence { mut rest, scope } => {
+            let next = rest.pop().unwrap();
+
+            if rest.is_empty() {
+                Ok(State::Eval(next, scope, k))
+            } else {
+                let k = k.push(Pending::Sequence { rest, scope: scope.clone() });
+                Ok(State::Eval(next, scope, k))
+            }
+        }
+        Pending::If { consequent, alternative, scope } => {
+            Ok(boolean::resume_if(value, consequent, alternative, scope, k))
+        }
+        Pending::And { rest, scope } => Ok(boolean::resume_and(value, rest, scope, k)),
+        Pending::Or { rest, scope } => Ok(boolean::resume_or(value, rest, scope, k)),
+        Pending::When { body, negate, scope } => {
+            Ok(boolean::resume_when(value, &body, negate, scope, k))
+        }
+        Pending::Cond { body, rest, scope } => boolean::resume_cond(value, &body, rest, scope, k),
+        Pending::Case { clauses, scope } => boolean::resume_case(value, &clauses, scope, k),
+        Pending::Define { name, scope } => Ok(built_in::resume_define(value, &name, &scope, k)),
+        Pending::Set { name, scope } => built_in::resume_set(value, &name, &scope, k),
+        Pending::Let { names, mut values, rest, body, scope } => {
+            values.push(value);
+            Ok(binding::resume_let(names, values, rest, body, scope, k))
+        }
+        Pending::LetStar { name, rest, body, scope } => {
+            Ok(binding::resume_let_star(value, name, rest, body, scope, k))
+        }
+        Pending::Letrec { names, mut values, rest, body, star, scope } => {
+            values.push(value);
+            Ok(binding::resume_letrec(names, values, rest, body, star, scope, k))
+        }
+        Pending::LetValues { formals, rest, mut bound, body, star, scope } => {
+            let scope = values::take_value(value, formals, &mut bound, star, scope)?;
+            values::resume_let_values(rest, bound, body, star, scope, k)
+        }
+        Pending::DefineValues { formals, scope } => {
+            values::bind_formals(&scope, &formals, value)?;
+            Ok(State::Return(Expr::Atom(Box::new(Atom::Nil)), k))
+        }
+        Pending::SpreadTo { consumer } => Ok(State::Apply(consumer, values::spread(value), k)),
+        Pending::Force { promise, delay } => promise::resume_force(value, promise, delay, k),
+        Pending::StreamCell { op } => stream::resume_stream_op(value, op, k),
+        Pending::StreamToList { items, limit } => stream::resume_to_list(value, items, limit, k),
+        Pending::StreamElement { mut items, rest, limit } => {
+            items.push(value);
+            Ok(stream::next_element(rest, items, limit, k))
+        }
+        Pending::GeneratorDone { generator } => Ok(generator::finish(env, generator)),
+        Pending::GeneratorToList { generator, mut items, limit } => {
+            if generator::is_eof(&value) {
+                return Ok(State::Return(Expr::List(items), k));
+            }
+
+            items.push(value);
+            Ok(generator::next_generated(generator, items, limit, k))
+        }
+        Pending::Member { item, list, index, compare, assoc } => {
+            Ok(equality::resume_member(value, item, list, index, compare, assoc, k))
+        }
+        Pending::StringIndex { chars, index, predicate } => {
+            Ok(string::resume_string_index(value, chars, index, predicate, k))
+        }
+        Pending::CharsToString => Ok(State::Return(string::chars_to_string(value)?, k)),
+        Pending::Map { procedure, calls, mut results, collect } => {
+            if collect {
+                results.push(value);
+            }
+
+            Ok(list::next_call(procedure, calls, results, collect, k))
+        }
+        Pending::WindBefore { before, thunk, after } => {
+            Ok(control::enter_wind(env, before, thunk, after, k))
+        }
+        Pending::WindBody => Ok(control::leave_wind(env, value, k)),
+        Pending::Restore { value } => Ok(State::Return(value, k)),
+        Pending::Rewind { steps, target, value } => Ok(control::rewind(env, steps, target, value)),
+        Pending::MakeParameter { converter } => {
+            Ok(parameter::resume_make_parameter(value, converter, k))
+        }
+        Pending::Parameterize { mut values, rest, body, scope } => {
+            values.push(value);
+            parameter::next_binding_expr(env, values, rest, body, scope, k)
+        }
+        Pending::ParameterConvert { parameter, pending, mut bound, body, scope } => {
+            bound.push((parameter, value));
+            Ok(parameter::next_conversion(env, pending, bound, body, scope, k))
+        }
+        Pending::RestoreParameters { parameters } => {
+            env.set_parameters(parameters);
+            Ok(State::Return(value, k))
+        }
+        Pending::Handled { handlers, continuable } => {
+            exception::resume_handled(env, value, handlers, continuable, k)
+        }
+        Pending::RestoreHandlers { handlers } => {
+            env.set_handlers(handlers);
+            Ok(State::Return(value, k))
+        }
+        Pending::GuardClauses { var, clauses, scope, reraise } => {
+            exception::resume_guard(env, value, &var, clauses, &scope, reraise, k)
+        }
+        Pending::Reraise => exception::raise(env, value, true, k),
+    }
+}
+
+// Starts evaluating the arguments of a call once the operator is known.
+fn operands(procedure: Expr, values: Vec<Expr>, args: Vec<Expr>, scope: Scope, k: Cont) -> State {
+    let rest = args.into_iter().rev().collect();
+
+    next_operand(procedure, values, rest, scope, k)
+}
+
+/**
+ * Evaluates the next argument of a call, or makes the call once there are
+ * none left.
+ *
+ * @param procedure Value of the operator.
+ * @param values Arguments evaluated so far.
+ * @param rest Arguments left to evaluate, in reverse.
+ * @param scope Frame the arguments are evaluated in.
+ * @param k Continuation of the call.
+ * @return Next step of the evaluator.
+ */
+pub fn next_operand(
+    procedure: Expr,
+    values: Vec<Expr>,
+    mut rest: Vec<Expr>,
+    scope: Scope,
+    k: Cont,
+) -> State {
+    match rest.pop() {
+        Some(next) => {
+            let k = k.push(Pending::Operand {
+                procedure,
+                values,
+                rest,
+                scope: scope.clone(),
+            });
+
+            State::Eval(next, scope, k)
+        }
+        None => State::Apply(procedure, values, k),
+    }
+}
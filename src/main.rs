@@ -1,11 +1,33 @@
+mod binding;
 mod boolean;
 mod built_in;
+mod control;
+mod convert;
 mod env;
+mod environment;
+mod equality;
+mod exception;
+mod format;
+mod generator;
 mod lex;
+mod list;
+mod machine;
 mod math;
+mod parameter;
+mod params;
+mod port;
+mod primitive;
+mod promise;
+mod random;
+mod record;
+mod regex;
+mod regexp;
+mod stream;
+mod string;
 mod syntax;
 mod sys;
 mod test;
+mod values;
 
 use crate::env::{Env, Eval};
 use crate::syntax::*;
@@ -43,7 +65,7 @@ fn repl_mode() {
     print!("Mini-Scheme Version {}\n", VERSION);
 
     // Holds all the predefined functions and values for REPL session.
-    let mut env: Env = Box::new(HashMap::new());
+    let mut env: Env = Env::new();
     let mut failed = false;
     let mut line_reader = rustyline::DefaultEditor::new().expect("REASON");
 
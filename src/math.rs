@@ -1,15 +1,21 @@
-use crate::env::{Env, Eval};
-use crate::syntax::{Atom, Expr};
-use bigdecimal::BigDecimal;
+use crate::env::Env;
+use crate::parameter::{self, DEFAULT_PRECISION};
+use crate::syntax::{self, Atom, Expr};
+use crate::values;
+use bigdecimal::num_bigint::{BigInt, Sign, ToBigInt};
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
 use core::ops::Rem;
 
 pub trait MathOps {
-    fn eq(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn add(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn sub(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn mul(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn div(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
-    fn modulo(&mut self, args: &Vec<Expr>) -> Result<Expr, String>;
+    fn eq(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn add(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn sub(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn mul(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn div(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn modulo(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn exact_integer_sqrt(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn floor_div(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn truncate_div(&mut self, args: &[Expr]) -> Result<Expr, String>;
 }
 
 struct OpInfo {
@@ -18,138 +24,80 @@ struct OpInfo {
     default: BigDecimal,
 }
 
+// Arguments of every operator are already evaluated.
 impl MathOps for Env {
-    fn eq(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
+    fn eq(&mut self, args: &[Expr]) -> Result<Expr, String> {
         let first = args
             .first()
-            .map(|x| Result::Ok(x))
-            .unwrap_or(Result::Err(
-                "Operator '=' must have at least one argument.".to_string(),
-            ))
-            .and_then(|x| self.simplify(x));
-
-        match first {
-            Ok(s) => {
-                let mut is_eq = true;
-
-                // Loop through all of them and make sure
-                // they are equal to each other.
-                for expr in args[1..].iter() {
-                    let simple_exp = self.simplify(&expr);
-
-                    match simple_exp {
-                        Ok(v) => {
-                            is_eq = is_eq && s == v;
-                        }
-                        Err(msg) => {
-                            return Err(msg);
-                        }
-                    }
-                }
-
-                Result::Ok(Expr::Atom(Box::new(Atom::Boolean(is_eq))))
-            }
-            Err(msg) => Result::Err(msg),
+            .ok_or_else(|| "Operator '=' must have at least one argument.".to_string())?;
+        let first = number_arg(first, "=")?;
+        let mut is_eq = true;
+
+        // Make sure all of them are equal to the first one, every argument
+        // must still be a number.
+        for expr in args[1..].iter() {
+            is_eq &= number_arg(expr, "=")? == first;
         }
+
+        Ok(Expr::Atom(Box::new(Atom::Boolean(is_eq))))
     }
 
     // elementary functions of math.
 
-    fn add(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        //dbg!("MADE IT TO THE ADD FUNCTION");
-        let mut total: Box<BigDecimal> = Box::new(BigDecimal::from(0));
-
-        for expr in args.into_iter() {
-            //dbg!("TIME TO SIMPLIFY");
-            let simple_tree = self.simplify(expr);
-            //dbg!("DONE SIMPLIFYING");
-
-            if simple_tree.is_ok() {
-                let number = match simple_tree.unwrap() {
-                    Expr::Atom(atom) => match *atom {
-                        Atom::Number(n) => Result::Ok(n),
-                        _ => Result::Err(
-                            "Non-number atom cannot have operator '+' applied to it.".to_string(),
-                        ),
-                    },
-                    Expr::List(_) => {
-                        Result::Err("List cannot have operator '+' applied to it.".to_string())
-                    }
-                };
-
-                match number {
-                    Ok(v) => {
-                        *total += v;
-                    }
-                    Err(msg) => {
-                        return Result::Err(msg);
-                    }
-                }
-            } else {
-                return Result::Err("Error".to_string());
-            }
+    fn add(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let mut total = BigDecimal::from(0);
+
+        for expr in args.iter() {
+            total += number_arg(expr, "+")?;
         }
 
-        Result::Ok(Expr::Atom(Box::new(Atom::Number(*total))))
+        Ok(Expr::Atom(Box::new(Atom::Number(total))))
     }
 
-    fn sub(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
+    fn sub(&mut self, args: &[Expr]) -> Result<Expr, String> {
         let op_info = OpInfo {
             name: String::from("-"),
             op_fn: |a, b| a - b,
             default: BigDecimal::from(0),
         };
 
-        return apply_neg_op(&self, args, &op_info);
+        apply_neg_op(args, &op_info)
     }
 
-    fn mul(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        let mut total: BigDecimal = BigDecimal::from(1);
-
-        for expr in args.into_iter() {
-            let simple_tree = self.simplify(expr);
-
-            match simple_tree {
-                Ok(v) => {
-                    let number = match v {
-                        Expr::Atom(atom) => match *atom {
-                            Atom::Number(n) => Result::Ok(n),
-                            _ => Result::Err(
-                                "Non-number atom cannot have operator '*' applied to it."
-                                    .to_string(),
-                            ),
-                        },
-                        Expr::List(_) => {
-                            Result::Err("List cannot have operator '*' applied to it.".to_string())
-                        }
-                    };
-
-                    if number.is_ok() {
-                        total = total * number.unwrap();
-                    } else {
-                        return Result::Err("Error".to_string());
-                    }
-                }
-                Err(msg) => {
-                    return Err(msg);
-                }
-            }
+    fn mul(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let mut total = BigDecimal::from(1);
+
+        for expr in args.iter() {
+            total *= number_arg(expr, "*")?;
         }
 
-        Result::Ok(Expr::Atom(Box::new(Atom::Number(total))))
+        Ok(Expr::Atom(Box::new(Atom::Number(total))))
     }
 
-    fn div(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
-        let op_info = OpInfo {
-            name: String::from("/"),
-            op_fn: |a, b| a / b,
-            default: BigDecimal::from(1),
+    // Quotients keep as many significant digits as 'numeric-precision'.
+    fn div(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let precision = current_precision(self);
+        let (first, rest) = args
+            .split_first()
+            .ok_or_else(|| "Incorrect argument count for '/' operator.".to_string())?;
+        let first = number_arg(first, "/")?;
+
+        let total = if rest.is_empty() {
+            divide(&BigDecimal::from(1), &first, precision)?
+        } else {
+            let mut total = first;
+
+            for expr in rest.iter() {
+                total = divide(&total, &number_arg(expr, "/")?, precision)?;
+            }
+
+            total
         };
 
-        return apply_neg_op(&self, args, &op_info);
+        Ok(Expr::Atom(Box::new(Atom::Number(total))))
     }
 
-    fn modulo(&mut self, args: &Vec<Expr>) -> Result<Expr, String> {
+    fn modulo(&mut self, args: &[Expr]) -> Result<Expr, String> {
         if args.len() == 2 {
             // Specifies default but it'll never actually be used.
             let op_info = OpInfo {
@@ -158,101 +106,177 @@ impl MathOps for Env {
                 default: BigDecimal::from(69),
             };
 
-            let result = apply_neg_op(self, args, &op_info);
-
-            return result;
+            apply_neg_op(args, &op_info)
         } else {
-            return Err("Incorrect argument count for '%' operator.".to_string());
+            Err("Incorrect argument count for '%' operator.".to_string())
+        }
+    }
+
+    // Returns the largest s with s * s <= n, and n - s * s.
+    fn exact_integer_sqrt(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() != 1 {
+            return Err("Incorrect argument count for 'exact-integer-sqrt' function.".to_string());
         }
+
+        let n = integer_arg(&args[0], syntax::EXACT_INT_SQRT_FN)?;
+
+        if n.sign() == Sign::Minus {
+            return Err("Argument of 'exact-integer-sqrt' must not be negative.".to_string());
+        }
+
+        let root = n.sqrt();
+        let rest = &n - &root * &root;
+
+        Ok(values::multiple(vec![integer_value(root), integer_value(rest)]))
+    }
+
+    // Quotient rounded down, the remainder has the sign of the divisor.
+    fn floor_div(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (mut quotient, mut remainder, divisor) = truncated(args, syntax::FLOOR_DIV_FN)?;
+
+        if !remainder.is_zero() && remainder.sign() != divisor.sign() {
+            quotient -= 1;
+            remainder += divisor;
+        }
+
+        Ok(values::multiple(vec![integer_value(quotient), integer_value(remainder)]))
+    }
+
+    // Quotient rounded toward zero, the remainder has the sign of the dividend.
+    fn truncate_div(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (quotient, remainder, _) = truncated(args, syntax::TRUNCATE_DIV_FN)?;
+
+        Ok(values::multiple(vec![integer_value(quotient), integer_value(remainder)]))
     }
 }
 
-fn apply_neg_op(env: &Env, args: &Vec<Expr>, info: &OpInfo) -> Result<Expr, String> {
-    let mut local_env = env.to_owned();
-    let total = args
+// Truncated quotient and remainder of two integers, along with the divisor.
+fn truncated(args: &[Expr], op_name: &str) -> Result<(BigInt, BigInt, BigInt), String> {
+    if args.len() != 2 {
+        return Err(format!("Incorrect argument count for '{}' function.", op_name));
+    }
+
+    let dividend = integer_arg(&args[0], op_name)?;
+    let divisor = integer_arg(&args[1], op_name)?;
+
+    if divisor.is_zero() {
+        return Err(format!("Division by zero in '{}' function.", op_name));
+    }
+
+    Ok((&dividend / &divisor, &dividend % &divisor, divisor))
+}
+
+fn integer_arg(expr: &Expr, op_name: &str) -> Result<BigInt, String> {
+    let n = number_arg(expr, op_name)?;
+
+    if !n.is_integer() {
+        return Err(format!("Operator '{}' only accepts integers.", op_name));
+    }
+
+    Ok(n.to_bigint().unwrap())
+}
+
+/**
+ * Divides two numbers, rounding the quotient to a number of significant
+ * digits. Quotients that need fewer digits are exact.
+ *
+ * @param dividend Number being divided.
+ * @param divisor Number to divide by.
+ * @param precision Most significant digits of the quotient.
+ * @return The quotient, or an error when dividing by zero.
+ */
+fn divide(dividend: &BigDecimal, divisor: &BigDecimal, precision: u64) -> Result<BigDecimal, String> {
+    if divisor.is_zero() {
+        return Err("Division by zero in '/' operator.".to_string());
+    }
+
+    if dividend.is_zero() {
+        return Ok(BigDecimal::zero());
+    }
+
+    let (a, a_scale) = dividend.as_bigint_and_exponent();
+    let (b, b_scale) = divisor.as_bigint_and_exponent();
+
+    // Shift the dividend so the quotient has a digit more than needed,
+    // which the rounding looks at.
+    let shift = (precision + divisor.digits() + 1).saturating_sub(dividend.digits());
+    let quotient = a * BigInt::from(10).pow(shift as u32) / b;
+    let quotient = BigDecimal::new(quotient, a_scale - b_scale + shift as i64);
+
+    Ok(quotient.with_prec(precision).normalized())
+}
+
+// Digits kept by division in the current dynamic extent.
+fn current_precision(env: &Env) -> u64 {
+    let value = parameter::current_value(env, &env.settings().precision);
+
+    match value {
+        Expr::Atom(atom) => match &*atom {
+            Atom::Number(n) => n.to_u64().unwrap_or(DEFAULT_PRECISION),
+            _ => DEFAULT_PRECISION,
+        },
+        Expr::List(_) => DEFAULT_PRECISION,
+    }
+}
+
+fn integer_value(n: BigInt) -> Expr {
+    Expr::Atom(Box::new(Atom::Number(BigDecimal::from(n))))
+}
+
+// With a single argument the operator is applied to its default and the
+// argument, so (- 5) is -5 and (/ 5) is 1/5.
+fn apply_neg_op(args: &[Expr], info: &OpInfo) -> Result<Expr, String> {
+    let first = args
         .first()
-        .ok_or(format!("Incorrect argument count for '{}' operator.", info.name).to_string())
-        .and_then(|x| local_env.simplify(x))
+        .ok_or_else(|| format!("Incorrect argument count for '{}' operator.", info.name))
         .and_then(|x| match x {
-            Expr::Atom(atom) => match *atom {
+            Expr::Atom(atom) => match &**atom {
                 Atom::Number(n) => Ok(n.to_owned()),
                 _ => Err(format!(
                     "Cannot perform '{}' operator on non-numeric type.",
                     info.name
-                )
-                .to_string()),
+                )),
             },
             Expr::List(_) => Err(format!(
                 "Cannot perform '{}' operator on non-numeric type.",
                 info.name
-            )
-            .to_string()),
-        });
+            )),
+        })?;
 
-    if args.len() > 1 {
-        let result = total.and_then(|n| {
-            apply_first_rest(&local_env, n, &args[1..].to_vec(), info.op_fn, &info.name)
-        });
+    let total = if args.len() > 1 {
+        let mut total = first;
 
-        return result;
+        for expr in args[1..].iter() {
+            total = (info.op_fn)(&total, &number_arg(expr, &info.name)?);
+        }
+
+        total
     } else {
-        let result = total
-            // .map(|x| Ok(Expr::Atom(Atom::Number(BigDecimal::from(1) / x))))
-            .map(|x| Ok(Expr::Atom(Box::new(Atom::Number((info.op_fn)(&info.default, &x))))))
-            .unwrap_or(Err(format!(
-                "Cannot perform '{}' operator on non-numeric type.",
-                info.name
-            )
-            .to_string()));
+        (info.op_fn)(&info.default, &first)
+    };
 
-        return result;
-    }
+    Ok(Expr::Atom(Box::new(Atom::Number(total))))
 }
 
-fn apply_first_rest(
-    env: &Env,
-    car: BigDecimal,
-    cdr: &Vec<Expr>,
-    op: fn(&BigDecimal, &BigDecimal) -> BigDecimal,
-    op_name: &str,
-) -> Result<Expr, String> {
-    let mut total = car;
-    let mut local_env = env.clone();
-
-    for expr in cdr.into_iter() {
-        let simple_tree = local_env.simplify(expr);
-
-        match simple_tree {
-            Ok(tree) => {
-                let number = match tree {
-                    Expr::Atom(atom) => match *atom {
-                        Atom::Number(n) => Result::Ok(n),
-                        _ => Result::Err(
-                            format!(
-                                "Non-number atom cannot have operator '{}' applied to it.",
-                                op_name
-                            )
-                            .to_string(),
-                        ),
-                    },
-                    Expr::List(_) => Result::Err(
-                        format!("List cannot have operator '{}' applied to it.", op_name)
-                            .to_string(),
-                    ),
-                };
-
-                match number {
-                    Ok(n) => {
-                        total = op(&total, &n);
-                    }
-                    Err(msg) => {
-                        return Result::Err(msg);
-                    }
-                }
-            }
-            Err(msg) => return Result::Err(msg),
-        };
+/**
+ * Gets the number out of an argument of a math operator.
+ *
+ * @param expr Evaluated argument.
+ * @param op_name Name of the operator, used in error messages.
+ * @return Number held by the argument.
+ */
+pub fn number_arg(expr: &Expr, op_name: &str) -> Result<BigDecimal, String> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Number(n) => Ok(n.to_owned()),
+            _ => Err(format!(
+                "Non-number atom cannot have operator '{}' applied to it.",
+                op_name
+            )),
+        },
+        Expr::List(_) => Err(format!(
+            "List cannot have operator '{}' applied to it.",
+            op_name
+        )),
     }
-
-    Result::Ok(Expr::Atom(Box::new(Atom::Number(total))))
 }
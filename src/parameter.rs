@@ -0,0 +1,359 @@
+use crate::control::Chained;
+use crate::env::{Env, Frame, Scope};
+use crate::machine::{self, Cont, Pending, State};
+use crate::port::{self, Port};
+use crate::primitive::Primitive;
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use std::fmt;
+use std::rc::Rc;
+
+pub trait ParameterOps {
+    fn make_parameter(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn parameterize(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+}
+
+/**
+ * Procedure without arguments whose value 'parameterize' can change for
+ * the time its body runs. Values given to the parameter are passed
+ * through its converter first, if it has one.
+ */
+#[derive(Clone)]
+pub struct Parameter(Rc<ParameterDef>);
+
+struct ParameterDef {
+    value: Expr,
+    converter: Option<Expr>,
+}
+
+/**
+ * Values given to parameters by the 'parameterize' forms control is
+ * currently inside of, innermost first. Like the 'dynamic-wind' blocks,
+ * the list is shared rather than copied.
+ */
+pub type Parameterization = Option<Rc<ParameterBinding>>;
+
+pub struct ParameterBinding {
+    parameter: Parameter,
+    value: Expr,
+    parent: Parameterization,
+}
+
+impl Chained for ParameterBinding {
+    fn parent(&self) -> &Parameterization {
+        &self.parent
+    }
+
+    fn with_parent(&self, parent: Parameterization) -> Self {
+        ParameterBinding {
+            parameter: self.parameter.clone(),
+            value: self.value.clone(),
+            parent,
+        }
+    }
+}
+
+// Parameter and value of every binding of 'parameterize', in order.
+pub type ParameterBindings = Vec<(Parameter, Expr)>;
+
+/**
+ * Settings of the interpreter, which Scheme code sees as parameters.
+ */
+#[derive(Clone)]
+pub struct Settings {
+    pub output_port: Parameter,
+    pub error_port: Parameter,
+    // Significant digits kept by division.
+    pub precision: Parameter,
+}
+
+pub const DEFAULT_PRECISION: u64 = 100;
+
+impl Parameter {
+    fn new(value: Expr, converter: Option<Expr>) -> Parameter {
+        Parameter(Rc::new(ParameterDef { value, converter }))
+    }
+
+    fn converter(&self) -> Option<Expr> {
+        self.0.converter.clone()
+    }
+}
+
+impl fmt::Debug for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Parameter")
+    }
+}
+
+// Two parameters are only equal if they are the same object.
+impl PartialEq for Parameter {
+    fn eq(&self, other: &Parameter) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Parameter {}
+
+impl Settings {
+    pub fn new() -> Settings {
+        let port_converter = converter(syntax::CURRENT_OUTPUT_PORT_FN, convert_port);
+        let precision = Expr::Atom(Box::new(Atom::Number(BigDecimal::from(DEFAULT_PRECISION))));
+
+        Settings {
+            output_port: Parameter::new(port::port_value(Port::Stdout), Some(port_converter.clone())),
+            error_port: Parameter::new(port::port_value(Port::Stderr), Some(port_converter)),
+            precision: Parameter::new(
+                precision,
+                Some(converter(syntax::NUMERIC_PRECISION_FN, convert_precision)),
+            ),
+        }
+    }
+
+    // Every setting by the name it is bound to.
+    pub fn named(&self) -> Vec<(&'static str, Parameter)> {
+        vec![
+            (syntax::CURRENT_OUTPUT_PORT_FN, self.output_port.clone()),
+            (syntax::CURRENT_ERROR_PORT_FN, self.error_port.clone()),
+            (syntax::NUMERIC_PRECISION_FN, self.precision.clone()),
+        ]
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new()
+    }
+}
+
+impl ParameterOps for Env {
+    // (make-parameter value [converter]), the initial value is converted too.
+    fn make_parameter(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        match args.len() {
+            1 => Ok(State::Return(parameter_value(Parameter::new(args.pop().unwrap(), None)), k)),
+            2 => {
+                let converter = args.pop().unwrap();
+                let k = k.push(Pending::MakeParameter {
+                    converter: converter.to_owned(),
+                });
+
+                Ok(State::Apply(converter, args, k))
+            }
+            _ => Err("Incorrect argument count for 'make-parameter' function.".to_string()),
+        }
+    }
+
+    // (parameterize ((param value)...) body...) gives each parameter its
+    // value while the body runs.
+    fn parameterize(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let bindings = match args.first() {
+            Some(Expr::List(bindings)) => bindings,
+            Some(Expr::Atom(_)) => {
+                return Err("Bindings of 'parameterize' operator must be a list.".to_string())
+            }
+            None => {
+                return Err("Incorrect number of arguments for 'parameterize' operator.".to_string())
+            }
+        };
+
+        // Parameters and values are evaluated in order, before any of the
+        // parameters change.
+        let mut exprs = Vec::new();
+
+        for binding in bindings.iter() {
+            match binding {
+                Expr::List(pair) if pair.len() == 2 => exprs.extend_from_slice(pair),
+                _ => return Err("Invalid binding in 'parameterize' operator.".to_string()),
+            }
+        }
+
+        exprs.reverse();
+
+        next_binding_expr(self, Vec::new(), exprs, args[1..].to_vec(), scope, k)
+    }
+}
+
+// Makes a parameter once its converter has returned the initial value.
+pub fn resume_make_parameter(value: Expr, converter: Expr, k: Cont) -> State {
+    State::Return(parameter_value(Parameter::new(value, Some(converter))), k)
+}
+
+/**
+ * Evaluates the next parameter or value of a 'parameterize', or starts
+ * converting the values once all of them are known.
+ *
+ * @param env Environment holding the current parameter values.
+ * @param values Parameters and values evaluated so far, alternating.
+ * @param rest Expressions left to evaluate, in reverse.
+ * @param body Body of the form.
+ * @param scope Frame the form is evaluated in.
+ * @param k Continuation of the form.
+ * @return Next step of the evaluator.
+ */
+pub fn next_binding_expr(
+    env: &mut Env,
+    values: Vec<Expr>,
+    mut rest: Vec<Expr>,
+    body: Vec<Expr>,
+    scope: Scope,
+    k: Cont,
+) -> Result<State, String> {
+    if let Some(next) = rest.pop() {
+        let k = k.push(Pending::Parameterize {
+            values,
+            rest,
+            body,
+            scope: scope.clone(),
+        });
+
+        return Ok(State::Eval(next, scope, k));
+    }
+
+    let mut pending: ParameterBindings = Vec::new();
+    let mut values = values.into_iter();
+
+    while let (Some(parameter), Some(value)) = (values.next(), values.next()) {
+        match parameter_arg(&parameter) {
+            Some(parameter) => pending.push((parameter, value)),
+            None => {
+                return Err(format!(
+                    "Value '{}' is not a parameter.",
+                    syntax::print_tree(&parameter, &false)
+                ))
+            }
+        }
+    }
+
+    pending.reverse();
+
+    Ok(next_conversion(env, pending, Vec::new(), body, scope, k))
+}
+
+/**
+ * Passes the next value of a 'parameterize' through the converter of its
+ * parameter, or runs the body with the new values once all are converted.
+ *
+ * @param env Environment holding the current parameter values.
+ * @param pending Parameters with values left to convert, in reverse.
+ * @param bound Parameters with their converted values.
+ * @param body Body of the form.
+ * @param scope Frame the form is evaluated in.
+ * @param k Continuation of the form.
+ * @return Next step of the evaluator.
+ */
+pub fn next_conversion(
+    env: &mut Env,
+    mut pending: ParameterBindings,
+    mut bound: ParameterBindings,
+    body: Vec<Expr>,
+    scope: Scope,
+    k: Cont,
+) -> State {
+    while let Some((parameter, value)) = pending.pop() {
+        if let Some(converter) = parameter.converter() {
+            let k = k.push(Pending::ParameterConvert {
+                parameter,
+                pending,
+                bound,
+                body,
+                scope,
+            });
+
+            return State::Apply(converter, vec![value], k);
+        }
+
+        bound.push((parameter, value));
+    }
+
+    // The old values come back when the body returns. Leaving it through
+    // a continuation or an error gives the values of that continuation.
+    let outer = env.parameters();
+    let mut parameters = outer.clone();
+
+    for (parameter, value) in bound {
+        parameters = Some(Rc::new(ParameterBinding {
+            parameter,
+            value,
+            parent: parameters,
+        }));
+    }
+
+    env.set_parameters(parameters);
+
+    let body_scope = Frame::new_scope(Some(&scope));
+    let k = k.push(Pending::RestoreParameters { parameters: outer });
+
+    machine::sequence(&body, body_scope, k)
+}
+
+/**
+ * Value of a parameter in the current dynamic extent.
+ *
+ * @param env Environment holding the current parameter values.
+ * @param parameter Parameter to look up.
+ * @return Value of the innermost 'parameterize' of it, or its initial one.
+ */
+pub fn current_value(env: &Env, parameter: &Parameter) -> Expr {
+    let mut current = env.parameters();
+
+    while let Some(binding) = current {
+        if binding.parameter == *parameter {
+            return binding.value.to_owned();
+        }
+
+        current = binding.parent.clone();
+    }
+
+    parameter.0.value.to_owned()
+}
+
+// Calling a parameter gives its value, it can't be changed by a call.
+pub fn call(env: &Env, parameter: &Parameter, values: &[Expr]) -> Result<Expr, String> {
+    if !values.is_empty() {
+        return Err("Parameter takes no arguments.".to_string());
+    }
+
+    Ok(current_value(env, parameter))
+}
+
+pub fn parameter_value(parameter: Parameter) -> Expr {
+    Expr::Atom(Box::new(Atom::Parameter(parameter)))
+}
+
+fn parameter_arg(expr: &Expr) -> Option<Parameter> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Parameter(parameter) => Some(parameter.clone()),
+            _ => None,
+        },
+        Expr::List(_) => None,
+    }
+}
+
+// Builtin procedure used as the converter of a setting.
+fn converter(name: &'static str, func: fn(&mut Env, &[Expr]) -> Result<Expr, String>) -> Expr {
+    Expr::Atom(Box::new(Atom::Primitive(Primitive::plain(name, func))))
+}
+
+fn convert_port(_env: &mut Env, args: &[Expr]) -> Result<Expr, String> {
+    match args {
+        [value] if port::port_arg(value).is_some() => Ok(value.to_owned()),
+        _ => Err("Value of a port parameter must be an output port.".to_string()),
+    }
+}
+
+fn convert_precision(_env: &mut Env, args: &[Expr]) -> Result<Expr, String> {
+    if let [Expr::Atom(atom)] = args {
+        if let Atom::Number(n) = &**atom {
+            if let (true, Some(digits)) = (n.is_integer(), n.to_u64()) {
+                if digits > 0 {
+                    return Ok(args[0].to_owned());
+                }
+            }
+        }
+    }
+
+    Err(format!(
+        "Value of '{}' must be a positive integer.",
+        syntax::NUMERIC_PRECISION_FN
+    ))
+}
@@ -0,0 +1,192 @@
+use crate::syntax::{Atom, Expr};
+
+// markers in extended parameter lists
+pub const KEY_MARKER: &str = "#!key";
+pub const REST_MARKER: &str = "#!rest";
+pub const DOT_MARKER: &str = ".";
+
+/**
+ * Parameter list of a function.
+ *
+ * Plain 'lambda' only has required parameters and a rest parameter, either
+ * as (a b . rest) or as a single symbol that takes every argument.
+ * 'lambda*' adds optional positional parameters written as (name default)
+ * and keyword parameters after '#!key', passed as 'name: value' pairs.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Params {
+    pub required: Vec<String>,
+    pub optional: Vec<(String, Expr)>,
+    pub keys: Vec<(String, Expr)>,
+    pub rest: Option<String>,
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Positional,
+    Optional,
+    Key,
+}
+
+impl Params {
+    /**
+     * Reads the parameter list of a function definition.
+     *
+     * @param expr Parameter list as written in the source.
+     * @param extended Whether optional and keyword parameters are allowed.
+     * @return Parameters grouped by kind.
+     */
+    pub fn parse(expr: &Expr, extended: bool) -> Result<Params, String> {
+        let mut params = Params::default();
+
+        let list = match expr {
+            Expr::List(list) => list,
+            Expr::Atom(_) => {
+                params.rest = Some(param_name(expr)?);
+                return Ok(params);
+            }
+        };
+
+        let mut section = Section::Positional;
+        let mut iter = list.iter();
+
+        while let Some(item) = iter.next() {
+            match item {
+                Expr::Atom(atom) => match &**atom {
+                    Atom::Symbol(s) if s == DOT_MARKER || (extended && s == REST_MARKER) => {
+                        let rest = iter
+                            .next()
+                            .ok_or_else(|| "Rest parameter is missing a name.".to_string())?;
+
+                        if iter.next().is_some() {
+                            return Err("Rest parameter must be the last parameter.".to_string());
+                        }
+
+                        params.rest = Some(param_name(rest)?);
+                    }
+                    Atom::Symbol(s) if extended && s == KEY_MARKER => {
+                        section = Section::Key;
+                    }
+                    _ => match section {
+                        Section::Positional => params.required.push(param_name(item)?),
+                        Section::Optional => {
+                            return Err(
+                                "Required parameter cannot follow an optional one.".to_string()
+                            )
+                        }
+                        // Keyword parameters without a default are false.
+                        Section::Key => params.keys.push((
+                            param_name(item)?,
+                            Expr::Atom(Box::new(Atom::Boolean(false))),
+                        )),
+                    },
+                },
+                Expr::List(pair) if extended && pair.len() == 2 => {
+                    let param = (param_name(&pair[0])?, pair[1].to_owned());
+
+                    if section == Section::Key {
+                        params.keys.push(param);
+                    } else {
+                        section = Section::Optional;
+                        params.optional.push(param);
+                    }
+                }
+                Expr::List(_) => return Err("List cannot be parameter name.".to_string()),
+            }
+        }
+
+        match params.duplicate() {
+            Some(name) => Err(format!("Parameter '{}' is declared more than once.", name)),
+            None => Ok(params),
+        }
+    }
+
+    // Whether a call with this many arguments can be bound, keywords aside.
+    pub fn accepts(&self, count: usize) -> bool {
+        let min = self.required.len();
+        let max = min + self.optional.len();
+
+        count >= min && (self.rest.is_some() || !self.keys.is_empty() || count <= max)
+    }
+
+    /**
+     * Describes how many arguments the function takes.
+     *
+     * @return Text like "exactly 2", "at least 1" or "between 1 and 3".
+     */
+    pub fn arity(&self) -> String {
+        let min = self.required.len();
+        let max = min + self.optional.len();
+
+        if self.rest.is_some() || !self.keys.is_empty() {
+            format!("at least {}", min)
+        } else if max > min {
+            format!("between {} and {}", min, max)
+        } else {
+            format!("exactly {}", min)
+        }
+    }
+
+    // Parameter list as it would be written in the source.
+    pub fn to_expr(&self) -> Expr {
+        let symbol = |s: &str| Expr::Atom(Box::new(Atom::Symbol(s.to_string())));
+        let mut list: Vec<Expr> = self.required.iter().map(|x| symbol(x)).collect();
+
+        for (name, default) in self.optional.iter() {
+            list.push(Expr::List(vec![symbol(name), default.to_owned()]));
+        }
+
+        if !self.keys.is_empty() {
+            list.push(symbol(KEY_MARKER));
+
+            for (name, default) in self.keys.iter() {
+                list.push(Expr::List(vec![symbol(name), default.to_owned()]));
+            }
+        }
+
+        match &self.rest {
+            Some(rest) if list.is_empty() => symbol(rest),
+            Some(rest) => {
+                list.push(symbol(DOT_MARKER));
+                list.push(symbol(rest));
+                Expr::List(list)
+            }
+            None => Expr::List(list),
+        }
+    }
+
+    fn duplicate(&self) -> Option<String> {
+        let mut names: Vec<&String> = self
+            .required
+            .iter()
+            .chain(self.optional.iter().map(|(name, _)| name))
+            .chain(self.keys.iter().map(|(name, _)| name))
+            .chain(self.rest.iter())
+            .collect();
+
+        names.sort();
+        names.windows(2).find(|x| x[0] == x[1]).map(|x| x[0].to_owned())
+    }
+}
+
+/**
+ * Describes the accepted argument counts of several parameter lists, as
+ * used for the clauses of 'case-lambda'.
+ */
+pub fn arities(params: &[&Params]) -> String {
+    params
+        .iter()
+        .map(|x| x.arity())
+        .collect::<Vec<String>>()
+        .join(" or ")
+}
+
+fn param_name(expr: &Expr) -> Result<String, String> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Symbol(s) => Ok(s.to_owned()),
+            _ => Err("Parameter name must be valid symbol.".to_string()),
+        },
+        Expr::List(_) => Err("List cannot be parameter name.".to_string()),
+    }
+}
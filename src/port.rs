@@ -0,0 +1,105 @@
+use crate::env::Env;
+use crate::parameter;
+use crate::syntax::{self, Atom, Expr};
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+pub trait PortOps {
+    fn open_output_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn get_output_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+/**
+ * Destination that text is written to. A string port keeps what is
+ * written to it until 'get-output-string' reads it back.
+ */
+#[derive(Clone)]
+pub enum Port {
+    Stdout,
+    Stderr,
+    String(Rc<RefCell<String>>),
+}
+
+impl fmt::Debug for Port {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Port::Stdout => write!(f, "Port(stdout)"),
+            Port::Stderr => write!(f, "Port(stderr)"),
+            Port::String(_) => write!(f, "Port(string)"),
+        }
+    }
+}
+
+// String ports are only equal if they are the same object.
+impl PartialEq for Port {
+    fn eq(&self, other: &Port) -> bool {
+        match (self, other) {
+            (Port::Stdout, Port::Stdout) | (Port::Stderr, Port::Stderr) => true,
+            (Port::String(a), Port::String(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Port {}
+
+impl Port {
+    pub fn write_str(&self, text: &str) {
+        match self {
+            Port::Stdout => {
+                print!("{}", text);
+                io::stdout().flush().ok();
+            }
+            Port::Stderr => eprint!("{}", text),
+            Port::String(buffer) => buffer.borrow_mut().push_str(text),
+        }
+    }
+}
+
+impl PortOps for Env {
+    fn open_output_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if !args.is_empty() {
+            return Err("Incorrect argument count for 'open-output-string' function.".to_string());
+        }
+
+        Ok(port_value(Port::String(Rc::new(RefCell::new(String::new())))))
+    }
+
+    fn get_output_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [port] => match port_arg(port) {
+                Some(Port::String(buffer)) => {
+                    Ok(Expr::Atom(Box::new(Atom::StringLiteral(buffer.borrow().to_owned()))))
+                }
+                _ => Err("Argument of 'get-output-string' must be a string port.".to_string()),
+            },
+            _ => Err(format!(
+                "Incorrect argument count for '{}' function.",
+                syntax::GET_OUTPUT_STRING_FN
+            )),
+        }
+    }
+}
+
+// Port that output goes to in the current dynamic extent.
+pub fn current_output_port(env: &Env) -> Port {
+    let value = parameter::current_value(env, &env.settings().output_port);
+
+    port_arg(&value).unwrap_or(Port::Stdout)
+}
+
+pub fn port_value(port: Port) -> Expr {
+    Expr::Atom(Box::new(Atom::Port(port)))
+}
+
+pub fn port_arg(expr: &Expr) -> Option<Port> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Port(port) => Some(port.clone()),
+            _ => None,
+        },
+        Expr::List(_) => None,
+    }
+}
@@ -0,0 +1,55 @@
+; Library procedures written in Scheme, evaluated in every new environment.
+
+; Streams, SRFI-41
+
+(define stream-null (make-promise '()))
+
+(define (stream-map f s)
+  (delay-force
+    (if (stream-null? s)
+        stream-null
+        (stream-cons (f (stream-car s)) (stream-map f (stream-cdr s))))))
+
+(define (stream-filter keep? s)
+  (delay-force
+    (cond ((stream-null? s) stream-null)
+          ((keep? (stream-car s))
+           (stream-cons (stream-car s) (stream-filter keep? (stream-cdr s))))
+          (else (stream-filter keep? (stream-cdr s))))))
+
+(define (stream-take n s)
+  (delay-force
+    (if (or (= n 0) (stream-null? s))
+        stream-null
+        (stream-cons (stream-car s) (stream-take (- n 1) (stream-cdr s))))))
+
+; Generators, SRFI-158
+
+(define (gmap f . gens)
+  (lambda ()
+    (let ((vals (map (lambda (g) (g)) gens)))
+      (if (call/cc (lambda (return) (for-each (lambda (v) (if (eof-object? v) (return #t))) vals) #f))
+          (eof-object)
+          (apply f vals)))))
+
+(define (gfilter keep? gen)
+  (lambda ()
+    (let loop ((v (gen)))
+      (if (or (eof-object? v) (keep? v)) v (loop (gen))))))
+
+(define* (gtake gen k (padding (eof-object)))
+  (lambda ()
+    (if (= k 0)
+        (eof-object)
+        (let ((v (gen)))
+          (set! k (- k 1))
+          (if (eof-object? v) padding v)))))
+
+(define* (make-iota-generator count (start 0) (step 1))
+  (let ((i 0))
+    (lambda ()
+      (if (= i count)
+          (eof-object)
+          (let ((v (+ start (* i step))))
+            (set! i (+ i 1))
+            v)))))
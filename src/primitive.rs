@@ -0,0 +1,263 @@
+use crate::boolean::LogicOps;
+use crate::built_in::EnvPrimitives;
+use crate::control::ControlOps;
+use crate::convert::ConvertOps;
+use crate::env::{Binding, Env, Frame, Scope};
+use crate::environment::EnvironmentOps;
+use crate::equality::EqualityOps;
+use crate::exception::ExceptionOps;
+use crate::format::FormatOps;
+use crate::generator::GeneratorOps;
+use crate::lex;
+use crate::list::ListOps;
+use crate::machine::{Cont, State};
+use crate::math::MathOps;
+use crate::parameter::{self, ParameterOps};
+use crate::port::PortOps;
+use crate::promise::PromiseOps;
+use crate::random::RandomOps;
+use crate::regexp::RegexpOps;
+use crate::stream::StreamOps;
+use crate::string::StringOps;
+use crate::syntax::{self, Atom, Expr};
+use crate::sys::EnvSys;
+use crate::values::ValuesOps;
+use std::fmt;
+
+/**
+ * Implementation of a builtin procedure, the arguments are already
+ * evaluated. Most builtins just compute a value, control builtins get the
+ * continuation of the call and decide how evaluation goes on.
+ */
+#[derive(Clone, Copy)]
+pub enum PrimitiveFn {
+    Plain(fn(&mut Env, &[Expr]) -> Result<Expr, String>),
+    Control(fn(&mut Env, Vec<Expr>, Cont) -> Result<State, String>),
+}
+
+/**
+ * Procedure implemented by the interpreter itself.
+ *
+ * Primitives are ordinary values bound in the global frame, so they can be
+ * passed around and stored, and rebinding their name doesn't change the
+ * value that was captured before.
+ */
+#[derive(Clone, Copy)]
+pub struct Primitive {
+    pub name: &'static str,
+    pub func: PrimitiveFn,
+}
+
+impl Primitive {
+    pub const fn plain(name: &'static str, func: fn(&mut Env, &[Expr]) -> Result<Expr, String>) -> Primitive {
+        Primitive {
+            name,
+            func: PrimitiveFn::Plain(func),
+        }
+    }
+
+    const fn control(
+        name: &'static str,
+        func: fn(&mut Env, Vec<Expr>, Cont) -> Result<State, String>,
+    ) -> Primitive {
+        Primitive {
+            name,
+            func: PrimitiveFn::Control(func),
+        }
+    }
+}
+
+impl fmt::Debug for Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Primitive({})", self.name)
+    }
+}
+
+// Names are unique in the registry, so they identify the primitive.
+impl PartialEq for Primitive {
+    fn eq(&self, other: &Primitive) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Primitive {}
+
+// Every builtin procedure by the name it is bound to.
+const PRIMITIVES: &[Primitive] = &[
+    Primitive::plain(syntax::EQ_OP, <Env as MathOps>::eq),
+    Primitive::plain(syntax::ADD_OP, <Env as MathOps>::add),
+    Primitive::plain(syntax::SUB_OP, <Env as MathOps>::sub),
+    Primitive::plain(syntax::MUL_OP, <Env as MathOps>::mul),
+    Primitive::plain(syntax::DIV_OP, <Env as MathOps>::div),
+    Primitive::plain(syntax::MOD_OP, <Env as MathOps>::modulo),
+    Primitive::plain(syntax::EXACT_INT_SQRT_FN, <Env as MathOps>::exact_integer_sqrt),
+    Primitive::plain(syntax::FLOOR_DIV_FN, <Env as MathOps>::floor_div),
+    Primitive::plain(syntax::TRUNCATE_DIV_FN, <Env as MathOps>::truncate_div),
+    Primitive::plain(syntax::CAR_OP, <Env as EnvPrimitives>::car),
+    Primitive::plain(syntax::CDR_OP, <Env as EnvPrimitives>::cdr),
+    Primitive::plain(syntax::STR_OP, <Env as EnvPrimitives>::string),
+    Primitive::plain(syntax::LOAD_FN, <Env as EnvPrimitives>::load),
+    Primitive::plain(syntax::NOT_OP, <Env as LogicOps>::not),
+    Primitive::plain(syntax::ATM_OP, <Env as LogicOps>::atom),
+    Primitive::plain(syntax::NUMBER_P_FN, <Env as LogicOps>::is_number),
+    Primitive::plain(syntax::INTEGER_P_FN, <Env as LogicOps>::is_integer),
+    Primitive::plain(syntax::RATIONAL_P_FN, <Env as LogicOps>::is_rational),
+    Primitive::plain(syntax::REAL_P_FN, <Env as LogicOps>::is_real),
+    Primitive::plain(syntax::STRING_P_FN, <Env as LogicOps>::is_string),
+    Primitive::plain(syntax::CHAR_P_FN, <Env as LogicOps>::is_char),
+    Primitive::plain(syntax::SYMBOL_P_FN, <Env as LogicOps>::is_symbol),
+    Primitive::plain(syntax::BOOLEAN_P_FN, <Env as LogicOps>::is_boolean),
+    Primitive::plain(syntax::PROCEDURE_P_FN, <Env as LogicOps>::is_procedure),
+    Primitive::plain(syntax::NULL_P_FN, <Env as LogicOps>::is_null),
+    Primitive::plain(syntax::LIST_P_FN, <Env as LogicOps>::is_list),
+    Primitive::plain(syntax::PAIR_P_FN, <Env as LogicOps>::is_pair),
+    Primitive::plain(syntax::VECTOR_P_FN, <Env as LogicOps>::is_vector),
+    Primitive::plain(syntax::BOOLEAN_EQ_FN, <Env as LogicOps>::boolean_eq),
+    Primitive::plain(syntax::SYMBOL_TO_STRING_FN, <Env as ConvertOps>::symbol_to_string),
+    Primitive::plain(syntax::STRING_TO_SYMBOL_FN, <Env as ConvertOps>::string_to_symbol),
+    Primitive::plain(syntax::LIST_TO_STRING_FN, <Env as ConvertOps>::list_to_string),
+    Primitive::plain(syntax::STRING_TO_LIST_FN, <Env as ConvertOps>::string_to_list),
+    Primitive::plain(syntax::NUMBER_TO_STRING_FN, <Env as ConvertOps>::number_to_string),
+    Primitive::plain(syntax::STRING_LENGTH_FN, <Env as StringOps>::string_length),
+    Primitive::plain(syntax::STRING_REF_FN, <Env as StringOps>::string_ref),
+    Primitive::plain(syntax::SUBSTRING_FN, <Env as StringOps>::substring),
+    Primitive::plain(syntax::STRING_COPY_FN, <Env as StringOps>::string_copy),
+    Primitive::plain(syntax::STRING_APPEND_FN, <Env as StringOps>::string_append),
+    Primitive::plain(syntax::STRING_UPCASE_FN, <Env as StringOps>::string_upcase),
+    Primitive::plain(syntax::STRING_DOWNCASE_FN, <Env as StringOps>::string_downcase),
+    Primitive::control(syntax::STRING_INDEX_FN, <Env as StringOps>::string_index),
+    Primitive::plain(syntax::STRING_CONTAINS_FN, <Env as StringOps>::string_contains),
+    Primitive::plain(syntax::STRING_SPLIT_FN, <Env as StringOps>::string_split),
+    Primitive::plain(syntax::STRING_JOIN_FN, <Env as StringOps>::string_join),
+    Primitive::plain(syntax::STRING_TRIM_FN, <Env as StringOps>::string_trim),
+    Primitive::plain(syntax::STRING_TRIM_RIGHT_FN, <Env as StringOps>::string_trim_right),
+    Primitive::plain(syntax::STRING_TRIM_BOTH_FN, <Env as StringOps>::string_trim_both),
+    Primitive::plain(syntax::STRING_PAD_FN, <Env as StringOps>::string_pad),
+    Primitive::plain(syntax::STRING_PAD_RIGHT_FN, <Env as StringOps>::string_pad_right),
+    Primitive::plain(syntax::STRING_PREFIX_P_FN, <Env as StringOps>::string_prefix),
+    Primitive::plain(syntax::STRING_SUFFIX_P_FN, <Env as StringOps>::string_suffix),
+    Primitive::plain(syntax::STRING_REPLACE_FN, <Env as StringOps>::string_replace),
+    Primitive::control(syntax::STRING_FOR_EACH_FN, <Env as StringOps>::string_for_each),
+    Primitive::control(syntax::STRING_MAP_FN, <Env as StringOps>::string_map),
+    Primitive::plain(syntax::STRING_EQ_FN, <Env as StringOps>::string_eq),
+    Primitive::plain(syntax::STRING_LT_FN, <Env as StringOps>::string_lt),
+    Primitive::plain(syntax::STRING_GT_FN, <Env as StringOps>::string_gt),
+    Primitive::plain(syntax::STRING_LE_FN, <Env as StringOps>::string_le),
+    Primitive::plain(syntax::STRING_GE_FN, <Env as StringOps>::string_ge),
+    Primitive::plain(syntax::STRING_CI_EQ_FN, <Env as StringOps>::string_ci_eq),
+    Primitive::plain(syntax::STRING_CI_LT_FN, <Env as StringOps>::string_ci_lt),
+    Primitive::plain(syntax::STRING_CI_GT_FN, <Env as StringOps>::string_ci_gt),
+    Primitive::plain(syntax::STRING_CI_LE_FN, <Env as StringOps>::string_ci_le),
+    Primitive::plain(syntax::STRING_CI_GE_FN, <Env as StringOps>::string_ci_ge),
+    Primitive::plain(syntax::MAKE_STRING_FN, <Env as StringOps>::make_string),
+    Primitive::plain(syntax::STRING_SET_FN, <Env as StringOps>::string_set),
+    Primitive::plain(syntax::STRING_FILL_FN, <Env as StringOps>::string_fill),
+    Primitive::plain(syntax::MAKE_STRING_BUILDER_FN, <Env as StringOps>::make_string_builder),
+    Primitive::plain(syntax::STRING_BUILDER_APPEND_FN, <Env as StringOps>::string_builder_append),
+    Primitive::plain(syntax::BUILDER_TO_STRING_FN, <Env as StringOps>::builder_to_string),
+    Primitive::plain(syntax::FORMAT_FN, <Env as FormatOps>::format),
+    Primitive::plain(syntax::REGEXP_FN, <Env as RegexpOps>::regexp),
+    Primitive::plain(syntax::REGEXP_P_FN, <Env as RegexpOps>::is_regexp),
+    Primitive::plain(syntax::REGEXP_MATCH_FN, <Env as RegexpOps>::regexp_match),
+    Primitive::plain(syntax::REGEXP_SEARCH_FN, <Env as RegexpOps>::regexp_search),
+    Primitive::plain(syntax::REGEXP_MATCH_ALL_FN, <Env as RegexpOps>::regexp_match_all),
+    Primitive::plain(syntax::REGEXP_REPLACE_FN, <Env as RegexpOps>::regexp_replace),
+    Primitive::plain(syntax::REGEXP_REPLACE_ALL_FN, <Env as RegexpOps>::regexp_replace_all),
+    Primitive::plain(syntax::REGEXP_SPLIT_FN, <Env as RegexpOps>::regexp_split),
+    Primitive::plain(syntax::REGEXP_MATCH_COUNT_FN, <Env as RegexpOps>::regexp_match_count),
+    Primitive::plain(syntax::REGEXP_MATCH_SUBMATCH_FN, <Env as RegexpOps>::regexp_match_submatch),
+    Primitive::plain(syntax::REGEXP_MATCH_SUBMATCH_START_FN, <Env as RegexpOps>::regexp_match_submatch_start),
+    Primitive::plain(syntax::REGEXP_MATCH_SUBMATCH_END_FN, <Env as RegexpOps>::regexp_match_submatch_end),
+    Primitive::plain(syntax::REGEXP_MATCH_TO_LIST_FN, <Env as RegexpOps>::regexp_match_to_list),
+    Primitive::plain(syntax::EQ_P_FN, <Env as EqualityOps>::is_eq),
+    Primitive::plain(syntax::EQV_P_FN, <Env as EqualityOps>::is_eqv),
+    Primitive::plain(syntax::EQUAL_P_FN, <Env as EqualityOps>::is_equal),
+    Primitive::plain(syntax::MEMQ_FN, <Env as EqualityOps>::memq),
+    Primitive::plain(syntax::MEMV_FN, <Env as EqualityOps>::memv),
+    Primitive::control(syntax::MEMBER_FN, <Env as EqualityOps>::member),
+    Primitive::plain(syntax::ASSQ_FN, <Env as EqualityOps>::assq),
+    Primitive::plain(syntax::ASSV_FN, <Env as EqualityOps>::assv),
+    Primitive::control(syntax::ASSOC_FN, <Env as EqualityOps>::assoc),
+    Primitive::plain(syntax::LIST_FN, <Env as ListOps>::list),
+    Primitive::control(syntax::MAP_FN, <Env as ListOps>::map),
+    Primitive::control(syntax::FOR_EACH_FN, <Env as ListOps>::for_each),
+    Primitive::control(syntax::APPLY_FN, <Env as EnvironmentOps>::apply_op),
+    Primitive::control(syntax::EVAL_FN, <Env as EnvironmentOps>::eval_op),
+    Primitive::plain(syntax::INTERACTION_ENV_FN, <Env as EnvironmentOps>::interaction_environment),
+    Primitive::plain(syntax::ENVIRONMENT_FN, <Env as EnvironmentOps>::environment),
+    Primitive::plain(syntax::EMPTY_ENV_FN, <Env as EnvironmentOps>::make_empty_environment),
+    Primitive::plain(syntax::VALUES_FN, <Env as ValuesOps>::values),
+    Primitive::control(syntax::CALL_WITH_VALUES_FN, <Env as ValuesOps>::call_with_values),
+    Primitive::plain(syntax::MAKE_PROMISE_FN, <Env as PromiseOps>::make_promise),
+    Primitive::plain(syntax::PROMISE_P_FN, <Env as PromiseOps>::is_promise),
+    Primitive::control(syntax::FORCE_FN, <Env as PromiseOps>::force),
+    Primitive::control(syntax::STREAM_CAR_FN, <Env as StreamOps>::stream_car),
+    Primitive::control(syntax::STREAM_CDR_FN, <Env as StreamOps>::stream_cdr),
+    Primitive::control(syntax::STREAM_NULL_P_FN, <Env as StreamOps>::is_stream_null),
+    Primitive::control(syntax::STREAM_PAIR_P_FN, <Env as StreamOps>::is_stream_pair),
+    Primitive::control(syntax::STREAM_TO_LIST_FN, <Env as StreamOps>::stream_to_list),
+    Primitive::plain(syntax::MAKE_GENERATOR_FN, <Env as GeneratorOps>::make_generator),
+    Primitive::control(syntax::GENERATOR_TO_LIST_FN, <Env as GeneratorOps>::generator_to_list),
+    Primitive::plain(syntax::EOF_OBJECT_FN, <Env as GeneratorOps>::eof_object),
+    Primitive::plain(syntax::EOF_OBJECT_P_FN, <Env as GeneratorOps>::is_eof_object),
+    Primitive::control(syntax::MAKE_PARAMETER_FN, <Env as ParameterOps>::make_parameter),
+    Primitive::control(syntax::CALL_CC_FN, <Env as ControlOps>::call_cc),
+    Primitive::control(syntax::CALL_CC_SHORT_FN, <Env as ControlOps>::call_cc),
+    Primitive::control(syntax::DYNAMIC_WIND_FN, <Env as ControlOps>::dynamic_wind),
+    Primitive::control(syntax::RAISE_FN, <Env as ExceptionOps>::raise),
+    Primitive::control(syntax::RAISE_CONTINUABLE_FN, <Env as ExceptionOps>::raise_continuable),
+    Primitive::control(syntax::ERROR_FN, <Env as ExceptionOps>::error),
+    Primitive::control(syntax::WITH_HANDLER_FN, <Env as ExceptionOps>::with_exception_handler),
+    Primitive::plain(syntax::ERROR_OBJECT_P_FN, <Env as ExceptionOps>::is_error_object),
+    Primitive::plain(syntax::ERROR_MESSAGE_FN, <Env as ExceptionOps>::error_object_message),
+    Primitive::plain(syntax::ERROR_IRRITANTS_FN, <Env as ExceptionOps>::error_object_irritants),
+    Primitive::plain(syntax::FILE_ERROR_P_FN, <Env as ExceptionOps>::is_file_error),
+    Primitive::plain(syntax::READ_ERROR_P_FN, <Env as ExceptionOps>::is_read_error),
+    Primitive::plain(syntax::SLURP_FN, <Env as EnvSys>::slurp),
+    Primitive::plain(syntax::SPIT_FN, <Env as EnvSys>::spit),
+    Primitive::plain(syntax::APPEND_FILE_FN, <Env as EnvSys>::append_file),
+    Primitive::plain(syntax::WRITE_LINES_FN, <Env as EnvSys>::write_lines),
+    Primitive::plain(syntax::PRINT_FN, <Env as EnvSys>::print),
+    Primitive::plain(syntax::PRINTLN_FN, <Env as EnvSys>::println),
+    Primitive::plain(syntax::DISPLAY_FN, <Env as EnvSys>::display),
+    Primitive::plain(syntax::WRITE_FN, <Env as EnvSys>::write_datum),
+    Primitive::plain(syntax::NEWLINE_FN, <Env as EnvSys>::newline),
+    Primitive::plain(syntax::OPEN_OUTPUT_STRING_FN, <Env as PortOps>::open_output_string),
+    Primitive::plain(syntax::GET_OUTPUT_STRING_FN, <Env as PortOps>::get_output_string),
+    Primitive::plain(syntax::RAND_INT_FN, <Env as RandomOps>::random_integer),
+    Primitive::plain(syntax::RAND_REAL_FN, <Env as RandomOps>::random_real),
+    Primitive::plain(syntax::RAND_CHOICE_FN, <Env as RandomOps>::random_choice),
+    Primitive::plain(syntax::SHUFFLE_FN, <Env as RandomOps>::shuffle),
+    Primitive::plain(syntax::RAND_SRC_FN, <Env as RandomOps>::make_random_source),
+    Primitive::plain(syntax::DEFAULT_RAND_SRC_FN, <Env as RandomOps>::default_random_source),
+];
+
+// Builtins that are simpler to write in Scheme.
+const PRELUDE: &str = include_str!("prelude.scm");
+
+/**
+ * Binds every builtin procedure in a frame, along with the parameters
+ * for the settings of the interpreter.
+ *
+ * @param env Environment the prelude is evaluated with.
+ * @param scope Global frame of a new environment.
+ */
+pub fn install(env: &mut Env, scope: &Scope) {
+    for primitive in PRIMITIVES.iter() {
+        let value = Expr::Atom(Box::new(Atom::Primitive(*primitive)));
+
+        Frame::insert(scope, primitive.name, Binding::Value(value));
+    }
+
+    // Every environment shares the settings of the session.
+    for (name, setting) in env.settings().named() {
+        Frame::insert(scope, name, Binding::Value(parameter::parameter_value(setting)));
+    }
+
+    for chunk in lex::chunk_file(PRELUDE) {
+        let tokens = lex::lexical_analysis(&chunk).expect("Prelude can't be read.");
+
+        env.simplify_in(&lex::parse_tokens(&tokens), scope.clone())
+            .expect("Prelude can't be evaluated.");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::env::{Env, Scope};
+use crate::machine::{Cont, Pending, State};
+use crate::syntax::{self, Atom, Expr};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub trait PromiseOps {
+    fn delay(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn delay_force(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn make_promise(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_promise(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn force(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+}
+
+/**
+ * Value computed at most once, the first time it is forced.
+ *
+ * The promise refers to its state through a box that other promises can
+ * share. A 'delay-force' whose expression returns another promise takes
+ * over the state of that promise and hands it its own box, so a long
+ * chain of them never holds more than one pending state.
+ */
+#[derive(Clone)]
+pub struct Promise(Rc<RefCell<Rc<RefCell<PromiseState>>>>);
+
+#[derive(Clone)]
+enum PromiseState {
+    Done(Expr),
+    // Expression left to evaluate. A plain 'delay' gives the value itself,
+    // 'delay-force' gives another promise.
+    Lazy { expr: Expr, scope: Scope, delay: bool },
+}
+
+impl Promise {
+    fn new(state: PromiseState) -> Promise {
+        Promise(Rc::new(RefCell::new(Rc::new(RefCell::new(state)))))
+    }
+
+    pub fn lazy(expr: Expr, scope: Scope, delay: bool) -> Promise {
+        Promise::new(PromiseState::Lazy { expr, scope, delay })
+    }
+
+    pub fn done(value: Expr) -> Promise {
+        Promise::new(PromiseState::Done(value))
+    }
+
+    fn state(&self) -> PromiseState {
+        self.0.borrow().borrow().clone()
+    }
+
+    fn set_state(&self, state: PromiseState) {
+        *self.0.borrow().borrow_mut() = state;
+    }
+
+    // Takes the state of another promise, which then shares this box.
+    fn take_over(&self, other: &Promise) {
+        if Rc::ptr_eq(&self.0, &other.0) {
+            return;
+        }
+
+        self.set_state(other.state());
+        *other.0.borrow_mut() = self.0.borrow().clone();
+    }
+}
+
+impl fmt::Debug for Promise {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Promise")
+    }
+}
+
+// Two promises are only equal if they are the same object.
+impl PartialEq for Promise {
+    fn eq(&self, other: &Promise) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Promise {}
+
+impl PromiseOps for Env {
+    fn delay(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() != 1 {
+            return Err("Incorrect argument count for 'delay' operator.".to_string());
+        }
+
+        Ok(State::Return(promise_value(Promise::lazy(args[0].to_owned(), scope, true)), k))
+    }
+
+    // The expression must return a promise, which is forced in turn
+    // without growing the continuation.
+    fn delay_force(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() != 1 {
+            return Err("Incorrect argument count for 'delay-force' operator.".to_string());
+        }
+
+        Ok(State::Return(promise_value(Promise::lazy(args[0].to_owned(), scope, false)), k))
+    }
+
+    // Promises are returned as they are.
+    fn make_promise(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [value] if promise_arg(value).is_some() => Ok(value.to_owned()),
+            [value] => Ok(promise_value(Promise::done(value.to_owned()))),
+            _ => Err("Incorrect argument count for 'make-promise' function.".to_string()),
+        }
+    }
+
+    fn is_promise(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [value] => Ok(Expr::Atom(Box::new(Atom::Boolean(promise_arg(value).is_some())))),
+            _ => Err(format!("Incorrect argument count for '{}' function.", syntax::PROMISE_P_FN)),
+        }
+    }
+
+    fn force(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        if args.len() != 1 {
+            return Err("Incorrect argument count for 'force' function.".to_string());
+        }
+
+        Ok(force(args.pop().unwrap(), k))
+    }
+}
+
+/**
+ * Forces a value, anything other than a promise is returned as it is.
+ *
+ * @param value Value to force.
+ * @param k Continuation that receives the value of the promise.
+ * @return Next step of the evaluator.
+ */
+pub fn force(value: Expr, k: Cont) -> State {
+    let promise = match promise_arg(&value) {
+        Some(promise) => promise,
+        None => return State::Return(value, k),
+    };
+
+    match promise.state() {
+        PromiseState::Done(value) => State::Return(value, k),
+        PromiseState::Lazy { expr, scope, delay } => {
+            State::Eval(expr, scope, k.push(Pending::Force { promise, delay }))
+        }
+    }
+}
+
+// Stores the value of a promise once its expression has returned.
+pub fn resume_force(value: Expr, promise: Promise, delay: bool, k: Cont) -> Result<State, String> {
+    // Forcing the promise again from its own expression may have finished
+    // it already, the first value wins.
+    if let PromiseState::Done(value) = promise.state() {
+        return Ok(State::Return(value, k));
+    }
+
+    if delay {
+        promise.set_state(PromiseState::Done(value.to_owned()));
+        return Ok(State::Return(value, k));
+    }
+
+    match promise_arg(&value) {
+        Some(next) => {
+            promise.take_over(&next);
+            Ok(force(promise_value(promise), k))
+        }
+        None => Err("Expression of 'delay-force' must return a promise.".to_string()),
+    }
+}
+
+pub fn promise_value(promise: Promise) -> Expr {
+    Expr::Atom(Box::new(Atom::Promise(promise)))
+}
+
+pub fn promise_arg(expr: &Expr) -> Option<Promise> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Promise(promise) => Some(promise.clone()),
+            _ => None,
+        },
+        Expr::List(_) => None,
+    }
+}
@@ -0,0 +1,255 @@
+use crate::env::Env;
+use crate::syntax::{Atom, Expr};
+use bigdecimal::num_bigint::{BigInt, BigUint, Sign};
+use bigdecimal::BigDecimal;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+// Seed of the default source, fixed so that runs are reproducible.
+const DEFAULT_SEED: u64 = 0x5EED_CAFE_F00D_D00D;
+
+// Number of decimal digits produced by 'random-real'.
+const REAL_DIGITS: i64 = 16;
+
+pub trait RandomOps {
+    fn random_integer(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn random_real(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn random_choice(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn shuffle(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn make_random_source(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn default_random_source(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+/**
+ * Pseudo-random number generator based on SplitMix64.
+ *
+ * Copies of a source share the same state, so drawing a number from
+ * one advances all of them.
+ */
+#[derive(Clone)]
+pub struct RandomSource {
+    state: Rc<RefCell<u64>>,
+}
+
+impl RandomSource {
+    pub fn new(seed: u64) -> RandomSource {
+        RandomSource {
+            state: Rc::new(RefCell::new(seed)),
+        }
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        let mut state = self.state.borrow_mut();
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /**
+     * Returns a uniformly distributed number in the range [0, bound).
+     *
+     * @param bound Exclusive upper limit, must be greater than zero.
+     */
+    pub fn below(&self, bound: u64) -> u64 {
+        // Reject the values that would make the low results more likely.
+        let threshold = bound.wrapping_neg() % bound;
+
+        loop {
+            let n = self.next_u64();
+
+            if n >= threshold {
+                return n % bound;
+            }
+        }
+    }
+
+    fn below_big(&self, bound: &BigUint) -> BigUint {
+        let bits = bound.bits();
+        let words = bits.div_ceil(32) as usize;
+        let top_mask = match bits % 32 {
+            0 => u32::MAX,
+            n => (1u32 << n) - 1,
+        };
+
+        loop {
+            let mut digits: Vec<u32> = (0..words).map(|_| self.next_u64() as u32).collect();
+
+            if let Some(top) = digits.last_mut() {
+                *top &= top_mask;
+            }
+
+            let n = BigUint::new(digits);
+
+            if &n < bound {
+                return n;
+            }
+        }
+    }
+}
+
+// Source used when none is passed in, with a fixed seed so that runs are
+// reproducible.
+impl Default for RandomSource {
+    fn default() -> RandomSource {
+        RandomSource::new(DEFAULT_SEED)
+    }
+}
+
+impl fmt::Debug for RandomSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RandomSource({})", self.state.borrow())
+    }
+}
+
+// Two sources are only equal if they share their state.
+impl PartialEq for RandomSource {
+    fn eq(&self, other: &RandomSource) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for RandomSource {}
+
+impl RandomOps for Env {
+    fn random_integer(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.is_empty() || args.len() > 2 {
+            return Err("Incorrect argument count for 'random-integer' function.".to_string());
+        }
+
+        let bound = match &args[0] {
+            Expr::Atom(atom) => match &**atom {
+                Atom::Number(n) if n.is_integer() && n > &BigDecimal::from(0) => {
+                    Ok(n.with_scale(0).as_bigint_and_exponent().0)
+                }
+                _ => Err("The bound of 'random-integer' must be a positive integer.".to_string()),
+            },
+            Expr::List(_) => {
+                Err("The bound of 'random-integer' must be a positive integer.".to_string())
+            }
+        }?;
+        let source = source_arg(self, args.get(1))?;
+
+        let (_, magnitude) = bound.into_parts();
+        let n = source.below_big(&magnitude);
+
+        Ok(Expr::Atom(Box::new(Atom::Number(BigDecimal::new(
+            BigInt::from_biguint(Sign::Plus, n),
+            0,
+        )))))
+    }
+
+    fn random_real(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() > 1 {
+            return Err("Incorrect argument count for 'random-real' function.".to_string());
+        }
+
+        let source = source_arg(self, args.first())?;
+
+        // Never returns exactly 0 or 1.
+        let digits = source.below(10u64.pow(REAL_DIGITS as u32) - 1) + 1;
+
+        Ok(Expr::Atom(Box::new(Atom::Number(BigDecimal::new(
+            BigInt::from(digits),
+            REAL_DIGITS,
+        )))))
+    }
+
+    fn random_choice(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.is_empty() || args.len() > 2 {
+            return Err("Incorrect argument count for 'random-choice' function.".to_string());
+        }
+
+        let list = list_arg(&args[0], "random-choice")?;
+        let source = source_arg(self, args.get(1))?;
+
+        if list.is_empty() {
+            Err("'random-choice' cannot be applied to empty lists.".to_string())
+        } else {
+            let index = source.below(list.len() as u64) as usize;
+
+            Ok(list[index].to_owned())
+        }
+    }
+
+    fn shuffle(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.is_empty() || args.len() > 2 {
+            return Err("Incorrect argument count for 'shuffle' function.".to_string());
+        }
+
+        let mut list = list_arg(&args[0], "shuffle")?;
+        let source = source_arg(self, args.get(1))?;
+
+        // Fisher-Yates, walking down from the end of the list.
+        for i in (1..list.len()).rev() {
+            let j = source.below(i as u64 + 1) as usize;
+            list.swap(i, j);
+        }
+
+        Ok(Expr::List(list))
+    }
+
+    fn make_random_source(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() > 1 {
+            return Err("Incorrect argument count for 'make-random-source' function.".to_string());
+        }
+
+        let seed = match args.first() {
+            Some(Expr::Atom(atom)) => match &**atom {
+                Atom::Number(n) if n.is_integer() => fold_seed(n),
+                _ => return Err("The seed of 'make-random-source' must be an integer.".to_string()),
+            },
+            Some(Expr::List(_)) => {
+                return Err("The seed of 'make-random-source' must be an integer.".to_string())
+            }
+            None => DEFAULT_SEED,
+        };
+
+        Ok(Expr::Atom(Box::new(Atom::RandomSource(RandomSource::new(seed)))))
+    }
+
+    // The source itself is kept by the interpreter, so redefining the name
+    // doesn't change what the other procedures draw from.
+    fn default_random_source(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if !args.is_empty() {
+            return Err(
+                "Incorrect argument count for 'default-random-source' function.".to_string()
+            );
+        }
+
+        Ok(Expr::Atom(Box::new(Atom::RandomSource(self.random_source()))))
+    }
+}
+
+// Reduces an integer of any size to the 64 bits used as generator state.
+fn fold_seed(n: &BigDecimal) -> u64 {
+    let (sign, digits) = n.with_scale(0).as_bigint_and_exponent().0.to_u64_digits();
+    let folded = digits.iter().fold(0u64, |acc, d| acc.rotate_left(17) ^ d);
+
+    match sign {
+        Sign::Minus => !folded,
+        _ => folded,
+    }
+}
+
+// Gets the optional source argument, falling back on the default one.
+fn source_arg(env: &Env, expr: Option<&Expr>) -> Result<RandomSource, String> {
+    match expr {
+        Some(Expr::Atom(atom)) => match &**atom {
+            Atom::RandomSource(source) => Ok(source.clone()),
+            _ => Err("Value is not a random source.".to_string()),
+        },
+        Some(Expr::List(_)) => Err("Value is not a random source.".to_string()),
+        None => Ok(env.random_source()),
+    }
+}
+
+fn list_arg(expr: &Expr, name: &str) -> Result<Vec<Expr>, String> {
+    match expr {
+        Expr::List(list) => Ok(list.to_owned()),
+        Expr::Atom(_) => Err(format!("'{}' can only be applied to lists.", name)),
+    }
+}
@@ -0,0 +1,302 @@
+use crate::env::{Binding, Env, Frame, Scope};
+use crate::machine::{Cont, State};
+use crate::syntax::{self, Atom, Expr};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub trait RecordOps {
+    fn define_record_type(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+}
+
+/**
+ * Type made by 'define-record-type', with the names of its fields in the
+ * order they are stored.
+ */
+#[derive(Clone)]
+pub struct RecordType(Rc<RecordTypeDef>);
+
+struct RecordTypeDef {
+    name: String,
+    fields: Vec<String>,
+}
+
+/**
+ * Instance of a record type. Fields can be changed by modifiers, so two
+ * records are only equal if they are the same object.
+ */
+#[derive(Clone)]
+pub struct Record(Rc<RecordData>);
+
+struct RecordData {
+    record_type: RecordType,
+    values: RefCell<Vec<Expr>>,
+}
+
+/**
+ * Procedure generated by 'define-record-type'.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordProcedure {
+    pub name: String,
+    record_type: RecordType,
+    kind: ProcedureKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ProcedureKind {
+    // Positions of the fields set by the arguments, in order.
+    Constructor(Vec<usize>),
+    Predicate,
+    Accessor(usize),
+    Modifier(usize),
+}
+
+impl RecordType {
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+}
+
+impl fmt::Debug for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RecordType({})", self.0.name)
+    }
+}
+
+// Two record types are only equal if they are the same object.
+impl PartialEq for RecordType {
+    fn eq(&self, other: &RecordType) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for RecordType {}
+
+impl Record {
+    // Identity of the record, the same for every copy of it.
+    pub fn id(&self) -> usize {
+        Rc::as_ptr(&self.0) as usize
+    }
+
+    // Type name and every field with its value.
+    pub fn fields(&self) -> (String, Vec<(String, Expr)>) {
+        let record_type = &self.0.record_type.0;
+        let values = self.0.values.borrow();
+        let fields = record_type.fields.iter().cloned().zip(values.iter().cloned()).collect();
+
+        (record_type.name.to_owned(), fields)
+    }
+
+    // Whether two records have the same type and every field agrees.
+    pub fn fields_equal(
+        &self,
+        other: &Record,
+        mut equal: impl FnMut(&Expr, &Expr) -> bool,
+    ) -> bool {
+        if self.0.record_type != other.0.record_type {
+            return false;
+        }
+
+        let values = self.0.values.borrow();
+        let other_values = other.0.values.borrow();
+
+        values.iter().zip(other_values.iter()).all(|(a, b)| equal(a, b))
+    }
+}
+
+impl fmt::Debug for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Record({})", self.0.record_type.0.name)
+    }
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Record) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Record {}
+
+impl RecordOps for Env {
+    // (define-record-type <point> (make-point x y) point? (x point-x set-point-x!) ...)
+    // binds the type, its constructor, predicate, accessors and modifiers.
+    fn define_record_type(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() < 3 {
+            return Err(
+                "Incorrect number of arguments for 'define-record-type' operator.".to_string(),
+            );
+        }
+
+        let type_name = symbol_arg(&args[0])?;
+        let mut field_specs = Vec::new();
+
+        for spec in args[3..].iter() {
+            match spec {
+                Expr::List(list) if !list.is_empty() && list.len() <= 3 => {
+                    let names = list.iter().map(symbol_arg);
+                    field_specs.push(names.collect::<Result<Vec<String>, String>>()?);
+                }
+                _ => return Err("Invalid field in 'define-record-type' operator.".to_string()),
+            }
+        }
+
+        let fields: Vec<String> = field_specs.iter().map(|x| x[0].to_owned()).collect();
+        let record_type = RecordType(Rc::new(RecordTypeDef {
+            name: type_name.trim_start_matches('<').trim_end_matches('>').to_string(),
+            fields: fields.to_owned(),
+        }));
+
+        let bind = |name: &str, kind: ProcedureKind| {
+            let procedure = RecordProcedure {
+                name: name.to_string(),
+                record_type: record_type.clone(),
+                kind,
+            };
+
+            let value = Expr::Atom(Box::new(Atom::RecordProcedure(procedure)));
+            Frame::insert(&scope, name, Binding::Value(value));
+        };
+
+        // The constructor is either a name, which takes every field, a
+        // name with the fields it takes, or #f for none at all.
+        match &args[1] {
+            Expr::List(list) if !list.is_empty() => {
+                let name = symbol_arg(&list[0])?;
+                let mut positions = Vec::new();
+
+                for field in list[1..].iter() {
+                    let field = symbol_arg(field)?;
+
+                    match fields.iter().position(|x| *x == field) {
+                        Some(position) => positions.push(position),
+                        None => {
+                            return Err(format!(
+                                "Constructor '{}' takes unknown field '{}'.",
+                                name, field
+                            ))
+                        }
+                    }
+                }
+
+                bind(&name, ProcedureKind::Constructor(positions));
+            }
+            Expr::Atom(atom) if **atom == Atom::Boolean(false) => {}
+            other => {
+                let all = (0..fields.len()).collect();
+                bind(&symbol_arg(other)?, ProcedureKind::Constructor(all));
+            }
+        }
+
+        if args[2] != Expr::Atom(Box::new(Atom::Boolean(false))) {
+            bind(&symbol_arg(&args[2])?, ProcedureKind::Predicate);
+        }
+
+        for (position, spec) in field_specs.iter().enumerate() {
+            if let Some(accessor) = spec.get(1) {
+                bind(accessor, ProcedureKind::Accessor(position));
+            }
+
+            if let Some(modifier) = spec.get(2) {
+                bind(modifier, ProcedureKind::Modifier(position));
+            }
+        }
+
+        let value = Expr::Atom(Box::new(Atom::RecordType(record_type.clone())));
+        Frame::insert(&scope, &type_name, Binding::Value(value));
+
+        Ok(State::Return(Expr::Atom(Box::new(Atom::Symbol(type_name))), k))
+    }
+}
+
+/**
+ * Calls a procedure generated by 'define-record-type'.
+ *
+ * @param procedure Constructor, predicate, accessor or modifier.
+ * @param values Evaluated arguments of the call.
+ * @return Value of the call.
+ */
+pub fn call(procedure: &RecordProcedure, values: Vec<Expr>) -> Result<Expr, String> {
+    let arity = match &procedure.kind {
+        ProcedureKind::Constructor(positions) => positions.len(),
+        ProcedureKind::Predicate | ProcedureKind::Accessor(_) => 1,
+        ProcedureKind::Modifier(_) => 2,
+    };
+
+    if values.len() != arity {
+        return Err(format!(
+            "Procedure '{}' accepts {} arguments, got {}.",
+            procedure.name,
+            arity,
+            values.len()
+        ));
+    }
+
+    match &procedure.kind {
+        ProcedureKind::Constructor(positions) => {
+            // Fields the constructor doesn't take start out as nil.
+            let count = procedure.record_type.0.fields.len();
+            let mut fields = vec![Expr::Atom(Box::new(Atom::Nil)); count];
+
+            for (position, value) in positions.iter().zip(values) {
+                fields[*position] = value;
+            }
+
+            let record = Record(Rc::new(RecordData {
+                record_type: procedure.record_type.clone(),
+                values: RefCell::new(fields),
+            }));
+
+            Ok(Expr::Atom(Box::new(Atom::Record(record))))
+        }
+        ProcedureKind::Predicate => {
+            let is_type = record_arg(&values[0], &procedure.record_type).is_some();
+
+            Ok(Expr::Atom(Box::new(Atom::Boolean(is_type))))
+        }
+        ProcedureKind::Accessor(position) => {
+            let record = typed_record(&values[0], procedure)?;
+            let value = record.0.values.borrow()[*position].to_owned();
+
+            Ok(value)
+        }
+        ProcedureKind::Modifier(position) => {
+            let record = typed_record(&values[0], procedure)?;
+            record.0.values.borrow_mut()[*position] = values[1].to_owned();
+
+            Ok(Expr::Atom(Box::new(Atom::Nil)))
+        }
+    }
+}
+
+fn typed_record(expr: &Expr, procedure: &RecordProcedure) -> Result<Record, String> {
+    record_arg(expr, &procedure.record_type).ok_or_else(|| {
+        format!(
+            "Procedure '{}' expects a record of type '{}', got '{}'.",
+            procedure.name,
+            procedure.record_type.name(),
+            syntax::print_tree(expr, &false)
+        )
+    })
+}
+
+fn record_arg(expr: &Expr, record_type: &RecordType) -> Option<Record> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Record(record) if record.0.record_type == *record_type => Some(record.clone()),
+            _ => None,
+        },
+        Expr::List(_) => None,
+    }
+}
+
+fn symbol_arg(expr: &Expr) -> Result<String, String> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::Symbol(s) => Ok(s.to_owned()),
+            _ => Err("Invalid name in 'define-record-type' operator.".to_string()),
+        },
+        Expr::List(_) => Err("Invalid name in 'define-record-type' operator.".to_string()),
+    }
+}
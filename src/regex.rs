@@ -0,0 +1,546 @@
+use std::collections::HashSet;
+
+// Counted repetitions are expanded into copies of what they repeat, so
+// their bounds are kept small.
+const MAX_REPEAT: usize = 1000;
+
+/**
+ * Compiled regular expression.
+ *
+ * Patterns are parsed into a tree and compiled into instructions for a
+ * backtracking matcher. The matcher remembers which instruction and
+ * position pairs already failed, so no pair is tried twice and matching
+ * takes at most time proportional to the size of the program times the
+ * length of the text.
+ *
+ * Supported syntax: literals, '.', classes like [a-z] and [^0-9], the
+ * escapes \d \w \s \D \W \S \b \B \n \t \r, anchors '^' and '$', capturing
+ * groups, non-capturing groups '(?:...)', alternation, and the greedy and
+ * lazy quantifiers '*', '+', '?', '{n}', '{n,}' and '{n,m}'.
+ */
+#[derive(Debug)]
+pub struct Regex {
+    program: Vec<Inst>,
+    groups: usize,
+}
+
+// Start and end of every group in a match, group 0 being the whole match.
+// Indexes count characters.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat { node: Box<Node>, min: usize, max: Option<usize>, greedy: bool },
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    // Shorthand classes like \d, true when they are negated like \D.
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+#[derive(Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    // Tries the first target, then the second one if it fails.
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Match,
+}
+
+// Work left for the matcher, a thread to try or a capture to undo when
+// backtracking past it.
+enum Job {
+    Try(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+impl Regex {
+    /**
+     * Compiles a pattern.
+     *
+     * @param pattern Text of the regular expression.
+     * @return Compiled expression, or a description of what is wrong with
+     * the pattern.
+     */
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser { chars: pattern.chars().collect(), position: 0, groups: 0 };
+        let tree = parser.parse_alternate()?;
+
+        if parser.position < parser.chars.len() {
+            return Err("Unmatched ')' in regular expression.".to_string());
+        }
+
+        let mut program = vec![Inst::Save(0)];
+        compile(&tree, &mut program);
+        program.push(Inst::Save(1));
+        program.push(Inst::Match);
+
+        Ok(Regex { program, groups: parser.groups })
+    }
+
+    /**
+     * Finds the first match that starts at or after a position.
+     *
+     * @param text Characters searched.
+     * @param start Position the search starts from.
+     * @return Groups of the match, or none if there is no match.
+     */
+    pub fn search(&self, text: &[char], start: usize) -> Option<Captures> {
+        let mut failed = HashSet::new();
+
+        // A pair that failed from one start fails from any other, so the
+        // failures are kept from one start to the next.
+        (start..=text.len()).find_map(|from| self.run(text, from, false, &mut failed))
+    }
+
+    // Matches the whole text.
+    pub fn match_all_of(&self, text: &[char]) -> Option<Captures> {
+        self.run(text, 0, true, &mut HashSet::new())
+    }
+
+    fn run(
+        &self,
+        text: &[char],
+        start: usize,
+        to_end: bool,
+        failed: &mut HashSet<(usize, usize)>,
+    ) -> Option<Captures> {
+        let mut slots: Vec<Option<usize>> = vec![None; (self.groups + 1) * 2];
+        let mut jobs = vec![Job::Try(0, start)];
+
+        while let Some(job) = jobs.pop() {
+            let (mut pc, mut position) = match job {
+                Job::Try(pc, position) => (pc, position),
+                Job::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+
+            loop {
+                if !failed.insert((pc, position)) {
+                    break;
+                }
+
+                let current = text.get(position).copied();
+
+                match &self.program[pc] {
+                    Inst::Char(c) if current == Some(*c) => {
+                        pc += 1;
+                        position += 1;
+                    }
+                    Inst::Any if current.is_some_and(|c| c != '\n') => {
+                        pc += 1;
+                        position += 1;
+                    }
+                    Inst::Class(class) if current.is_some_and(|c| class.matches(c)) => {
+                        pc += 1;
+                        position += 1;
+                    }
+                    Inst::Start if position == 0 => pc += 1,
+                    Inst::End if position == text.len() => pc += 1,
+                    Inst::WordBoundary(expected) => {
+                        let before = position > 0 && is_word(text[position - 1]);
+                        let after = current.is_some_and(is_word);
+
+                        if (before != after) != *expected {
+                            break;
+                        }
+
+                        pc += 1;
+                    }
+                    Inst::Split(first, second) => {
+                        jobs.push(Job::Try(*second, position));
+                        pc = *first;
+                    }
+                    Inst::Jump(target) => pc = *target,
+                    Inst::Save(slot) => {
+                        jobs.push(Job::Restore(*slot, slots[*slot]));
+                        slots[*slot] = Some(position);
+                        pc += 1;
+                    }
+                    Inst::Match if !to_end || position == text.len() => {
+                        return Some(captures(&slots));
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let found = self.items.iter().any(|item| match item {
+            ClassItem::Range(low, high) => *low <= c && c <= *high,
+            ClassItem::Digit(negated) => c.is_ascii_digit() != *negated,
+            ClassItem::Word(negated) => is_word(c) != *negated,
+            ClassItem::Space(negated) => c.is_whitespace() != *negated,
+        });
+
+        found != self.negated
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn captures(slots: &[Option<usize>]) -> Captures {
+    slots
+        .chunks(2)
+        .map(|pair| match (pair[0], pair[1]) {
+            (Some(start), Some(end)) => Some((start, end)),
+            _ => None,
+        })
+        .collect()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    groups: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn parse_alternate(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_concat()?];
+
+        while self.peek() == Some('|') {
+            self.position += 1;
+            branches.push(self.parse_concat()?);
+        }
+
+        Ok(match branches.len() {
+            1 => branches.remove(0),
+            _ => Node::Alternate(branches),
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('(') => {
+                let index = if self.chars[self.position..].starts_with(&['?', ':']) {
+                    self.position += 2;
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+
+                let inner = self.parse_alternate()?;
+
+                if self.next() != Some(')') {
+                    return Err("Missing ')' in regular expression.".to_string());
+                }
+
+                Ok(Node::Group(Box::new(inner), index))
+            }
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('[') => self.parse_class(),
+            Some('\\') => self.parse_escape(),
+            Some(c) if c == '*' || c == '+' || c == '?' => {
+                Err(format!("Nothing to repeat before '{}' in regular expression.", c))
+            }
+            Some(c) => Ok(Node::Char(c)),
+            None => Err("Unexpected end of regular expression.".to_string()),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('b') => Ok(Node::WordBoundary(true)),
+            Some('B') => Ok(Node::WordBoundary(false)),
+            Some(c) => match class_escape(c)? {
+                Some(item) => Ok(Node::Class(Class { items: vec![item], negated: false })),
+                None => Ok(Node::Char(literal_escape(c))),
+            },
+            None => Err("Regular expression ends with '\\'.".to_string()),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+
+        if negated {
+            self.position += 1;
+        }
+
+        let mut items = Vec::new();
+        let mut first = true;
+
+        loop {
+            let low = match self.next() {
+                Some(']') if !first => break,
+                Some('\\') => match self.next() {
+                    Some(c) => match class_escape(c)? {
+                        Some(item) => {
+                            items.push(item);
+                            first = false;
+                            continue;
+                        }
+                        None => literal_escape(c),
+                    },
+                    None => return Err("Regular expression ends with '\\'.".to_string()),
+                },
+                Some(c) => c,
+                None => return Err("Missing ']' in regular expression.".to_string()),
+            };
+
+            first = false;
+
+            // A '-' between two characters makes a range, elsewhere it is
+            // itself.
+            let is_range = self.peek() == Some('-')
+                && self.chars.get(self.position + 1).is_some_and(|c| *c != ']');
+
+            if is_range {
+                self.position += 1;
+
+                let high = match self.next() {
+                    Some('\\') => self.next().map(literal_escape),
+                    other => other,
+                };
+
+                match high {
+                    Some(high) if low <= high => items.push(ClassItem::Range(low, high)),
+                    Some(_) => return Err("Invalid range in regular expression.".to_string()),
+                    None => return Err("Missing ']' in regular expression.".to_string()),
+                }
+            } else {
+                items.push(ClassItem::Range(low, low));
+            }
+        }
+
+        Ok(Node::Class(Class { items, negated }))
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('{') => match self.parse_bounds()? {
+                Some(bounds) => bounds,
+                None => return Ok(atom),
+            },
+            Some(c) if c == '*' || c == '+' || c == '?' => {
+                self.position += 1;
+
+                match c {
+                    '*' => (0, None),
+                    '+' => (1, None),
+                    _ => (0, Some(1)),
+                }
+            }
+            _ => return Ok(atom),
+        };
+
+        let greedy = self.peek() != Some('?');
+
+        if !greedy {
+            self.position += 1;
+        }
+
+        if matches!(self.peek(), Some('*') | Some('+') | Some('?')) {
+            return Err("Nothing to repeat in regular expression.".to_string());
+        }
+
+        if matches!(atom, Node::Start | Node::End | Node::WordBoundary(_)) {
+            return Err("Anchors can't be repeated in regular expression.".to_string());
+        }
+
+        Ok(Node::Repeat { node: Box::new(atom), min, max, greedy })
+    }
+
+    // Bounds of '{n}', '{n,}' or '{n,m}'. A '{' that doesn't start bounds
+    // is left to be read as itself.
+    fn parse_bounds(&mut self) -> Result<Option<(usize, Option<usize>)>, String> {
+        let rest: String = self.chars[self.position..].iter().collect();
+
+        let inner = match rest.find('}') {
+            Some(end) => &rest[1..end],
+            None => return Ok(None),
+        };
+
+        let number = |s: &str| s.parse::<usize>().ok();
+
+        let bounds = match inner.split_once(',') {
+            None => number(inner).map(|n| (n, Some(n))),
+            Some((low, "")) => number(low).map(|n| (n, None)),
+            Some((low, high)) => number(low).and_then(|n| number(high).map(|m| (n, Some(m)))),
+        };
+
+        let (min, max) = match bounds {
+            Some(bounds) => bounds,
+            None => return Ok(None),
+        };
+
+        if max.is_some_and(|m| m < min) {
+            return Err("Invalid repetition bounds in regular expression.".to_string());
+        }
+
+        if max.unwrap_or(min) > MAX_REPEAT {
+            return Err(format!(
+                "Repetition bounds in regular expression can't be over {}.",
+                MAX_REPEAT
+            ));
+        }
+
+        self.position += inner.chars().count() + 2;
+
+        Ok(Some((min, max)))
+    }
+}
+
+// Shorthand classes, and an error for letters that aren't escapes.
+fn class_escape(c: char) -> Result<Option<ClassItem>, String> {
+    match c {
+        'd' => Ok(Some(ClassItem::Digit(false))),
+        'D' => Ok(Some(ClassItem::Digit(true))),
+        'w' => Ok(Some(ClassItem::Word(false))),
+        'W' => Ok(Some(ClassItem::Word(true))),
+        's' => Ok(Some(ClassItem::Space(false))),
+        'S' => Ok(Some(ClassItem::Space(true))),
+        'n' | 't' | 'r' => Ok(None),
+        c if c.is_ascii_alphanumeric() => {
+            Err(format!("Unknown escape '\\{}' in regular expression.", c))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn literal_escape(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        c => c,
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::WordBoundary(expected) => program.push(Inst::WordBoundary(*expected)),
+        Node::Group(inner, index) => match index {
+            Some(index) => {
+                program.push(Inst::Save(index * 2));
+                compile(inner, program);
+                program.push(Inst::Save(index * 2 + 1));
+            }
+            None => compile(inner, program),
+        },
+        Node::Concat(nodes) => nodes.iter().for_each(|x| compile(x, program)),
+        Node::Alternate(branches) => {
+            let mut jumps = Vec::new();
+
+            for (i, branch) in branches.iter().enumerate() {
+                if i == branches.len() - 1 {
+                    compile(branch, program);
+                } else {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(branch, program);
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+            }
+
+            let end = program.len();
+
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat { node, min, max, greedy } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(0, 0));
+                    compile(node, program);
+                    program.push(Inst::Jump(split));
+                    program[split] = branch(split + 1, program.len(), *greedy);
+                }
+                Some(max) => {
+                    for _ in *min..*max {
+                        let split = program.len();
+                        program.push(Inst::Split(0, 0));
+                        compile(node, program);
+                        program[split] = branch(split + 1, program.len(), *greedy);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Split that prefers taking the repetition when greedy, and skipping it
+// otherwise.
+fn branch(repeat: usize, skip: usize, greedy: bool) -> Inst {
+    if greedy {
+        Inst::Split(repeat, skip)
+    } else {
+        Inst::Split(skip, repeat)
+    }
+}
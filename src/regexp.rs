@@ -0,0 +1,412 @@
+use crate::env::Env;
+use crate::regex::{Captures, Regex};
+use crate::string;
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use std::fmt;
+use std::rc::Rc;
+
+pub trait RegexpOps {
+    fn regexp(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn is_regexp(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_match(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_search(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_match_all(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_replace(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_replace_all(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_split(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_match_count(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_match_submatch(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_match_submatch_start(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_match_submatch_end(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn regexp_match_to_list(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+/**
+ * Regular expression compiled by 'regexp'. Procedures that take one also
+ * take the pattern as a string, and compile it on every call.
+ */
+#[derive(Clone)]
+pub struct Regexp(Rc<RegexpDef>);
+
+struct RegexpDef {
+    pattern: String,
+    regex: Regex,
+}
+
+/**
+ * Result of a successful match, with the text that was searched and the
+ * start and end of every group, counted in characters.
+ */
+#[derive(Clone)]
+pub struct RegexpMatch(Rc<MatchData>);
+
+struct MatchData {
+    text: Rc<Vec<char>>,
+    groups: Captures,
+}
+
+impl Regexp {
+    pub fn pattern(&self) -> &str {
+        &self.0.pattern
+    }
+}
+
+impl fmt::Debug for Regexp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Regexp({:?})", self.0.pattern)
+    }
+}
+
+impl PartialEq for Regexp {
+    fn eq(&self, other: &Regexp) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Regexp {}
+
+impl RegexpMatch {
+    // Text of the whole match.
+    pub fn text(&self) -> String {
+        self.submatch(0).unwrap_or_default()
+    }
+
+    fn submatch(&self, group: usize) -> Option<String> {
+        self.0.groups[group].map(|(start, end)| self.0.text[start..end].iter().collect())
+    }
+}
+
+impl fmt::Debug for RegexpMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RegexpMatch({:?})", self.text())
+    }
+}
+
+impl PartialEq for RegexpMatch {
+    fn eq(&self, other: &RegexpMatch) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for RegexpMatch {}
+
+impl RegexpOps for Env {
+    fn regexp(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [pattern] => {
+                let regexp = regexp_arg(pattern, syntax::REGEXP_FN)?;
+
+                Ok(Expr::Atom(Box::new(Atom::Regexp(regexp))))
+            }
+            _ => Err(arg_count_error(syntax::REGEXP_FN)),
+        }
+    }
+
+    fn is_regexp(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [Expr::Atom(atom)] => Ok(boolean_value(matches!(**atom, Atom::Regexp(_)))),
+            [Expr::List(_)] => Ok(boolean_value(false)),
+            _ => Err(arg_count_error(syntax::REGEXP_P_FN)),
+        }
+    }
+
+    // Matches the whole string, a match of only part of it doesn't count.
+    fn regexp_match(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (regexp, text) = regexp_and_text(args, syntax::REGEXP_MATCH_FN)?;
+        let groups = regexp.0.regex.match_all_of(&text);
+
+        Ok(groups.map_or_else(|| boolean_value(false), |x| match_value(&text, x)))
+    }
+
+    // Finds the first match, from the start or from the index given after
+    // the string.
+    fn regexp_search(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::REGEXP_SEARCH_FN;
+
+        let (regexp, text, start) = match args {
+            [_, _] => {
+                let (regexp, text) = regexp_and_text(args, name)?;
+                (regexp, text, 0)
+            }
+            [_, _, start] => {
+                let (regexp, text) = regexp_and_text(&args[..2], name)?;
+                (regexp, text, index_arg(start, name)?)
+            }
+            _ => return Err(arg_count_error(name)),
+        };
+
+        if start > text.len() {
+            return Err(format!("Index {} is out of range for '{}'.", start, name));
+        }
+
+        let groups = regexp.0.regex.search(&text, start);
+
+        Ok(groups.map_or_else(|| boolean_value(false), |x| match_value(&text, x)))
+    }
+
+    fn regexp_match_all(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (regexp, text) = regexp_and_text(args, syntax::REGEXP_MATCH_ALL_FN)?;
+
+        Ok(Expr::List(
+            all_matches(&regexp, &text).into_iter().map(|x| match_value(&text, x)).collect(),
+        ))
+    }
+
+    // In the replacement \0 to \9 stand for the text of a group, and \\
+    // for a backslash.
+    fn regexp_replace(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        replace(args, syntax::REGEXP_REPLACE_FN, false)
+    }
+
+    fn regexp_replace_all(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        replace(args, syntax::REGEXP_REPLACE_ALL_FN, true)
+    }
+
+    // Pieces of the string between the matches.
+    fn regexp_split(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (regexp, text) = regexp_and_text(args, syntax::REGEXP_SPLIT_FN)?;
+        let mut pieces = Vec::new();
+        let mut last = 0;
+
+        for groups in all_matches(&regexp, &text) {
+            if let Some((start, end)) = groups[0] {
+                // An empty match at the very start or end doesn't split.
+                if start == end && (end == 0 || start == text.len()) {
+                    continue;
+                }
+
+                pieces.push(string_value(text[last..start].iter().collect()));
+                last = end;
+            }
+        }
+
+        pieces.push(string_value(text[last..].iter().collect()));
+
+        Ok(Expr::List(pieces))
+    }
+
+    // Number of groups, not counting the whole match.
+    fn regexp_match_count(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [m] => {
+                let m = match_arg(m, syntax::REGEXP_MATCH_COUNT_FN)?;
+
+                Ok(number_value(m.0.groups.len() - 1))
+            }
+            _ => Err(arg_count_error(syntax::REGEXP_MATCH_COUNT_FN)),
+        }
+    }
+
+    // Text of a group, or false if it took no part in the match. Group 0
+    // is the whole match, and is the default.
+    fn regexp_match_submatch(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (m, group) = match_and_group(args, syntax::REGEXP_MATCH_SUBMATCH_FN)?;
+
+        Ok(m.submatch(group).map_or_else(|| boolean_value(false), string_value))
+    }
+
+    fn regexp_match_submatch_start(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (m, group) = match_and_group(args, syntax::REGEXP_MATCH_SUBMATCH_START_FN)?;
+
+        Ok(m.0.groups[group].map_or_else(|| boolean_value(false), |(start, _)| number_value(start)))
+    }
+
+    fn regexp_match_submatch_end(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (m, group) = match_and_group(args, syntax::REGEXP_MATCH_SUBMATCH_END_FN)?;
+
+        Ok(m.0.groups[group].map_or_else(|| boolean_value(false), |(_, end)| number_value(end)))
+    }
+
+    // Texts of the whole match and of every group.
+    fn regexp_match_to_list(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [m] => {
+                let m = match_arg(m, syntax::REGEXP_MATCH_TO_LIST_FN)?;
+
+                Ok(Expr::List(
+                    (0..m.0.groups.len())
+                        .map(|i| m.submatch(i).map_or_else(|| boolean_value(false), string_value))
+                        .collect(),
+                ))
+            }
+            _ => Err(arg_count_error(syntax::REGEXP_MATCH_TO_LIST_FN)),
+        }
+    }
+}
+
+// Matches that don't overlap, from the start of the text. After an empty
+// match the search goes on from the next character.
+fn all_matches(regexp: &Regexp, text: &[char]) -> Vec<Captures> {
+    let mut matches = Vec::new();
+    let mut position = 0;
+
+    while position <= text.len() {
+        let groups = match regexp.0.regex.search(text, position) {
+            Some(groups) => groups,
+            None => break,
+        };
+
+        let (start, end) = groups[0].unwrap_or((position, position));
+        position = if end == start { end + 1 } else { end };
+        matches.push(groups);
+    }
+
+    matches
+}
+
+fn replace(args: &[Expr], name: &str, all: bool) -> Result<Expr, String> {
+    let (regexp, text, replacement) = match args {
+        [_, _, replacement] => {
+            let (regexp, text) = regexp_and_text(&args[..2], name)?;
+            (regexp, text, string::string_arg(replacement, name)?)
+        }
+        _ => return Err(arg_count_error(name)),
+    };
+
+    let matches = if all {
+        all_matches(&regexp, &text)
+    } else {
+        regexp.0.regex.search(&text, 0).into_iter().collect()
+    };
+
+    let mut result = String::new();
+    let mut last = 0;
+
+    for groups in matches {
+        if let Some((start, end)) = groups[0] {
+            result.extend(&text[last..start]);
+            result.push_str(&expand(&replacement, &groups, &text, name)?);
+            last = end;
+        }
+    }
+
+    result.extend(&text[last..]);
+
+    Ok(string_value(result))
+}
+
+// Fills the groups of a match into a replacement.
+fn expand(
+    replacement: &str,
+    groups: &Captures,
+    text: &[char],
+    name: &str,
+) -> Result<String, String> {
+    let mut result = String::new();
+    let mut chars = replacement.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(digit) if digit.is_ascii_digit() => {
+                let group = digit.to_digit(10).unwrap_or(0) as usize;
+
+                match groups.get(group) {
+                    Some(Some((start, end))) => result.extend(&text[*start..*end]),
+                    Some(None) => {}
+                    None => {
+                        return Err(format!(
+                            "Replacement of '{}' refers to group {}, which doesn't exist.",
+                            name, group
+                        ))
+                    }
+                }
+            }
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+
+    Ok(result)
+}
+
+fn regexp_arg(expr: &Expr, name: &str) -> Result<Regexp, String> {
+    if let Expr::Atom(atom) = expr {
+        if let Atom::Regexp(regexp) = &**atom {
+            return Ok(regexp.clone());
+        }
+
+        if let Some(pattern) = string::text_of(atom) {
+            let regex = Regex::new(&pattern).map_err(|e| format!("{} ({})", e, name))?;
+
+            return Ok(Regexp(Rc::new(RegexpDef { pattern, regex })));
+        }
+    }
+
+    Err(format!("First argument of '{}' must be a regular expression or a string.", name))
+}
+
+fn regexp_and_text(args: &[Expr], name: &str) -> Result<(Regexp, Rc<Vec<char>>), String> {
+    match args {
+        [regexp, text] => {
+            let regexp = regexp_arg(regexp, name)?;
+            let text = string::string_arg(text, name)?;
+
+            Ok((regexp, Rc::new(text.chars().collect())))
+        }
+        _ => Err(arg_count_error(name)),
+    }
+}
+
+fn match_arg(expr: &Expr, name: &str) -> Result<RegexpMatch, String> {
+    if let Expr::Atom(atom) = expr {
+        if let Atom::RegexpMatch(m) = &**atom {
+            return Ok(m.clone());
+        }
+    }
+
+    Err(format!("First argument of '{}' must be a match.", name))
+}
+
+fn match_and_group(args: &[Expr], name: &str) -> Result<(RegexpMatch, usize), String> {
+    let (m, group) = match args {
+        [m] => (match_arg(m, name)?, 0),
+        [m, group] => (match_arg(m, name)?, index_arg(group, name)?),
+        _ => return Err(arg_count_error(name)),
+    };
+
+    if group >= m.0.groups.len() {
+        return Err(format!("Match has no group {} for '{}'.", group, name));
+    }
+
+    Ok((m, group))
+}
+
+fn index_arg(expr: &Expr, name: &str) -> Result<usize, String> {
+    if let Expr::Atom(atom) = expr {
+        if let Atom::Number(n) = &**atom {
+            if let (true, Some(index)) = (n.is_integer(), n.to_usize()) {
+                return Ok(index);
+            }
+        }
+    }
+
+    Err(format!("Indexes given to '{}' must be non-negative integers.", name))
+}
+
+fn match_value(text: &Rc<Vec<char>>, groups: Captures) -> Expr {
+    let data = MatchData { text: text.clone(), groups };
+
+    Expr::Atom(Box::new(Atom::RegexpMatch(RegexpMatch(Rc::new(data)))))
+}
+
+fn arg_count_error(name: &str) -> String {
+    format!("Incorrect argument count for '{}' function.", name)
+}
+
+fn string_value(text: String) -> Expr {
+    Expr::Atom(Box::new(Atom::StringLiteral(text)))
+}
+
+fn number_value(n: usize) -> Expr {
+    Expr::Atom(Box::new(Atom::Number(BigDecimal::from(n as u64))))
+}
+
+fn boolean_value(b: bool) -> Expr {
+    Expr::Atom(Box::new(Atom::Boolean(b)))
+}
@@ -0,0 +1,169 @@
+use crate::env::{Env, Scope};
+use crate::machine::{Cont, Pending, State};
+use crate::promise::{self, Promise};
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::ToPrimitive;
+
+pub trait StreamOps {
+    fn stream_cons(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn stream_car(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn stream_cdr(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn is_stream_null(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn is_stream_pair(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn stream_to_list(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+}
+
+/**
+ * What to do with a stream once it has been forced.
+ *
+ * A stream is a promise of either the empty list, for the end of the
+ * stream, or of a list of two promises: one of the first element and
+ * one of the stream of the remaining elements.
+ */
+#[derive(Clone, Copy)]
+pub enum StreamOp {
+    Car,
+    Cdr,
+    IsNull,
+    IsPair,
+}
+
+impl StreamOps for Env {
+    // Neither argument is evaluated until it is needed.
+    fn stream_cons(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() != 2 {
+            return Err("Incorrect argument count for 'stream-cons' operator.".to_string());
+        }
+
+        let first = Promise::lazy(args[0].to_owned(), scope.clone(), true);
+        let rest = Promise::lazy(args[1].to_owned(), scope, false);
+        let pair = Expr::List(vec![promise::promise_value(first), promise::promise_value(rest)]);
+
+        Ok(State::Return(promise::promise_value(Promise::done(pair)), k))
+    }
+
+    fn stream_car(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        stream_op(args, StreamOp::Car, syntax::STREAM_CAR_FN, k)
+    }
+
+    fn stream_cdr(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        stream_op(args, StreamOp::Cdr, syntax::STREAM_CDR_FN, k)
+    }
+
+    fn is_stream_null(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        stream_op(args, StreamOp::IsNull, syntax::STREAM_NULL_P_FN, k)
+    }
+
+    fn is_stream_pair(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        stream_op(args, StreamOp::IsPair, syntax::STREAM_PAIR_P_FN, k)
+    }
+
+    // (stream->list [n] stream) takes at most n elements if n is given.
+    fn stream_to_list(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let limit = match args.len() {
+            1 => None,
+            2 => Some(count_arg(&args[0])?),
+            _ => return Err("Incorrect argument count for 'stream->list' function.".to_string()),
+        };
+
+        let stream = args.pop().unwrap();
+        stream_arg(&stream)?;
+
+        Ok(next_element(stream, Vec::new(), limit, k))
+    }
+}
+
+// Forces a stream, then applies an operation to it.
+fn stream_op(args: Vec<Expr>, op: StreamOp, name: &str, k: Cont) -> Result<State, String> {
+    if args.len() != 1 {
+        return Err(format!("Incorrect argument count for '{}' function.", name));
+    }
+
+    stream_arg(&args[0])?;
+
+    Ok(promise::force(args[0].to_owned(), k.push(Pending::StreamCell { op })))
+}
+
+// Applies an operation to the value of a forced stream.
+pub fn resume_stream_op(cell: Expr, op: StreamOp, k: Cont) -> Result<State, String> {
+    let pair = stream_cell(cell)?;
+
+    match (op, pair) {
+        (StreamOp::Car, Some((first, _))) => Ok(promise::force(first, k)),
+        (StreamOp::Cdr, Some((_, rest))) => Ok(State::Return(rest, k)),
+        (StreamOp::Car, None) | (StreamOp::Cdr, None) => {
+            Err("Cannot take an element of an empty stream.".to_string())
+        }
+        (StreamOp::IsNull, pair) => Ok(State::Return(boolean(pair.is_none()), k)),
+        (StreamOp::IsPair, pair) => Ok(State::Return(boolean(pair.is_some()), k)),
+    }
+}
+
+/**
+ * Forces the next element of a stream being collected into a list, or
+ * returns the list once the stream or the limit is reached.
+ *
+ * @param stream Rest of the stream.
+ * @param items Elements collected so far.
+ * @param limit Most elements to collect, if any.
+ * @param k Continuation of 'stream->list'.
+ * @return Next step of the evaluator.
+ */
+pub fn next_element(stream: Expr, items: Vec<Expr>, limit: Option<usize>, k: Cont) -> State {
+    if limit == Some(items.len()) {
+        return State::Return(Expr::List(items), k);
+    }
+
+    promise::force(stream, k.push(Pending::StreamToList { items, limit }))
+}
+
+// Forces the first element of the stream once its cell is known.
+pub fn resume_to_list(
+    cell: Expr,
+    items: Vec<Expr>,
+    limit: Option<usize>,
+    k: Cont,
+) -> Result<State, String> {
+    match stream_cell(cell)? {
+        Some((first, rest)) => {
+            let k = k.push(Pending::StreamElement { items, rest, limit });
+            Ok(promise::force(first, k))
+        }
+        None => Ok(State::Return(Expr::List(items), k)),
+    }
+}
+
+// First element and rest of a forced stream, none at its end.
+fn stream_cell(cell: Expr) -> Result<Option<(Expr, Expr)>, String> {
+    match cell {
+        Expr::List(list) if list.is_empty() => Ok(None),
+        Expr::List(list) if list.len() == 2 => {
+            let mut list = list.into_iter();
+            Ok(Some((list.next().unwrap(), list.next().unwrap())))
+        }
+        _ => Err("Value is not a stream.".to_string()),
+    }
+}
+
+fn stream_arg(expr: &Expr) -> Result<(), String> {
+    match promise::promise_arg(expr) {
+        Some(_) => Ok(()),
+        None => Err("Value is not a stream.".to_string()),
+    }
+}
+
+fn count_arg(expr: &Expr) -> Result<usize, String> {
+    if let Expr::Atom(atom) = expr {
+        if let Atom::Number(n) = &**atom {
+            if let (true, Some(count)) = (n.is_integer(), n.to_usize()) {
+                return Ok(count);
+            }
+        }
+    }
+
+    Err("Count of 'stream->list' must be a non-negative integer.".to_string())
+}
+
+fn boolean(b: bool) -> Expr {
+    Expr::Atom(Box::new(Atom::Boolean(b)))
+}
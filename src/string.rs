@@ -0,0 +1,769 @@
+use crate::boolean;
+use crate::env::Env;
+use crate::list;
+use crate::machine::{Cont, Pending, State};
+use crate::syntax::{self, Atom, Expr};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+
+pub trait StringOps {
+    fn string_length(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_ref(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn substring(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_copy(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_append(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_upcase(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_downcase(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_index(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn string_contains(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_split(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_join(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_trim(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_trim_right(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_trim_both(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_pad(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_pad_right(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_prefix(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_suffix(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_replace(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_for_each(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn string_map(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn string_eq(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_lt(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_gt(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_le(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_ge(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_ci_eq(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_ci_lt(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_ci_gt(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_ci_le(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_ci_ge(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn make_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_set(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_fill(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn make_string_builder(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn string_builder_append(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn builder_to_string(&mut self, args: &[Expr]) -> Result<Expr, String>;
+}
+
+/**
+ * String made by 'make-string' or 'string-copy', which 'string-set!' and
+ * 'string-fill!' can change. String literals can't be changed, so only a
+ * mutable string is shared between the places that hold it.
+ */
+#[derive(Clone)]
+pub struct MutableString(Rc<RefCell<Vec<char>>>);
+
+/**
+ * Accumulates text for 'builder->string', appending to it doesn't copy
+ * what was appended before.
+ */
+#[derive(Clone)]
+pub struct StringBuilder(Rc<RefCell<String>>);
+
+impl MutableString {
+    pub fn new(chars: Vec<char>) -> MutableString {
+        MutableString(Rc::new(RefCell::new(chars)))
+    }
+
+    pub fn text(&self) -> String {
+        self.0.borrow().iter().collect()
+    }
+}
+
+impl fmt::Debug for MutableString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MutableString({:?})", self.text())
+    }
+}
+
+// Two mutable strings are only equal if they are the same object.
+impl PartialEq for MutableString {
+    fn eq(&self, other: &MutableString) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for MutableString {}
+
+impl fmt::Debug for StringBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StringBuilder({} bytes)", self.0.borrow().len())
+    }
+}
+
+impl PartialEq for StringBuilder {
+    fn eq(&self, other: &StringBuilder) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for StringBuilder {}
+
+// Indexes and lengths of strings count characters, not bytes.
+impl StringOps for Env {
+    fn string_length(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [s] => Ok(number_value(string_arg(s, syntax::STRING_LENGTH_FN)?.chars().count())),
+            _ => Err(arg_count_error(syntax::STRING_LENGTH_FN)),
+        }
+    }
+
+    fn string_ref(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_REF_FN;
+
+        match args {
+            [s, index] => {
+                let s = string_arg(s, name)?;
+                let index = index_arg(index, name)?;
+
+                match s.chars().nth(index) {
+                    Some(c) => Ok(Expr::Atom(Box::new(Atom::Char(c)))),
+                    None => Err(format!("Index {} is out of range for '{}'.", index, name)),
+                }
+            }
+            _ => Err(arg_count_error(name)),
+        }
+    }
+
+    fn substring(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if args.len() < 2 {
+            return Err(arg_count_error(syntax::SUBSTRING_FN));
+        }
+
+        slice(args, syntax::SUBSTRING_FN)
+    }
+
+    // The whole string, or the part between the optional start and end,
+    // as a new mutable string.
+    fn string_copy(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match slice(args, syntax::STRING_COPY_FN)? {
+            Expr::Atom(atom) => match *atom {
+                Atom::StringLiteral(s) => Ok(mutable_value(s.chars().collect())),
+                other => Ok(Expr::Atom(Box::new(other))),
+            },
+            list => Ok(list),
+        }
+    }
+
+    fn string_append(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let mut result = String::new();
+
+        for arg in args {
+            result.push_str(&string_arg(arg, syntax::STRING_APPEND_FN)?);
+        }
+
+        Ok(string_value(result))
+    }
+
+    fn string_upcase(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [s] => Ok(string_value(string_arg(s, syntax::STRING_UPCASE_FN)?.to_uppercase())),
+            _ => Err(arg_count_error(syntax::STRING_UPCASE_FN)),
+        }
+    }
+
+    fn string_downcase(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [s] => Ok(string_value(string_arg(s, syntax::STRING_DOWNCASE_FN)?.to_lowercase())),
+            _ => Err(arg_count_error(syntax::STRING_DOWNCASE_FN)),
+        }
+    }
+
+    // Finds the first character that is the given one, or that passes the
+    // given predicate.
+    fn string_index(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let name = syntax::STRING_INDEX_FN;
+
+        match args.as_slice() {
+            [s, test] => {
+                let chars: Vec<char> = string_arg(s, name)?.chars().collect();
+
+                match char_of(test) {
+                    Some(c) => {
+                        let index = chars.iter().position(|x| *x == c);
+
+                        Ok(State::Return(index.map_or_else(false_value, number_value), k))
+                    }
+                    None => Ok(next_index(chars, 0, test.to_owned(), k)),
+                }
+            }
+            _ => Err(arg_count_error(name)),
+        }
+    }
+
+    fn string_contains(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_CONTAINS_FN;
+
+        match args {
+            [s, pattern] => {
+                let s = string_arg(s, name)?;
+                let pattern = string_arg(pattern, name)?;
+
+                Ok(s.find(&pattern).map_or_else(false_value, |i| char_index(&s, i)))
+            }
+            _ => Err(arg_count_error(name)),
+        }
+    }
+
+    // Without a delimiter the string is split on whitespace, and empty
+    // pieces are dropped.
+    fn string_split(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_SPLIT_FN;
+
+        let pieces: Vec<String> = match args {
+            [s] => string_arg(s, name)?.split_whitespace().map(String::from).collect(),
+            [s, delimiter] => {
+                let s = string_arg(s, name)?;
+                let delimiter = match char_of(delimiter) {
+                    Some(c) => c.to_string(),
+                    None => string_arg(delimiter, name)?,
+                };
+
+                if delimiter.is_empty() {
+                    return Err(format!("Delimiter of '{}' cannot be empty.", name));
+                }
+
+                s.split(delimiter.as_str()).map(String::from).collect()
+            }
+            _ => return Err(arg_count_error(name)),
+        };
+
+        Ok(Expr::List(pieces.into_iter().map(string_value).collect()))
+    }
+
+    // The delimiter is a space unless one is given.
+    fn string_join(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_JOIN_FN;
+
+        let (list, delimiter) = match args {
+            [Expr::List(list)] => (list, " ".to_string()),
+            [Expr::List(list), delimiter] => (list, string_arg(delimiter, name)?),
+            [_] | [_, _] => return Err(format!("First argument of '{}' must be a list.", name)),
+            _ => return Err(arg_count_error(name)),
+        };
+
+        let pieces = list
+            .iter()
+            .map(|x| string_arg(x, name))
+            .collect::<Result<Vec<String>, String>>()?;
+
+        Ok(string_value(pieces.join(&delimiter)))
+    }
+
+    // Trims the start of the string, like SRFI-13.
+    fn string_trim(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        trim(args, syntax::STRING_TRIM_FN, true, false)
+    }
+
+    fn string_trim_right(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        trim(args, syntax::STRING_TRIM_RIGHT_FN, false, true)
+    }
+
+    fn string_trim_both(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        trim(args, syntax::STRING_TRIM_BOTH_FN, true, true)
+    }
+
+    // Pads on the left, or keeps the last characters of a string that is
+    // too long, which right-aligns it.
+    fn string_pad(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        pad(args, syntax::STRING_PAD_FN, true)
+    }
+
+    fn string_pad_right(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        pad(args, syntax::STRING_PAD_RIGHT_FN, false)
+    }
+
+    fn string_prefix(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (prefix, s) = string_pair(args, syntax::STRING_PREFIX_P_FN)?;
+
+        Ok(boolean_value(s.starts_with(&prefix)))
+    }
+
+    fn string_suffix(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let (suffix, s) = string_pair(args, syntax::STRING_SUFFIX_P_FN)?;
+
+        Ok(boolean_value(s.ends_with(&suffix)))
+    }
+
+    // Replaces every occurrence of a substring.
+    fn string_replace(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_REPLACE_FN;
+
+        match args {
+            [s, from, to] => {
+                let s = string_arg(s, name)?;
+                let from = string_arg(from, name)?;
+                let to = string_arg(to, name)?;
+
+                if from.is_empty() {
+                    return Err(format!("String replaced by '{}' cannot be empty.", name));
+                }
+
+                Ok(string_value(s.replace(&from, &to)))
+            }
+            _ => Err(arg_count_error(name)),
+        }
+    }
+
+    // With several strings the procedure gets one character of each, and
+    // stops at the end of the shortest.
+    fn string_for_each(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let (procedure, calls) = procedure_and_chars(&args, syntax::STRING_FOR_EACH_FN)?;
+
+        Ok(list::next_call(procedure, calls, Vec::new(), false, k))
+    }
+
+    // The procedure must return characters, which are collected into the
+    // resulting string.
+    fn string_map(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        let (procedure, calls) = procedure_and_chars(&args, syntax::STRING_MAP_FN)?;
+        let k = k.push(Pending::CharsToString);
+
+        Ok(list::next_call(procedure, calls, Vec::new(), true, k))
+    }
+
+    fn string_eq(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_EQ_FN, false, |x| x == Ordering::Equal)
+    }
+
+    fn string_lt(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_LT_FN, false, |x| x == Ordering::Less)
+    }
+
+    fn string_gt(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_GT_FN, false, |x| x == Ordering::Greater)
+    }
+
+    fn string_le(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_LE_FN, false, |x| x != Ordering::Greater)
+    }
+
+    fn string_ge(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_GE_FN, false, |x| x != Ordering::Less)
+    }
+
+    fn string_ci_eq(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_CI_EQ_FN, true, |x| x == Ordering::Equal)
+    }
+
+    fn string_ci_lt(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_CI_LT_FN, true, |x| x == Ordering::Less)
+    }
+
+    fn string_ci_gt(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_CI_GT_FN, true, |x| x == Ordering::Greater)
+    }
+
+    fn string_ci_le(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_CI_LE_FN, true, |x| x != Ordering::Greater)
+    }
+
+    fn string_ci_ge(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        compare_all(args, syntax::STRING_CI_GE_FN, true, |x| x != Ordering::Less)
+    }
+
+    // Filled with spaces unless a character is given.
+    fn make_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::MAKE_STRING_FN;
+
+        let (length, c) = match args {
+            [length] => (index_arg(length, name)?, ' '),
+            [length, c] => match char_of(c) {
+                Some(c) => (index_arg(length, name)?, c),
+                None => return Err(format!("Second argument of '{}' must be a character.", name)),
+            },
+            _ => return Err(arg_count_error(name)),
+        };
+
+        Ok(mutable_value(vec![c; length]))
+    }
+
+    fn string_set(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_SET_FN;
+
+        match args {
+            [s, index, c] => {
+                let s = mutable_arg(s, name)?;
+                let index = index_arg(index, name)?;
+                let c = char_of(c)
+                    .ok_or_else(|| format!("Third argument of '{}' must be a character.", name))?;
+
+                match s.0.borrow_mut().get_mut(index) {
+                    Some(slot) => *slot = c,
+                    None => return Err(format!("Index {} is out of range for '{}'.", index, name)),
+                }
+
+                Ok(Expr::Atom(Box::new(Atom::Nil)))
+            }
+            _ => Err(arg_count_error(name)),
+        }
+    }
+
+    // Fills the whole string, or the part between the optional start and
+    // end.
+    fn string_fill(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_FILL_FN;
+
+        if args.len() < 2 || args.len() > 4 {
+            return Err(arg_count_error(name));
+        }
+
+        let s = mutable_arg(&args[0], name)?;
+        let c = char_of(&args[1])
+            .ok_or_else(|| format!("Second argument of '{}' must be a character.", name))?;
+        let mut chars = s.0.borrow_mut();
+        let start = args.get(2).map_or(Ok(0), |x| index_arg(x, name))?;
+        let end = args.get(3).map_or(Ok(chars.len()), |x| index_arg(x, name))?;
+
+        if start > end || end > chars.len() {
+            return Err(format!(
+                "Range {} to {} is out of bounds for '{}' on a string of length {}.",
+                start,
+                end,
+                name,
+                chars.len()
+            ));
+        }
+
+        chars[start..end].iter_mut().for_each(|x| *x = c);
+
+        Ok(Expr::Atom(Box::new(Atom::Nil)))
+    }
+
+    fn make_string_builder(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        if !args.is_empty() {
+            return Err(arg_count_error(syntax::MAKE_STRING_BUILDER_FN));
+        }
+
+        let builder = StringBuilder(Rc::new(RefCell::new(String::new())));
+
+        Ok(Expr::Atom(Box::new(Atom::StringBuilder(builder))))
+    }
+
+    // Appends any number of strings and characters, in order.
+    fn string_builder_append(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        let name = syntax::STRING_BUILDER_APPEND_FN;
+
+        let builder = match args.first() {
+            Some(builder) => builder_arg(builder, name)?,
+            None => return Err(arg_count_error(name)),
+        };
+
+        for arg in &args[1..] {
+            match char_of(arg) {
+                Some(c) => builder.0.borrow_mut().push(c),
+                None => builder.0.borrow_mut().push_str(&string_arg(arg, name).map_err(|_| {
+                    format!("Only strings and characters can be appended by '{}'.", name)
+                })?),
+            }
+        }
+
+        Ok(Expr::Atom(Box::new(Atom::Nil)))
+    }
+
+    // The builder keeps its text, so it can be appended to again.
+    fn builder_to_string(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        match args {
+            [builder] => {
+                let builder = builder_arg(builder, syntax::BUILDER_TO_STRING_FN)?;
+                let text = builder.0.borrow().clone();
+
+                Ok(string_value(text))
+            }
+            _ => Err(arg_count_error(syntax::BUILDER_TO_STRING_FN)),
+        }
+    }
+}
+
+/**
+ * Calls the predicate of 'string-index' on the next character, or returns
+ * false once there are none left.
+ *
+ * @param chars Characters of the string.
+ * @param index Position of the next character.
+ * @param predicate Procedure that takes a character.
+ * @param k Continuation of the search.
+ * @return Next step of the evaluator.
+ */
+pub fn next_index(chars: Vec<char>, index: usize, predicate: Expr, k: Cont) -> State {
+    match chars.get(index) {
+        Some(c) => {
+            let c = Expr::Atom(Box::new(Atom::Char(*c)));
+            let k = k.push(Pending::StringIndex {
+                chars,
+                index,
+                predicate: predicate.to_owned(),
+            });
+
+            State::Apply(predicate, vec![c], k)
+        }
+        None => State::Return(false_value(), k),
+    }
+}
+
+// Result of the predicate of 'string-index' for one character.
+pub fn resume_string_index(
+    value: Expr,
+    chars: Vec<char>,
+    index: usize,
+    predicate: Expr,
+    k: Cont,
+) -> State {
+    if boolean::is_truthy(&value) {
+        State::Return(number_value(index), k)
+    } else {
+        next_index(chars, index + 1, predicate, k)
+    }
+}
+
+// Turns the characters returned by the procedure of 'string-map' into a
+// string.
+pub fn chars_to_string(value: Expr) -> Result<Expr, String> {
+    let chars = match value {
+        Expr::List(chars) => chars,
+        Expr::Atom(_) => return Ok(value),
+    };
+
+    chars
+        .iter()
+        .map(|x| {
+            char_of(x).ok_or_else(|| {
+                format!("Procedure of '{}' must return characters.", syntax::STRING_MAP_FN)
+            })
+        })
+        .collect::<Result<String, String>>()
+        .map(string_value)
+}
+
+/**
+ * Gets the text of a string argument.
+ *
+ * @param expr Evaluated argument.
+ * @param name Name of the procedure, used in error messages.
+ * @return Text of the string.
+ */
+pub fn string_arg(expr: &Expr, name: &str) -> Result<String, String> {
+    match expr {
+        Expr::Atom(atom) => {
+            text_of(atom).ok_or_else(|| format!("Arguments of '{}' must be strings.", name))
+        }
+        Expr::List(_) => Err(format!("Arguments of '{}' must be strings.", name)),
+    }
+}
+
+// Text of a string literal or of the current contents of a mutable string.
+pub fn text_of(atom: &Atom) -> Option<String> {
+    match atom {
+        Atom::StringLiteral(s) => Some(s.to_owned()),
+        Atom::MutableString(s) => Some(s.text()),
+        _ => None,
+    }
+}
+
+fn mutable_arg(expr: &Expr, name: &str) -> Result<MutableString, String> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::MutableString(s) => Ok(s.clone()),
+            Atom::StringLiteral(_) => Err(format!(
+                "String literals can't be changed by '{}', use a copy made by '{}'.",
+                name,
+                syntax::STRING_COPY_FN
+            )),
+            _ => Err(format!("First argument of '{}' must be a mutable string.", name)),
+        },
+        Expr::List(_) => Err(format!("First argument of '{}' must be a mutable string.", name)),
+    }
+}
+
+fn builder_arg(expr: &Expr, name: &str) -> Result<StringBuilder, String> {
+    match expr {
+        Expr::Atom(atom) => match &**atom {
+            Atom::StringBuilder(builder) => Ok(builder.clone()),
+            _ => Err(format!("First argument of '{}' must be a string builder.", name)),
+        },
+        Expr::List(_) => Err(format!("First argument of '{}' must be a string builder.", name)),
+    }
+}
+
+fn index_arg(expr: &Expr, name: &str) -> Result<usize, String> {
+    if let Expr::Atom(atom) = expr {
+        if let Atom::Number(n) = &**atom {
+            if let (true, Some(index)) = (n.is_integer(), n.to_usize()) {
+                return Ok(index);
+            }
+        }
+    }
+
+    Err(format!("Indexes given to '{}' must be non-negative integers.", name))
+}
+
+fn char_of(expr: &Expr) -> Option<char> {
+    match expr {
+        Expr::Atom(atom) => match **atom {
+            Atom::Char(c) => Some(c),
+            _ => None,
+        },
+        Expr::List(_) => None,
+    }
+}
+
+// Part of a string between an optional start and end, for 'substring'
+// and 'string-copy'.
+fn slice(args: &[Expr], name: &str) -> Result<Expr, String> {
+    if args.is_empty() || args.len() > 3 {
+        return Err(arg_count_error(name));
+    }
+
+    let chars: Vec<char> = string_arg(&args[0], name)?.chars().collect();
+    let start = args.get(1).map_or(Ok(0), |x| index_arg(x, name))?;
+    let end = args.get(2).map_or(Ok(chars.len()), |x| index_arg(x, name))?;
+
+    if start > end || end > chars.len() {
+        return Err(format!(
+            "Range {} to {} is out of bounds for '{}' on a string of length {}.",
+            start,
+            end,
+            name,
+            chars.len()
+        ));
+    }
+
+    Ok(string_value(chars[start..end].iter().collect()))
+}
+
+// Removes whitespace, or the given character, from either end.
+fn trim(args: &[Expr], name: &str, start: bool, end: bool) -> Result<Expr, String> {
+    let (s, c) = match args {
+        [s] => (string_arg(s, name)?, None),
+        [s, c] => match char_of(c) {
+            Some(c) => (string_arg(s, name)?, Some(c)),
+            None => return Err(format!("Second argument of '{}' must be a character.", name)),
+        },
+        _ => return Err(arg_count_error(name)),
+    };
+
+    let is_trimmed = |x: char| match c {
+        Some(c) => x == c,
+        None => x.is_whitespace(),
+    };
+
+    let mut result = s.as_str();
+
+    if start {
+        result = result.trim_start_matches(is_trimmed);
+    }
+
+    if end {
+        result = result.trim_end_matches(is_trimmed);
+    }
+
+    Ok(string_value(result.to_string()))
+}
+
+// Pads with spaces, or the given character, to the given length.
+fn pad(args: &[Expr], name: &str, left: bool) -> Result<Expr, String> {
+    let (s, length, c) = match args {
+        [s, length] => (string_arg(s, name)?, index_arg(length, name)?, ' '),
+        [s, length, c] => match char_of(c) {
+            Some(c) => (string_arg(s, name)?, index_arg(length, name)?, c),
+            None => return Err(format!("Third argument of '{}' must be a character.", name)),
+        },
+        _ => return Err(arg_count_error(name)),
+    };
+
+    let chars: Vec<char> = s.chars().collect();
+    let padding: String = std::iter::repeat_n(c, length.saturating_sub(chars.len())).collect();
+
+    let result: String = if chars.len() >= length && left {
+        chars[chars.len() - length..].iter().collect()
+    } else if chars.len() >= length {
+        chars[..length].iter().collect()
+    } else if left {
+        padding + &s
+    } else {
+        s + &padding
+    };
+
+    Ok(string_value(result))
+}
+
+fn string_pair(args: &[Expr], name: &str) -> Result<(String, String), String> {
+    match args {
+        [a, b] => Ok((string_arg(a, name)?, string_arg(b, name)?)),
+        _ => Err(arg_count_error(name)),
+    }
+}
+
+// Arguments for each call of 'string-map' or 'string-for-each', taking the
+// n-th character of every string.
+fn procedure_and_chars(args: &[Expr], name: &str) -> Result<(Expr, Vec<Vec<Expr>>), String> {
+    if args.len() < 2 {
+        return Err(arg_count_error(name));
+    }
+
+    let strings = args[1..]
+        .iter()
+        .map(|x| string_arg(x, name).map(|s| s.chars().collect()))
+        .collect::<Result<Vec<Vec<char>>, String>>()?;
+
+    let len = strings.iter().map(|x| x.len()).min().unwrap_or(0);
+
+    let calls = (0..len)
+        .rev()
+        .map(|i| strings.iter().map(|x| Expr::Atom(Box::new(Atom::Char(x[i])))).collect())
+        .collect();
+
+    Ok((args[0].to_owned(), calls))
+}
+
+// Whether every string is in the given order with the next one.
+fn compare_all(
+    args: &[Expr],
+    name: &str,
+    fold_case: bool,
+    test: fn(Ordering) -> bool,
+) -> Result<Expr, String> {
+    if args.len() < 2 {
+        return Err(arg_count_error(name));
+    }
+
+    let strings = args
+        .iter()
+        .map(|x| string_arg(x, name).map(|s| if fold_case { s.to_lowercase() } else { s }))
+        .collect::<Result<Vec<String>, String>>()?;
+
+    Ok(boolean_value(strings.windows(2).all(|x| test(x[0].cmp(&x[1])))))
+}
+
+// Index in characters of a byte offset into a string.
+fn char_index(s: &str, byte_index: usize) -> Expr {
+    number_value(s[..byte_index].chars().count())
+}
+
+fn arg_count_error(name: &str) -> String {
+    format!("Incorrect argument count for '{}' function.", name)
+}
+
+fn string_value(text: String) -> Expr {
+    Expr::Atom(Box::new(Atom::StringLiteral(text)))
+}
+
+fn mutable_value(chars: Vec<char>) -> Expr {
+    Expr::Atom(Box::new(Atom::MutableString(MutableString::new(chars))))
+}
+
+fn number_value(n: usize) -> Expr {
+    Expr::Atom(Box::new(Atom::Number(BigDecimal::from(n as u64))))
+}
+
+fn boolean_value(b: bool) -> Expr {
+    Expr::Atom(Box::new(Atom::Boolean(b)))
+}
+
+fn false_value() -> Expr {
+    boolean_value(false)
+}
@@ -1,4 +1,21 @@
+use crate::env::Scope;
+use crate::control::Continuation;
+use crate::environment::Environment;
+use crate::exception::ErrorObject;
+use crate::generator::Generator;
+use crate::promise::Promise;
+use crate::parameter::Parameter;
+use crate::params::Params;
+use crate::port::Port;
+use crate::primitive::Primitive;
+use crate::random::RandomSource;
+use crate::record::{Record, RecordProcedure, RecordType};
+use crate::regexp::{Regexp, RegexpMatch};
+use crate::string::{MutableString, StringBuilder};
 use bigdecimal::BigDecimal;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
 use ansi_term::Colour::{Blue, Green, Purple, Red, Yellow};
 
 // math operator key words
@@ -14,9 +31,14 @@ pub const CAR_OP: &str = "car";
 pub const CDR_OP: &str = "cdr";
 pub const QT_OP: &str = "quote";
 pub const DEF_OP: &str = "define";
+pub const SET_OP: &str = "set!";
 pub const ATM_OP: &str = "atom";
 pub const FUN_OP: &str = "lambda";
+pub const FUN_STAR_OP: &str = "lambda*";
+pub const DEF_STAR_OP: &str = "define*";
+pub const CASE_FUN_OP: &str = "case-lambda";
 pub const STR_OP: &str = "string";
+pub const BEGIN_OP: &str = "begin";
 
 // logical operations
 pub const IF_OP: &str = "if";
@@ -24,31 +46,282 @@ pub const OR_OP: &str = "or";
 pub const AND_OP: &str = "and";
 pub const NOT_OP: &str = "not";
 
+// binding forms
+pub const LET_OP: &str = "let";
+pub const LET_STAR_OP: &str = "let*";
+pub const LETREC_OP: &str = "letrec";
+pub const LETREC_STAR_OP: &str = "letrec*";
+pub const COND_OP: &str = "cond";
+pub const CASE_OP: &str = "case";
+pub const WHEN_OP: &str = "when";
+pub const UNLESS_OP: &str = "unless";
+pub const GUARD_OP: &str = "guard";
+pub const LET_VALUES_OP: &str = "let-values";
+pub const LET_STAR_VALUES_OP: &str = "let*-values";
+pub const DEFINE_VALUES_OP: &str = "define-values";
+pub const RECEIVE_OP: &str = "receive";
+pub const DELAY_OP: &str = "delay";
+pub const DELAY_FORCE_OP: &str = "delay-force";
+pub const STREAM_CONS_OP: &str = "stream-cons";
+pub const PARAMETERIZE_OP: &str = "parameterize";
+pub const DEFINE_RECORD_TYPE_OP: &str = "define-record-type";
+
+// clause keywords
+pub const ELSE_KW: &str = "else";
+pub const ARROW_KW: &str = "=>";
+
 // literals
 pub const FALSE_LIT: &str = "#f";
 pub const TRUE_LIT: &str = "#t";
 pub const NIL_LIT: &str = "nil";
 
+// printed forms of opaque values
+pub const RANDOM_SOURCE_REPR: &str = "#<random-source>";
+pub const ENVIRONMENT_REPR: &str = "#<environment>";
+pub const CONTINUATION_REPR: &str = "#<continuation>";
+pub const PROMISE_REPR: &str = "#<promise>";
+pub const GENERATOR_REPR: &str = "#<generator>";
+pub const YIELD_REPR: &str = "#<yield>";
+pub const EOF_REPR: &str = "#<eof>";
+pub const PARAMETER_REPR: &str = "#<parameter>";
+pub const OUTPUT_PORT_REPR: &str = "#<output-port>";
+pub const STRING_BUILDER_REPR: &str = "#<string-builder>";
+
+// type predicates
+pub const NUMBER_P_FN: &str = "number?";
+pub const INTEGER_P_FN: &str = "integer?";
+pub const RATIONAL_P_FN: &str = "rational?";
+pub const REAL_P_FN: &str = "real?";
+pub const STRING_P_FN: &str = "string?";
+pub const CHAR_P_FN: &str = "char?";
+pub const SYMBOL_P_FN: &str = "symbol?";
+pub const BOOLEAN_P_FN: &str = "boolean?";
+pub const PROCEDURE_P_FN: &str = "procedure?";
+pub const NULL_P_FN: &str = "null?";
+pub const LIST_P_FN: &str = "list?";
+pub const PAIR_P_FN: &str = "pair?";
+pub const VECTOR_P_FN: &str = "vector?";
+pub const BOOLEAN_EQ_FN: &str = "boolean=?";
+
+// conversions
+pub const SYMBOL_TO_STRING_FN: &str = "symbol->string";
+pub const STRING_TO_SYMBOL_FN: &str = "string->symbol";
+pub const LIST_TO_STRING_FN: &str = "list->string";
+pub const STRING_TO_LIST_FN: &str = "string->list";
+pub const NUMBER_TO_STRING_FN: &str = "number->string";
+
+// strings
+pub const STRING_LENGTH_FN: &str = "string-length";
+pub const STRING_REF_FN: &str = "string-ref";
+pub const SUBSTRING_FN: &str = "substring";
+pub const STRING_COPY_FN: &str = "string-copy";
+pub const STRING_APPEND_FN: &str = "string-append";
+pub const STRING_UPCASE_FN: &str = "string-upcase";
+pub const STRING_DOWNCASE_FN: &str = "string-downcase";
+pub const STRING_INDEX_FN: &str = "string-index";
+pub const STRING_CONTAINS_FN: &str = "string-contains";
+pub const STRING_SPLIT_FN: &str = "string-split";
+pub const STRING_JOIN_FN: &str = "string-join";
+pub const STRING_TRIM_FN: &str = "string-trim";
+pub const STRING_TRIM_RIGHT_FN: &str = "string-trim-right";
+pub const STRING_TRIM_BOTH_FN: &str = "string-trim-both";
+pub const STRING_PAD_FN: &str = "string-pad";
+pub const STRING_PAD_RIGHT_FN: &str = "string-pad-right";
+pub const STRING_PREFIX_P_FN: &str = "string-prefix?";
+pub const STRING_SUFFIX_P_FN: &str = "string-suffix?";
+pub const STRING_REPLACE_FN: &str = "string-replace";
+pub const STRING_FOR_EACH_FN: &str = "string-for-each";
+pub const STRING_MAP_FN: &str = "string-map";
+pub const STRING_EQ_FN: &str = "string=?";
+pub const STRING_LT_FN: &str = "string<?";
+pub const STRING_GT_FN: &str = "string>?";
+pub const STRING_LE_FN: &str = "string<=?";
+pub const STRING_GE_FN: &str = "string>=?";
+pub const STRING_CI_EQ_FN: &str = "string-ci=?";
+pub const STRING_CI_LT_FN: &str = "string-ci<?";
+pub const STRING_CI_GT_FN: &str = "string-ci>?";
+pub const STRING_CI_LE_FN: &str = "string-ci<=?";
+pub const STRING_CI_GE_FN: &str = "string-ci>=?";
+pub const MAKE_STRING_FN: &str = "make-string";
+pub const STRING_SET_FN: &str = "string-set!";
+pub const STRING_FILL_FN: &str = "string-fill!";
+pub const MAKE_STRING_BUILDER_FN: &str = "make-string-builder";
+pub const STRING_BUILDER_APPEND_FN: &str = "string-builder-append!";
+pub const BUILDER_TO_STRING_FN: &str = "builder->string";
+pub const FORMAT_FN: &str = "format";
+
+// regular expressions
+pub const REGEXP_FN: &str = "regexp";
+pub const REGEXP_P_FN: &str = "regexp?";
+pub const REGEXP_MATCH_FN: &str = "regexp-match";
+pub const REGEXP_SEARCH_FN: &str = "regexp-search";
+pub const REGEXP_MATCH_ALL_FN: &str = "regexp-match-all";
+pub const REGEXP_REPLACE_FN: &str = "regexp-replace";
+pub const REGEXP_REPLACE_ALL_FN: &str = "regexp-replace-all";
+pub const REGEXP_SPLIT_FN: &str = "regexp-split";
+pub const REGEXP_MATCH_COUNT_FN: &str = "regexp-match-count";
+pub const REGEXP_MATCH_SUBMATCH_FN: &str = "regexp-match-submatch";
+pub const REGEXP_MATCH_SUBMATCH_START_FN: &str = "regexp-match-submatch-start";
+pub const REGEXP_MATCH_SUBMATCH_END_FN: &str = "regexp-match-submatch-end";
+pub const REGEXP_MATCH_TO_LIST_FN: &str = "regexp-match->list";
+
+// equality
+pub const EQ_P_FN: &str = "eq?";
+pub const EQV_P_FN: &str = "eqv?";
+pub const EQUAL_P_FN: &str = "equal?";
+pub const MEMQ_FN: &str = "memq";
+pub const MEMV_FN: &str = "memv";
+pub const MEMBER_FN: &str = "member";
+pub const ASSQ_FN: &str = "assq";
+pub const ASSV_FN: &str = "assv";
+pub const ASSOC_FN: &str = "assoc";
+
+// list functions
+pub const LIST_FN: &str = "list";
+pub const MAP_FN: &str = "map";
+pub const FOR_EACH_FN: &str = "for-each";
+
+// evaluation
+pub const APPLY_FN: &str = "apply";
+pub const EVAL_FN: &str = "eval";
+pub const INTERACTION_ENV_FN: &str = "interaction-environment";
+pub const ENVIRONMENT_FN: &str = "environment";
+pub const EMPTY_ENV_FN: &str = "make-empty-environment";
+
+// multiple values
+pub const VALUES_FN: &str = "values";
+pub const CALL_WITH_VALUES_FN: &str = "call-with-values";
+pub const EXACT_INT_SQRT_FN: &str = "exact-integer-sqrt";
+pub const FLOOR_DIV_FN: &str = "floor/";
+pub const TRUNCATE_DIV_FN: &str = "truncate/";
+
+// promises and streams
+pub const MAKE_PROMISE_FN: &str = "make-promise";
+pub const PROMISE_P_FN: &str = "promise?";
+pub const FORCE_FN: &str = "force";
+pub const STREAM_CAR_FN: &str = "stream-car";
+pub const STREAM_CDR_FN: &str = "stream-cdr";
+pub const STREAM_NULL_P_FN: &str = "stream-null?";
+pub const STREAM_PAIR_P_FN: &str = "stream-pair?";
+pub const STREAM_TO_LIST_FN: &str = "stream->list";
+
+// generators
+pub const MAKE_GENERATOR_FN: &str = "make-generator";
+pub const GENERATOR_TO_LIST_FN: &str = "generator->list";
+pub const EOF_OBJECT_FN: &str = "eof-object";
+pub const EOF_OBJECT_P_FN: &str = "eof-object?";
+
+// parameters
+pub const MAKE_PARAMETER_FN: &str = "make-parameter";
+pub const CURRENT_OUTPUT_PORT_FN: &str = "current-output-port";
+pub const CURRENT_ERROR_PORT_FN: &str = "current-error-port";
+pub const NUMERIC_PRECISION_FN: &str = "numeric-precision";
+
+// control
+pub const CALL_CC_FN: &str = "call-with-current-continuation";
+pub const CALL_CC_SHORT_FN: &str = "call/cc";
+pub const DYNAMIC_WIND_FN: &str = "dynamic-wind";
+
+// exceptions
+pub const RAISE_FN: &str = "raise";
+pub const RAISE_CONTINUABLE_FN: &str = "raise-continuable";
+pub const ERROR_FN: &str = "error";
+pub const WITH_HANDLER_FN: &str = "with-exception-handler";
+pub const ERROR_OBJECT_P_FN: &str = "error-object?";
+pub const ERROR_MESSAGE_FN: &str = "error-object-message";
+pub const ERROR_IRRITANTS_FN: &str = "error-object-irritants";
+pub const FILE_ERROR_P_FN: &str = "file-error?";
+pub const READ_ERROR_P_FN: &str = "read-error?";
+
 // IO functions
 pub const SLURP_FN: &str = "slurp";
-pub const WRITE_FILE_FN: &str = "write";
+pub const SPIT_FN: &str = "spit";
+pub const APPEND_FILE_FN: &str = "append-file";
+pub const WRITE_LINES_FN: &str = "write-lines";
 pub const PRINT_FN: &str = "print";
 pub const PRINTLN_FN: &str = "println";
+pub const DISPLAY_FN: &str = "display";
+pub const WRITE_FN: &str = "write";
+pub const NEWLINE_FN: &str = "newline";
 pub const LOAD_FN: &str = "load";
+pub const OPEN_OUTPUT_STRING_FN: &str = "open-output-string";
+pub const GET_OUTPUT_STRING_FN: &str = "get-output-string";
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+// random numbers
+pub const RAND_INT_FN: &str = "random-integer";
+pub const RAND_REAL_FN: &str = "random-real";
+pub const RAND_CHOICE_FN: &str = "random-choice";
+pub const SHUFFLE_FN: &str = "shuffle";
+pub const RAND_SRC_FN: &str = "make-random-source";
+pub const DEFAULT_RAND_SRC_FN: &str = "default-random-source";
+
+#[derive(Clone)]
 pub struct LambdaDef {
-    pub params: Vec<Expr>,
-    pub body: Box<Expr>,
+    // Set once the function is bound with 'define', used in error messages.
+    pub name: Option<String>,
+    pub params: Params,
+    // Shared by every copy of the function, which gives it its identity.
+    pub body: Rc<Vec<Expr>>,
+    // Frame the function was created in.
+    pub scope: Scope,
+}
+
+// Frames can hold the function itself, so they are left out.
+impl fmt::Debug for LambdaDef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LambdaDef")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("body", &self.body)
+            .finish()
+    }
 }
 
+// Two functions are only equal if they were made by the same evaluation of
+// a lambda expression.
+impl PartialEq for LambdaDef {
+    fn eq(&self, other: &LambdaDef) -> bool {
+        Rc::ptr_eq(&self.body, &other.body)
+    }
+}
+
+impl Eq for LambdaDef {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Atom {
     Boolean(bool),
+    Char(char),
     StringLiteral(String),
+    // String made by 'make-string' or 'string-copy', it can be changed.
+    MutableString(MutableString),
+    StringBuilder(StringBuilder),
     Number(BigDecimal),
     Symbol(String),
+    Keyword(String),
     Lambda(LambdaDef),
+    CaseLambda(Vec<LambdaDef>),
+    Primitive(Primitive),
+    Environment(Environment),
+    Continuation(Continuation),
+    ErrorObject(ErrorObject),
+    Promise(Promise),
+    Generator(Generator),
+    // Procedure a generator body calls to hand out a value.
+    Yield(Generator),
+    // End of file, also returned by generators that are done.
+    Eof,
+    Parameter(Parameter),
+    Port(Port),
+    RecordType(RecordType),
+    Record(Record),
+    // Constructor, predicate, accessor or modifier of a record type.
+    RecordProcedure(RecordProcedure),
+    // Several values returned at once by 'values'.
+    Values(Vec<Expr>),
+    RandomSource(RandomSource),
+    Regexp(Regexp),
+    // Groups of a successful match of a regular expression.
+    RegexpMatch(RegexpMatch),
     Nil,
 }
 
@@ -58,6 +331,138 @@ pub enum Expr {
     Atom(Box<Atom>),
 }
 
+/**
+ * Prints a value for people to read rather than for the reader, strings
+ * and characters are written as their text.
+ *
+ * @param expr Value to print.
+ * @return Text of the value.
+ */
+pub fn print_display(expr: &Expr) -> String {
+    Writer::new(expr, true, false).print(expr)
+}
+
+/**
+ * Prints a value the way the reader reads it back. Records that appear
+ * more than once, because they are shared or part of a cycle, are labelled
+ * with #n= the first time and written as #n# after that.
+ *
+ * @param expr Value to print.
+ * @return Text of the value.
+ */
+pub fn print_write(expr: &Expr) -> String {
+    Writer::new(expr, false, false).print(expr)
+}
+
+/**
+ * Printer that labels the records it meets more than once, so shared and
+ * cyclic records are printed once rather than forever.
+ */
+struct Writer {
+    shared: HashSet<usize>,
+    labels: HashMap<usize, usize>,
+    display: bool,
+    color: bool,
+}
+
+impl Writer {
+    fn new(expr: &Expr, display: bool, color: bool) -> Writer {
+        let mut seen = HashSet::new();
+        let mut shared = HashSet::new();
+        find_shared(expr, &mut seen, &mut shared);
+
+        Writer { shared, labels: HashMap::new(), display, color }
+    }
+
+    fn print(&mut self, expr: &Expr) -> String {
+        let atom = match expr {
+            Expr::List(list) => {
+                let printed: Vec<String> = list.iter().map(|x| self.print(x)).collect();
+
+                return format!("({})", printed.join(" "));
+            }
+            Expr::Atom(atom) => atom,
+        };
+
+        match &**atom {
+            Atom::StringLiteral(s) if self.display => s.to_owned(),
+            Atom::MutableString(s) if self.display => s.text(),
+            Atom::Char(c) if self.display => c.to_string(),
+            Atom::Record(record) => self.print_record(record),
+            Atom::Values(values) => {
+                let printed: Vec<String> = values.iter().map(|x| self.print(x)).collect();
+
+                printed.join(" ")
+            }
+            Atom::ErrorObject(error) => {
+                let mut acc = format!("#<error {}", escape_string(&error.message));
+
+                for irritant in error.irritants.iter() {
+                    acc.push(' ');
+                    acc.push_str(&self.print(irritant));
+                }
+
+                acc.push('>');
+                acc
+            }
+            other => print_atom(other, &self.color),
+        }
+    }
+
+    // Type name followed by every field with its value.
+    fn print_record(&mut self, record: &Record) -> String {
+        let id = record.id();
+
+        if let Some(label) = self.labels.get(&id) {
+            return format!("#{}#", label);
+        }
+
+        let mut acc = String::new();
+
+        if self.shared.contains(&id) {
+            let label = self.labels.len();
+            self.labels.insert(id, label);
+            acc.push_str(&format!("#{}=", label));
+        }
+
+        let (name, fields) = record.fields();
+        acc.push_str(&format!("#<{}", name));
+
+        for (field, value) in fields.iter() {
+            acc.push_str(&format!(" {}: {}", field, self.print(value)));
+        }
+
+        acc.push('>');
+        acc
+    }
+}
+
+// Collects the records that are reached more than once.
+fn find_shared(expr: &Expr, seen: &mut HashSet<usize>, shared: &mut HashSet<usize>) {
+    let atom = match expr {
+        Expr::List(list) => return list.iter().for_each(|x| find_shared(x, seen, shared)),
+        Expr::Atom(atom) => atom,
+    };
+
+    match &**atom {
+        Atom::Record(record) => {
+            if !seen.insert(record.id()) {
+                shared.insert(record.id());
+                return;
+            }
+
+            for (_, value) in record.fields().1.iter() {
+                find_shared(value, seen, shared);
+            }
+        }
+        Atom::Values(values) => values.iter().for_each(|x| find_shared(x, seen, shared)),
+        Atom::ErrorObject(error) => {
+            error.irritants.iter().for_each(|x| find_shared(x, seen, shared))
+        }
+        _ => {}
+    }
+}
+
 pub fn print_tree(expr_tree: &Expr, color: &bool) -> String {
     match expr_tree {
         Expr::List(v) => print_list(v, color),
@@ -68,22 +473,113 @@ pub fn print_tree(expr_tree: &Expr, color: &bool) -> String {
 fn print_lambda(lambda: &LambdaDef, color: &bool) -> String {
     // create new string with lambda at start.
     let mut acc = String::new();
+    let is_extended = !lambda.params.optional.is_empty() || !lambda.params.keys.is_empty();
 
     acc.push('(');
-    acc.push_str(FUN_OP);
+    acc.push_str(if is_extended { FUN_STAR_OP } else { FUN_OP });
     acc.push(' ');
-    acc.push_str(&print_list(&lambda.params, color));
-    acc.push_str(&print_list(&lambda.params, color));
-    acc.push(' ');
-    acc.push_str(&print_tree(&*lambda.body, color));
+    acc.push_str(&print_clause(lambda, color));
     acc.push(')');
 
     acc
 }
 
+fn print_case_lambda(clauses: &[LambdaDef], color: &bool) -> String {
+    let mut acc = String::new();
+
+    acc.push('(');
+    acc.push_str(CASE_FUN_OP);
+
+    for clause in clauses.iter() {
+        acc.push_str(" (");
+        acc.push_str(&print_clause(clause, color));
+        acc.push(')');
+    }
+
+    acc.push(')');
+
+    acc
+}
+
+// Parameters followed by the body.
+fn print_clause(lambda: &LambdaDef, color: &bool) -> String {
+    let mut acc = print_tree(&lambda.params.to_expr(), color);
+
+    for expr in lambda.body.iter() {
+        acc.push(' ');
+        acc.push_str(&print_tree(expr, color));
+    }
+
+    acc
+}
+
+// Message and irritants, like the call to 'error' that made it.
+fn print_error_object(error: &ErrorObject, color: &bool) -> String {
+    let mut acc = String::from("#<error ");
+    acc.push_str(&escape_string(&error.message));
+
+    for irritant in error.irritants.iter() {
+        acc.push(' ');
+        acc.push_str(&print_tree(irritant, color));
+    }
+
+    acc.push('>');
+    acc
+}
+
+// Records can be cyclic, so they are printed with labels.
+fn print_record(record: &Record, color: &bool) -> String {
+    let expr = Expr::Atom(Box::new(Atom::Record(record.clone())));
+
+    Writer::new(&expr, false, *color).print(&expr)
+}
+
+// Characters that are written by name rather than as themselves.
+const CHAR_NAMES: &[(&str, char)] = &[
+    ("space", ' '),
+    ("newline", '\n'),
+    ("tab", '\t'),
+    ("return", '\r'),
+    ("null", '\0'),
+];
+
+/**
+ * Reads the part of a character literal after '#\\'.
+ *
+ * @param name A single character, or the name of one like 'space'.
+ * @return The character, if the name is known.
+ */
+pub fn char_named(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => CHAR_NAMES.iter().find(|(n, _)| *n == name).map(|(_, c)| *c),
+    }
+}
+
+// Writes a character the way it is read back.
+pub fn print_char(c: char) -> String {
+    match CHAR_NAMES.iter().find(|(_, x)| *x == c) {
+        Some((name, _)) => format!("#\\{}", name),
+        None => format!("#\\{}", c),
+    }
+}
+
+// Each value in turn, separated by spaces.
+fn print_values(values: &[Expr], color: &bool) -> String {
+    let printed: Vec<String> = values.iter().map(|x| print_tree(x, color)).collect();
+
+    printed.join(" ")
+}
+
 fn print_list(expr_list: &Vec<Expr>, color: &bool) -> String {
     let mut acc = String::new();
 
+    if expr_list.is_empty() {
+        acc.push_str("()");
+    }
+
     for (i, exp) in expr_list.iter().enumerate() {
         if i == 0 {
             acc.push('(');
@@ -112,17 +608,63 @@ fn print_atom(expr_atom: &Atom, color: &bool) -> String {
     result
 }
 
+/**
+ * Writes a string the way it is read back, between double quotes and with
+ * escape sequences.
+ *
+ * @param s Contents of the string.
+ * @return Literal that reads as the same string.
+ */
+pub fn escape_string(s: &str) -> String {
+    let mut result = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            _ => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
+
 fn print_atom_without_color(expr_atom: &Atom) -> String {
     let result = match expr_atom {
         Atom::Boolean(b) => match b {
             true => TRUE_LIT.to_string(),
             false => FALSE_LIT.to_string(),
         },
-        Atom::StringLiteral(s) => s.to_string(),
+        Atom::Char(c) => print_char(*c),
+        Atom::StringLiteral(s) => escape_string(s),
+        Atom::MutableString(s) => escape_string(&s.text()),
+        Atom::StringBuilder(_) => STRING_BUILDER_REPR.to_string(),
         Atom::Number(n) => n.to_string(),
         Atom::Symbol(s) => s.to_string(),
         Atom::Nil => NIL_LIT.to_string(),
+        Atom::Keyword(k) => format!("{}:", k),
         Atom::Lambda(ld) => print_lambda(ld, &false),
+        Atom::CaseLambda(clauses) => print_case_lambda(clauses, &false),
+        Atom::Primitive(p) => format!("#<primitive {}>", p.name),
+        Atom::RandomSource(_) => RANDOM_SOURCE_REPR.to_string(),
+        Atom::Environment(_) => ENVIRONMENT_REPR.to_string(),
+        Atom::Continuation(_) => CONTINUATION_REPR.to_string(),
+        Atom::ErrorObject(e) => print_error_object(e, &false),
+        Atom::Values(values) => print_values(values, &false),
+        Atom::Promise(_) => PROMISE_REPR.to_string(),
+        Atom::Generator(_) => GENERATOR_REPR.to_string(),
+        Atom::Yield(_) => YIELD_REPR.to_string(),
+        Atom::Eof => EOF_REPR.to_string(),
+        Atom::Parameter(_) => PARAMETER_REPR.to_string(),
+        Atom::Port(_) => OUTPUT_PORT_REPR.to_string(),
+        Atom::RecordType(t) => format!("#<record-type {}>", t.name()),
+        Atom::Record(r) => print_record(r, &false),
+        Atom::RecordProcedure(p) => format!("#<procedure {}>", p.name),
+        Atom::Regexp(r) => format!("#<regexp {}>", escape_string(r.pattern())),
+        Atom::RegexpMatch(m) => format!("#<regexp-match {}>", escape_string(&m.text())),
     };
 
     result
@@ -134,11 +676,33 @@ fn print_atom_colored(expr_atom: &Atom) -> String {
             true => Red.paint(TRUE_LIT).to_string(),
             false => Red.paint(FALSE_LIT).to_string(),
         },
-        Atom::StringLiteral(s) => Yellow.paint(s.to_string()).to_string(),
+        Atom::Char(c) => Yellow.paint(print_char(*c)).to_string(),
+        Atom::StringLiteral(s) => Yellow.paint(escape_string(s)).to_string(),
+        Atom::MutableString(s) => Yellow.paint(escape_string(&s.text())).to_string(),
+        Atom::StringBuilder(_) => STRING_BUILDER_REPR.to_string(),
         Atom::Number(n) => Red.paint(n.to_string()).to_string(),
         Atom::Symbol(s) => s.to_string(),
         Atom::Nil => Red.paint(NIL_LIT.to_string()).to_string(),
+        Atom::Keyword(k) => Blue.paint(format!("{}:", k)).to_string(),
         Atom::Lambda(ld) => print_lambda(ld, &true),
+        Atom::CaseLambda(clauses) => print_case_lambda(clauses, &true),
+        Atom::Primitive(p) => Green.paint(format!("#<primitive {}>", p.name)).to_string(),
+        Atom::RandomSource(_) => RANDOM_SOURCE_REPR.to_string(),
+        Atom::Environment(_) => ENVIRONMENT_REPR.to_string(),
+        Atom::Continuation(_) => CONTINUATION_REPR.to_string(),
+        Atom::ErrorObject(e) => print_error_object(e, &true),
+        Atom::Values(values) => print_values(values, &true),
+        Atom::Promise(_) => PROMISE_REPR.to_string(),
+        Atom::Generator(_) => GENERATOR_REPR.to_string(),
+        Atom::Yield(_) => YIELD_REPR.to_string(),
+        Atom::Eof => EOF_REPR.to_string(),
+        Atom::Parameter(_) => PARAMETER_REPR.to_string(),
+        Atom::Port(_) => OUTPUT_PORT_REPR.to_string(),
+        Atom::RecordType(t) => format!("#<record-type {}>", t.name()),
+        Atom::Record(r) => print_record(r, &true),
+        Atom::RecordProcedure(p) => Green.paint(format!("#<procedure {}>", p.name)).to_string(),
+        Atom::Regexp(r) => format!("#<regexp {}>", escape_string(r.pattern())),
+        Atom::RegexpMatch(m) => format!("#<regexp-match {}>", escape_string(&m.text())),
     };
 
     result
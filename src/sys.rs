@@ -1,105 +1,180 @@
 use crate::env::*;
+use crate::exception;
+use crate::port;
+use crate::string;
 use crate::syntax::*;
-use std::fs;
-use std::fs::File;
-use std::io::Read;
-use std::vec::Vec;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
 
 pub trait EnvSys {
-    fn slurp(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn write(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn print(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
-    fn println(&mut self, expr: &Vec<Expr>) -> Result<Expr, String>;
+    fn slurp(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn spit(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn append_file(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn write_lines(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn print(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn println(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn display(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn write_datum(&mut self, expr: &[Expr]) -> Result<Expr, String>;
+    fn newline(&mut self, expr: &[Expr]) -> Result<Expr, String>;
 }
 
 impl EnvSys for Env {
-    fn slurp(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
+    fn slurp(&mut self, expr: &[Expr]) -> Result<Expr, String> {
         if expr.len() != 1 {
             Err("Incorrect argument count for 'slurp' function.".to_string())
         } else {
-            let arg1 = self.simplify(&expr[0]);
-
-            match arg1 {
-                Ok(v) => match v {
-                    Expr::Atom(a) => match *a {
-                        Atom::StringLiteral(s) => {
-                            let trimmed: &str = &s.as_str()[1..s.len() - 1];
-                            let mut f = File::open(trimmed);
-                            let mut buf = String::new();
-
-                            let result = f.and_then(|mut x| x.read_to_string(&mut buf));
-                            if let Ok(_) = result {
-                                Ok(Expr::Atom(Box::new(Atom::StringLiteral(buf))))
-                            } else {
-                                Err(String::from("'slurp' could not read file..."))
-                            }
+            match &expr[0] {
+                Expr::Atom(a) => match &**a {
+                    Atom::StringLiteral(s) => {
+                        let mut buf = String::new();
+
+                        let result = File::open(s).and_then(|mut x| x.read_to_string(&mut buf));
+                        if result.is_ok() {
+                            Ok(Expr::Atom(Box::new(Atom::StringLiteral(buf))))
+                        } else {
+                            Err(exception::file_error(
+                                self,
+                                String::from("'slurp' could not read file..."),
+                            ))
                         }
-                        _ => Err(String::from("The argument of 'slurp' must be a string!")),
-                    },
-                    _ => Err(String::from("Argument of 'slurp' must be file path!")),
+                    }
+                    _ => Err(String::from("The argument of 'slurp' must be a string!")),
                 },
-                Err(msg) => Err(msg),
+                _ => Err(String::from("Argument of 'slurp' must be file path!")),
             }
         }
     }
 
-    fn write(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
-        todo!()
-        // if expr.len() != 2 {
-        //     Err("Incorrect argument count for 'write' function.".to_string())
-        // }
-        // else {
-        //     let file_name = self.simplify(&expr[0]);
-        //     let content = self.simplify(&expr[1]);
-
-        //     match tree {
-        //         Ok(v) => Ok(Expr::Atom(Atom::Nil)),
-        //         Err(msg) => Err(msg),
-        //     }
-        // }
+    // Creates or overwrites a file. Values other than strings are written
+    // the way 'display' shows them.
+    fn spit(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        match expr {
+            [path, content] => write_file(self, path, &print_display(content), false, SPIT_FN),
+            _ => Err(format!("Incorrect argument count for '{}' function.", SPIT_FN)),
+        }
     }
 
-    fn print(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
-        if expr.len() != 1 {
-            Err("Incorrect argument count for 'slurp' function.".to_string())
-        } else {
-            let arg1 = self.simplify(&expr[0]);
-
-            match arg1 {
-                Ok(v) => match v {
-                    Expr::Atom(a) => match *a {
-                        Atom::StringLiteral(s) => {
-                            print!("{}\n", s);
-                            Ok(Expr::Atom(Box::new(Atom::Nil)))
-                        }
-                        _ => Err(String::from("The argument of 'slurp' must be a string!")),
-                    },
-                    _ => Err(String::from("Argument of 'slurp' must be file path!")),
-                },
-                Err(msg) => Err(msg),
+    // Like 'spit', but adds to the end of a file that already exists.
+    fn append_file(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        match expr {
+            [path, content] => {
+                write_file(self, path, &print_display(content), true, APPEND_FILE_FN)
             }
+            _ => Err(format!("Incorrect argument count for '{}' function.", APPEND_FILE_FN)),
         }
     }
 
-    fn println(&mut self, expr: &Vec<Expr>) -> Result<Expr, String> {
-        if expr.len() != 1 {
-            Err("Incorrect argument count for 'slurp' function.".to_string())
-        } else {
-            let arg1 = self.simplify(&expr[0]);
-
-            match arg1 {
-                Ok(v) => match v {
-                    Expr::Atom(a) => match *a {
-                        Atom::StringLiteral(s) => {
-                            print!("{}\n", s);
-                            Ok(Expr::Atom(Box::new(Atom::Nil)))
-                        }
-                        _ => Err(String::from("The argument of 'slurp' must be a string!")),
-                    },
-                    _ => Err(String::from("Argument of 'slurp' must be file path!")),
-                },
-                Err(msg) => Err(msg),
+    // Writes every element of a list on a line of its own.
+    fn write_lines(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        match expr {
+            [path, Expr::List(lines)] => {
+                let text: String = lines.iter().map(|x| print_display(x) + "\n").collect();
+
+                write_file(self, path, &text, false, WRITE_LINES_FN)
+            }
+            [_, _] => Err(format!("The second argument of '{}' must be a list.", WRITE_LINES_FN)),
+            _ => Err(format!("Incorrect argument count for '{}' function.", WRITE_LINES_FN)),
+        }
+    }
+
+    // Like 'display' on the current output port, followed by a newline.
+    fn print(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        print_line(self, expr, PRINT_FN)
+    }
+
+    fn println(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        print_line(self, expr, PRINTLN_FN)
+    }
+
+    // Strings and characters are written as their text, without a newline.
+    fn display(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        match expr {
+            [value] | [value, _] => {
+                let port = output_port(self, &expr[1..], DISPLAY_FN)?;
+                port.write_str(&print_display(value));
+                Ok(Expr::Atom(Box::new(Atom::Nil)))
             }
+            _ => Err(format!("Incorrect argument count for '{}' function.", DISPLAY_FN)),
+        }
+    }
+
+    // Writes a value so that it can be read back.
+    fn write_datum(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        match expr {
+            [value] | [value, _] => {
+                let port = output_port(self, &expr[1..], WRITE_FN)?;
+                port.write_str(&print_write(value));
+                Ok(Expr::Atom(Box::new(Atom::Nil)))
+            }
+            _ => Err(format!("Incorrect argument count for '{}' function.", WRITE_FN)),
+        }
+    }
+
+    fn newline(&mut self, expr: &[Expr]) -> Result<Expr, String> {
+        if expr.len() > 1 {
+            return Err(format!("Incorrect argument count for '{}' function.", NEWLINE_FN));
+        }
+
+        output_port(self, expr, NEWLINE_FN)?.write_str("\n");
+        Ok(Expr::Atom(Box::new(Atom::Nil)))
+    }
+}
+
+/**
+ * Writes text to a file, errors like a missing directory or a file that
+ * can't be written are raised as file errors.
+ *
+ * @param env Environment the error is raised in.
+ * @param path Path of the file.
+ * @param text Text to write.
+ * @param append Whether the text is added to the end of the file rather
+ * than replacing it.
+ * @param name Name of the procedure, used in error messages.
+ * @return Nil, or the error message.
+ */
+fn write_file(
+    env: &mut Env,
+    path: &Expr,
+    text: &str,
+    append: bool,
+    name: &str,
+) -> Result<Expr, String> {
+    let path = string::string_arg(path, name)
+        .map_err(|_| format!("The path given to '{}' must be a string.", name))?;
+
+    let result = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()));
+
+    match result {
+        Ok(()) => Ok(Expr::Atom(Box::new(Atom::Nil))),
+        Err(e) => Err(exception::file_error(
+            env,
+            format!("'{}' could not write file '{}': {}.", name, path, e),
+        )),
+    }
+}
+
+// The port given as the optional last argument, or the current output port.
+fn output_port(env: &Env, rest: &[Expr], name: &str) -> Result<port::Port, String> {
+    match rest.first() {
+        Some(expr) => port::port_arg(expr)
+            .ok_or_else(|| format!("The port given to '{}' must be an output port.", name)),
+        None => Ok(port::current_output_port(env)),
+    }
+}
+
+// Output goes to the port of 'current-output-port'.
+fn print_line(env: &mut Env, expr: &[Expr], name: &str) -> Result<Expr, String> {
+    match expr {
+        [value] => {
+            port::current_output_port(env).write_str(&format!("{}\n", print_display(value)));
+            Ok(Expr::Atom(Box::new(Atom::Nil)))
         }
+        _ => Err(format!("Incorrect argument count for '{}' function.", name)),
     }
 }
@@ -1,10 +1,9 @@
 use crate::env::{Env, Eval};
 use crate::syntax;
-use std::collections::HashMap;
 
 #[test]
 fn one_plus_one_equals_2() {
-    let mut environment: Env = Box::new(HashMap::new());
+    let mut environment: Env = Env::new();
     let lisp = String::from("(+ 1 1)");
     let expected = Ok(String::from("2"));
     let actual = environment
@@ -15,7 +14,7 @@ fn one_plus_one_equals_2() {
 
 #[test]
 fn a_plus_b_equals_three() {
-    let mut environment: Env = Box::new(HashMap::new());
+    let mut environment: Env = Env::new();
     let expected = Ok(String::from("3"));
     let define_a = String::from("(define a 2)");
     let define_b = String::from("(define b 1)");
@@ -33,9 +32,9 @@ fn a_plus_b_equals_three() {
 
 #[test]
 fn using_add2_on_3_will_equal_5() {
-    let mut environment: Env = Box::new(HashMap::new());
+    let mut environment: Env = Env::new();
     let expected = Ok(String::from("5"));
-    let define_add2 = String::from("(define add2 (lambda n (+ n 2)))");
+    let define_add2 = String::from("(define add2 (lambda (n) (+ n 2)))");
     let equation = String::from("(add2 3)");
 
     environment.eval(&define_add2);
@@ -50,10 +49,10 @@ fn using_add2_on_3_will_equal_5() {
 // Overflows stack currently.
 #[test]
 fn define_add4_in_terms_of_add2() {
-    let mut environment: Env = Box::new(HashMap::new());
+    let mut environment: Env = Env::new();
     let expected = Ok(String::from("5"));
-    let define_add2 = String::from("(define add2 (lambda n (+ n 2)))");
-    let define_add4 = String::from("(define add4 (lambda n (add2 (add2 n))))");
+    let define_add2 = String::from("(define add2 (lambda (n) (+ n 2)))");
+    let define_add4 = String::from("(define add4 (lambda (n) (add2 (add2 n))))");
     let equation = String::from("(add4 1)");
 
     environment.eval(&define_add2);
@@ -65,3 +64,1614 @@ fn define_add4_in_terms_of_add2() {
 
     assert_eq!(expected, actual);
 }
+
+// Evaluates each line in a fresh environment and prints the last result.
+#[cfg(test)]
+fn eval_lines(lines: &[&str]) -> Result<String, String> {
+    let mut environment: Env = Env::new();
+    let mut result = Err(String::from("No input."));
+
+    for line in lines {
+        result = environment
+            .eval(&line.to_string())
+            .map(|x| syntax::print_tree(&x, &false));
+    }
+
+    result
+}
+
+#[test]
+fn random_sources_with_same_seed_agree() {
+    let a = eval_lines(&[
+        "(define src (make-random-source 42))",
+        "(random-integer 1000000 src)",
+    ]);
+    let b = eval_lines(&[
+        "(define src (make-random-source 42))",
+        "(random-integer 1000000 src)",
+    ]);
+
+    assert!(a.is_ok());
+    assert_eq!(a, b);
+}
+
+#[test]
+fn default_random_source_survives_redefinition() {
+    let redefined = eval_lines(&[
+        "(define default-random-source 5)",
+        "(random-integer 1000000)",
+    ]);
+    let untouched = eval_lines(&["(random-integer 1000000)"]);
+
+    assert!(redefined.is_ok());
+    assert_eq!(untouched, redefined);
+    assert_eq!(
+        untouched,
+        eval_lines(&["(random-integer 1000000 (default-random-source))"])
+    );
+}
+
+#[test]
+fn random_integer_stays_below_bound() {
+    let mut environment: Env = Env::new();
+    let lisp = String::from("(random-integer 3)");
+
+    for _ in 0..50 {
+        let actual = environment
+            .eval(&lisp)
+            .map(|x| syntax::print_tree(&x, &false))
+            .unwrap();
+
+        assert!(["0", "1", "2"].contains(&actual.as_str()));
+    }
+}
+
+#[test]
+fn shuffle_keeps_every_element() {
+    let mut environment: Env = Env::new();
+    let shuffled = environment
+        .eval(&String::from("(shuffle (quote (1 2 3 4 5 6)) (make-random-source 7))"))
+        .map(|x| syntax::print_tree(&x, &false))
+        .unwrap();
+
+    let mut numbers: Vec<&str> = shuffled
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(' ')
+        .collect();
+    numbers.sort();
+
+    assert_eq!(vec!["1", "2", "3", "4", "5", "6"], numbers);
+}
+
+#[test]
+fn random_choice_of_single_element_list() {
+    let expected = Ok(String::from("9"));
+    let actual = eval_lines(&["(random-choice (quote (9)))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn random_real_is_between_zero_and_one() {
+    let actual = eval_lines(&["(random-real (make-random-source 1))"]).unwrap();
+
+    assert!(actual.starts_with("0."));
+}
+
+#[test]
+fn or_returns_first_true_value() {
+    let expected = Ok(String::from("5"));
+    let actual = eval_lines(&["(or #f 5 (undefined-function))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn and_returns_last_value() {
+    let expected = Ok(String::from("3"));
+    let actual = eval_lines(&["(and 1 2 3)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn and_stops_at_first_false_value() {
+    let expected = Ok(String::from("#f"));
+    let actual = eval_lines(&["(and 1 #f (undefined-function))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn if_treats_non_boolean_as_true() {
+    let expected = Ok(String::from("yes"));
+    let actual = eval_lines(&["(if (quote (1 2)) (quote yes) (quote no))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn if_without_alternative() {
+    assert_eq!(Ok(String::from("nil")), eval_lines(&["(if #f 1)"]));
+    assert_eq!(Ok(String::from("1")), eval_lines(&["(if 0 1)"]));
+}
+
+#[test]
+fn not_is_only_true_for_false() {
+    assert_eq!(Ok(String::from("#f")), eval_lines(&["(not 0)"]));
+    assert_eq!(Ok(String::from("#t")), eval_lines(&["(not #f)"]));
+}
+
+#[test]
+fn cond_picks_first_true_clause() {
+    let expected = Ok(String::from("two"));
+    let actual = eval_lines(&[
+        "(define n 2)",
+        "(cond ((= n 1) (quote one)) ((= n 2) (quote two)) (else (quote many)))",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn cond_falls_through_to_else() {
+    let expected = Ok(String::from("many"));
+    let actual = eval_lines(&["(cond ((= 1 2) (quote one)) (else (quote many)))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn cond_arrow_passes_test_value() {
+    let expected = Ok(String::from("10"));
+    let actual = eval_lines(&["(cond (#f 1) (5 => (lambda (x) (* x 2))))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn cond_clause_without_body_returns_test() {
+    let expected = Ok(String::from("7"));
+    let actual = eval_lines(&["(cond (#f) (7))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn cond_else_must_be_last() {
+    let expected = Err(String::from("'else' clause must be last in 'cond' operator."));
+    let actual = eval_lines(&["(cond (else 1) (#t 2))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn case_matches_datum_lists() {
+    let expected = Ok(String::from("composite"));
+    let actual = eval_lines(&[
+        "(case (* 2 3) ((2 3 5 7) (quote prime)) ((1 4 6 8 9) (quote composite)))",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn case_else_with_arrow() {
+    let expected = Ok(String::from("8"));
+    let actual = eval_lines(&["(case 4 ((1) (quote one)) (else => (lambda (x) (* x 2))))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn when_and_unless() {
+    assert_eq!(Ok(String::from("2")), eval_lines(&["(when (= 1 1) 1 2)"]));
+    assert_eq!(Ok(String::from("nil")), eval_lines(&["(when #f 1)"]));
+    assert_eq!(Ok(String::from("3")), eval_lines(&["(unless #f 3)"]));
+    assert_eq!(Ok(String::from("nil")), eval_lines(&["(unless 0 3)"]));
+}
+
+#[test]
+fn single_element_lists_are_kept() {
+    let expected = Ok(String::from("(1)"));
+    let actual = eval_lines(&["(quote (1))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn single_element_clauses_keep_their_parentheses() {
+    assert_eq!(
+        Ok(String::from("(called one ((1)))")),
+        eval_lines(&[
+            "(define (f) (quote called))",
+            "(list (cond (#t (f))) (case 1 ((1) (quote one)) (else (quote other))) (quote ((1))))"
+        ])
+    );
+}
+
+#[test]
+fn single_token_lines_are_parsed_as_atoms() {
+    assert_eq!(Ok(String::from("5")), eval_lines(&["5"]));
+    assert_eq!(Ok(String::from("2")), eval_lines(&["(define x 2)", "x"]));
+}
+
+#[test]
+fn calling_function_without_arguments() {
+    let expected = Ok(String::from("5"));
+    let actual = eval_lines(&["(define five (lambda () 5))", "(five)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn let_binds_local_variables() {
+    let expected = Ok(String::from("3"));
+    let actual = eval_lines(&["(let ((a 1) (b 2)) (+ a b))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn let_bindings_are_gone_after_it_returns() {
+    let expected = Err(String::from("Symbol of name 'a' is undefined."));
+    let actual = eval_lines(&["(let ((a 1)) a)", "a"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn let_does_not_overwrite_globals() {
+    let expected = Ok(String::from("1"));
+    let actual = eval_lines(&["(define x 1)", "(let ((x 10)) x)", "x"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn let_initial_values_use_outer_scope() {
+    let expected = Ok(String::from("2"));
+    let actual = eval_lines(&["(define x 1)", "(let ((x 5) (y x)) (+ y 1))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn let_star_sees_previous_bindings() {
+    let expected = Ok(String::from("6"));
+    let actual = eval_lines(&["(let* ((x 1) (y (+ x 1)) (z (* y 3))) z)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn letrec_allows_mutual_recursion() {
+    let expected = Ok(String::from("#t"));
+    let actual = eval_lines(&[
+        "(letrec ((even? (lambda (n) (if (= n 0) #t (odd? (- n 1))))) \
+                  (odd? (lambda (n) (if (= n 0) #f (even? (- n 1)))))) \
+           (even? 10))",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn letrec_star_initializes_in_order() {
+    let expected = Ok(String::from("8"));
+    let actual = eval_lines(&["(letrec* ((a 4) (b (* a 2))) b)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn letrec_value_used_before_assignment() {
+    let expected = Err(String::from("Symbol of name 'b' is used before it is assigned."));
+    let actual = eval_lines(&["(letrec ((a b) (b 1)) a)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn named_let_loops_in_constant_stack() {
+    let expected = Ok(String::from("50005000"));
+    let actual = eval_lines(&[
+        "(let loop ((i 1) (total 0)) (if (= i 10001) total (loop (+ i 1) (+ total i))))",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn lambda_captures_let_variables() {
+    let expected = Ok(String::from("15"));
+    let actual = eval_lines(&[
+        "(define add5 (let ((n 5)) (lambda (x) (+ x n))))",
+        "(add5 10)",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn lambda_body_with_several_expressions() {
+    let expected = Ok(String::from("3"));
+    let actual = eval_lines(&[
+        "(define f (lambda (x) (define y 1) (+ x y)))",
+        "(f 2)",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn internal_define_is_local_to_body() {
+    let expected = Err(String::from("Symbol of name 'y' is undefined."));
+    let actual = eval_lines(&["(define f (lambda (x) (define y x) y))", "(f 2)", "y"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn begin_returns_last_value() {
+    assert_eq!(Ok(String::from("3")), eval_lines(&["(begin 1 2 3)"]));
+    assert_eq!(Ok(String::from("nil")), eval_lines(&["(begin)"]));
+}
+
+#[test]
+fn begin_at_top_level_defines_globals() {
+    let expected = Ok(String::from("2"));
+    let actual = eval_lines(&["(begin (define a 1) (define b 2))", "b"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn let_body_with_internal_define() {
+    let expected = Ok(String::from("20"));
+    let actual = eval_lines(&["(let ((x 10)) (define (double n) (* n 2)) (double x))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn define_procedure_shorthand() {
+    let expected = Ok(String::from("120"));
+    let actual = eval_lines(&[
+        "(define (fac n) (if (= n 0) 1 (* n (fac (- n 1)))))",
+        "(fac 5)",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn define_curried_procedure() {
+    let expected = Ok(String::from("7"));
+    let actual = eval_lines(&[
+        "(define ((adder a) b) (+ a b))",
+        "(define add3 (adder 3))",
+        "(add3 4)",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn define_evaluates_value_once() {
+    let expected = Ok(String::from("1"));
+    let actual = eval_lines(&[
+        "(define calls 0)",
+        "(define x (begin (set! calls (+ calls 1)) 42))",
+        "(+ x x)",
+        "calls",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn set_changes_global_binding() {
+    let expected = Ok(String::from("5"));
+    let actual = eval_lines(&["(define x 1)", "(set! x 5)", "x"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn set_changes_nearest_binding() {
+    assert_eq!(
+        Ok(String::from("3")),
+        eval_lines(&["(define x 1)", "(let ((x 2)) (set! x 3) x)"])
+    );
+    assert_eq!(
+        Ok(String::from("1")),
+        eval_lines(&["(define x 1)", "(let ((x 2)) (set! x 3) x)", "x"])
+    );
+}
+
+#[test]
+fn set_keeps_state_in_closure() {
+    let expected = Ok(String::from("3"));
+    let actual = eval_lines(&[
+        "(define counter (let ((n 0)) (lambda () (set! n (+ n 1)) n)))",
+        "(counter)",
+        "(counter)",
+        "(counter)",
+    ]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn set_undefined_symbol() {
+    let expected = Err(String::from("Cannot assign undefined symbol 'y'."));
+    let actual = eval_lines(&["(set! y 1)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn lambda_symbol_takes_all_arguments() {
+    let expected = Ok(String::from("(1 2 3)"));
+    let actual = eval_lines(&["(define f (lambda args args))", "(f 1 2 3)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn lambda_dotted_rest_parameter() {
+    assert_eq!(
+        Ok(String::from("(2 3)")),
+        eval_lines(&["(define (f a . rest) rest)", "(f 1 2 3)"])
+    );
+    assert_eq!(
+        Ok(String::from("()")),
+        eval_lines(&["(define (f a . rest) rest)", "(f 1)"])
+    );
+}
+
+#[test]
+fn optional_parameters_use_defaults() {
+    let lines = ["(define* (f a (b 2) (c (+ a b))) (+ (* 100 a) (* 10 b) c))"];
+
+    assert_eq!(
+        Ok(String::from("123")),
+        eval_lines(&[lines[0], "(f 1)"])
+    );
+    assert_eq!(
+        Ok(String::from("156")),
+        eval_lines(&[lines[0], "(f 1 5)"])
+    );
+}
+
+#[test]
+fn keyword_arguments() {
+    let define = "(define* (f a #!key (b 10) (c 20)) (+ a (* b c)))";
+
+    assert_eq!(Ok(String::from("201")), eval_lines(&[define, "(f 1)"]));
+    assert_eq!(Ok(String::from("61")), eval_lines(&[define, "(f 1 c: 6)"]));
+    assert_eq!(Ok(String::from("7")), eval_lines(&[define, "(f 1 c: 3 b: 2)"]));
+}
+
+#[test]
+fn unknown_keyword_argument() {
+    let expected = Err(String::from("Procedure 'f' has no keyword parameter 'd:'."));
+    let actual = eval_lines(&["(define* (f #!key (b 1)) b)", "(f d: 2)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn case_lambda_dispatches_on_argument_count() {
+    let define = "(define f (case-lambda ((a) 1) ((a b) 2) ((a . rest) 3)))";
+
+    assert_eq!(Ok(String::from("1")), eval_lines(&[define, "(f 0)"]));
+    assert_eq!(Ok(String::from("2")), eval_lines(&[define, "(f 0 0)"]));
+    assert_eq!(Ok(String::from("3")), eval_lines(&[define, "(f 0 0 0)"]));
+    assert_eq!(
+        Err(String::from(
+            "Procedure 'f' accepts exactly 1 or exactly 2 or at least 1 arguments, got 0."
+        )),
+        eval_lines(&[define, "(f)"])
+    );
+}
+
+#[test]
+fn arity_error_names_procedure() {
+    assert_eq!(
+        Err(String::from("Procedure 'f' accepts exactly 2 arguments, got 1.")),
+        eval_lines(&["(define (f a b) a)", "(f 1)"])
+    );
+    assert_eq!(
+        Err(String::from("Procedure 'g' accepts between 1 and 2 arguments, got 3.")),
+        eval_lines(&["(define* (g a (b 1)) a)", "(g 1 2 3)"])
+    );
+}
+
+#[test]
+fn lambda_in_operator_position() {
+    let expected = Ok(String::from("16"));
+    let actual = eval_lines(&["((lambda (x) (* x x)) 4)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn call_result_in_operator_position() {
+    assert_eq!(
+        Ok(String::from("7")),
+        eval_lines(&["(define (adder a) (lambda (b) (+ a b)))", "((adder 3) 4)"])
+    );
+    assert_eq!(
+        Ok(String::from("10")),
+        eval_lines(&[
+            "(define (twice x) (* x 2))",
+            "(define (compose f g) (lambda (x) (f (g x))))",
+            "((compose twice twice) 2)",
+            "((if #t twice compose) 5)",
+        ])
+    );
+}
+
+#[test]
+fn calling_non_procedure_names_value() {
+    assert_eq!(
+        Err(String::from("Value '5' is not a procedure and cannot be called.")),
+        eval_lines(&["(define x 5)", "(x 1)"])
+    );
+    assert_eq!(
+        Err(String::from("Value '3' is not a procedure and cannot be called.")),
+        eval_lines(&["((+ 1 2) 4)"])
+    );
+}
+
+#[test]
+fn primitive_is_a_value() {
+    assert_eq!(Ok(String::from("#<primitive +>")), eval_lines(&["+"]));
+    assert_eq!(
+        Ok(String::from("3")),
+        eval_lines(&["(define plus +)", "(plus 1 2)"])
+    );
+    assert_eq!(Ok(String::from("#t")), eval_lines(&["(eq? car car)"]));
+    assert_eq!(Ok(String::from("#f")), eval_lines(&["(eq? car cdr)"]));
+}
+
+#[test]
+fn map_with_primitive() {
+    let expected = Ok(String::from("(1 3)"));
+    let actual = eval_lines(&["(map car (quote ((1 2) (3 4))))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn map_over_several_lists() {
+    let expected = Ok(String::from("(11 22)"));
+    let actual = eval_lines(&["(map + (list 1 2 3) (list 10 20))"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn shadowing_primitive_keeps_captured_original() {
+    assert_eq!(
+        Ok(String::from("3")),
+        eval_lines(&["(define add +)", "(define + -)", "(add 1 2)"])
+    );
+    assert_eq!(
+        Ok(String::from("6")),
+        eval_lines(&["(let ((+ *)) (+ 2 3))"])
+    );
+    assert_eq!(
+        Ok(String::from("5")),
+        eval_lines(&["(let ((+ *)) (+ 2 3))", "(+ 2 3)"])
+    );
+}
+
+#[test]
+fn quote_shorthand() {
+    assert_eq!(Ok(String::from("(1 2 3)")), eval_lines(&["'(1 2 3)"]));
+    assert_eq!(Ok(String::from("(quote x)")), eval_lines(&["''x"]));
+}
+
+#[test]
+fn string_literal_escapes() {
+    assert_eq!(Ok(String::from("\"a (b) c\"")), eval_lines(&["\"a (b) c\""]));
+    assert_eq!(
+        Ok(String::from("\"say \\\"hi\\\"\\n\"")),
+        eval_lines(&["\"say \\\"hi\\\"\\n\""])
+    );
+}
+
+#[test]
+fn unterminated_string() {
+    let expected = Err(String::from("Missing closing quote of string."));
+    let actual = eval_lines(&["(string \"abc)"]);
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn apply_spreads_last_argument() {
+    assert_eq!(Ok(String::from("10")), eval_lines(&["(apply + 1 2 '(3 4))"]));
+    assert_eq!(
+        Ok(String::from("(1 2)")),
+        eval_lines(&["(apply list '(1 2))"])
+    );
+}
+
+#[test]
+fn eval_constructed_expression() {
+    assert_eq!(
+        Ok(String::from("3")),
+        eval_lines(&["(eval (list '+ 1 2) (interaction-environment))"])
+    );
+    assert_eq!(
+        Ok(String::from("5")),
+        eval_lines(&["(eval '(define x 5) (interaction-environment))", "x"])
+    );
+}
+
+#[test]
+fn eval_in_isolated_environment() {
+    let lines = [
+        "(define x 1)",
+        "(define e (environment '(scheme base)))",
+        "(eval '(define x 2) e)",
+    ];
+
+    assert_eq!(
+        Ok(String::from("2")),
+        eval_lines(&[lines[0], lines[1], lines[2], "(eval '(* x 1) e)"])
+    );
+    assert_eq!(
+        Ok(String::from("1")),
+        eval_lines(&[lines[0], lines[1], lines[2], "x"])
+    );
+}
+
+#[test]
+fn empty_environment_has_no_procedures() {
+    assert_eq!(
+        Err(String::from("Function name '+' not recognized.")),
+        eval_lines(&["(eval '(+ 1 2) (make-empty-environment))"])
+    );
+    assert_eq!(
+        Ok(String::from("3")),
+        eval_lines(&["(eval '(if #t 3 4) (make-empty-environment))"])
+    );
+}
+
+#[test]
+fn call_cc_escapes() {
+    assert_eq!(
+        Ok(String::from("3")),
+        eval_lines(&["(+ 1 (call/cc (lambda (k) (+ 10 (k 2)))))"])
+    );
+    assert_eq!(
+        Ok(String::from("4")),
+        eval_lines(&["(+ 1 (call-with-current-continuation (lambda (k) 3)))"])
+    );
+}
+
+#[test]
+fn continuation_can_be_resumed() {
+    let lines = [
+        "(define k nil)",
+        "(define n 0)",
+        "(let () (call/cc (lambda (c) (set! k c))) (set! n (+ n 1)) (if (= n 3) n (k 0)))",
+    ];
+
+    assert_eq!(Ok(String::from("3")), eval_lines(&lines));
+}
+
+#[test]
+fn continuations_escape_from_parameter_defaults() {
+    assert_eq!(
+        Ok(String::from("6")),
+        eval_lines(&["(+ 1 (call/cc (lambda (k) (define* (h (x (k 5))) x) (h))))"])
+    );
+    assert_eq!(
+        Ok(String::from("(2 3)")),
+        eval_lines(&[
+            "(define k nil)",
+            "(define n 0)",
+            "(define* (f #!key (x (call/cc (lambda (c) (set! k c) 1)))) x)",
+            "(let () (define r (f)) (set! n (+ n 1)) (if (= n 3) (list r n) (k n)))",
+        ])
+    );
+}
+
+#[test]
+fn dynamic_wind_runs_after_on_escape() {
+    let lines = [
+        "(define log 0)",
+        "(define (note d) (lambda () (set! log (+ (* log 10) d))))",
+        "(call/cc (lambda (k) (dynamic-wind (note 1) (lambda () (k 0) ((note 2))) (note 3))))",
+        "log",
+    ];
+
+    assert_eq!(Ok(String::from("13")), eval_lines(&lines));
+}
+
+#[test]
+fn dynamic_wind_returns_thunk_value() {
+    let lines = [
+        "(define log 0)",
+        "(define (note d) (lambda () (set! log (+ (* log 10) d))))",
+        "(define v (dynamic-wind (note 1) (lambda () ((note 2)) 7) (note 3)))",
+        "(+ (* log 10) v)",
+    ];
+
+    assert_eq!(Ok(String::from("1237")), eval_lines(&lines));
+}
+
+#[test]
+fn deep_recursion_does_not_overflow() {
+    let lines = [
+        "(define (sum n) (if (= n 0) 0 (+ n (sum (- n 1)))))",
+        "(sum 20000)",
+    ];
+
+    assert_eq!(Ok(String::from("200010000")), eval_lines(&lines));
+}
+
+#[test]
+fn guard_catches_error() {
+    assert_eq!(
+        Ok(String::from("(\"Bad value:\" (1 2))")),
+        eval_lines(&[
+            "(guard (e ((error-object? e) (list (error-object-message e) (error-object-irritants e)))) (error \"Bad value:\" 1 2))"
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("42")),
+        eval_lines(&["(guard (e ((= e 1) 0) (else (* e 2))) (+ 1 (raise 21)))"])
+    );
+}
+
+#[test]
+fn guard_catches_interpreter_errors() {
+    assert_eq!(
+        Ok(String::from("\"Symbol of name 'y' is undefined.\"")),
+        eval_lines(&["(guard (e (#t (error-object-message e))) (+ 1 y))"])
+    );
+    assert_eq!(
+        Ok(String::from("#t")),
+        eval_lines(&["(guard (e (#t (file-error? e))) (slurp \"no/such/file\"))"])
+    );
+}
+
+#[test]
+fn guard_without_matching_clause_raises_again() {
+    let lines = [
+        "(guard (outer (#t (list 'outer outer))) (guard (inner ((= inner 1) 'one)) (raise 2)))",
+    ];
+
+    assert_eq!(Ok(String::from("(outer 2)")), eval_lines(&lines));
+}
+
+#[test]
+fn raise_continuable_returns_handler_value() {
+    assert_eq!(
+        Ok(String::from("43")),
+        eval_lines(&[
+            "(with-exception-handler (lambda (c) 42) (lambda () (+ (raise-continuable 'oops) 1)))"
+        ])
+    );
+    assert_eq!(
+        Err(String::from("Exception handler returned from non-continuable 'raise'.")),
+        eval_lines(&["(with-exception-handler (lambda (c) 42) (lambda () (+ (raise 'oops) 1)))"])
+    );
+}
+
+#[test]
+fn handlers_reach_into_parameter_defaults() {
+    assert_eq!(
+        Ok(String::from("43")),
+        eval_lines(&[
+            "(define* (f (x (raise-continuable 'missing))) (+ x 1))",
+            "(with-exception-handler (lambda (c) 42) (lambda () (f)))"
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("caught")),
+        eval_lines(&[
+            "(define* (g #!key (x (error \"no x\"))) x)",
+            "(guard (e (#t 'caught)) (g))"
+        ])
+    );
+}
+
+#[test]
+fn uncaught_error_reports_message() {
+    assert_eq!(
+        Err(String::from("Value too large: 7")),
+        eval_lines(&["(error \"Value too large:\" 7)"])
+    );
+    assert_eq!(
+        Err(String::from("Uncaught exception '5'.")),
+        eval_lines(&["(raise 5)"])
+    );
+}
+
+#[test]
+fn guard_runs_dynamic_wind_after() {
+    let lines = [
+        "(define log 0)",
+        "(define (note d) (lambda () (set! log (+ (* log 10) d))))",
+        "(guard (e (#t ((note 4)))) (dynamic-wind (note 1) (lambda () (raise 'x)) (note 3)))",
+        "log",
+    ];
+
+    assert_eq!(Ok(String::from("134")), eval_lines(&lines));
+}
+
+#[test]
+fn call_with_values_spreads_values() {
+    assert_eq!(
+        Ok(String::from("5")),
+        eval_lines(&["(call-with-values (lambda () (values 2 3)) +)"])
+    );
+    assert_eq!(
+        Ok(String::from("(1)")),
+        eval_lines(&["(call-with-values (lambda () 1) list)"])
+    );
+}
+
+#[test]
+fn let_values_binds_formals() {
+    assert_eq!(
+        Ok(String::from("(1 2 (3 (4)))")),
+        eval_lines(&["(let-values (((a b) (values 1 2)) ((c . d) (values 3 4))) (list a b (list c d)))"])
+    );
+    assert_eq!(
+        Ok(String::from("(1 3)")),
+        eval_lines(&["(let*-values (((a b) (values 1 2)) ((c) (values (+ a b)))) (list a c))"])
+    );
+    assert_eq!(
+        Err(String::from("Expected exactly 2 values, got 3.")),
+        eval_lines(&["(let-values (((a b) (values 1 2 3))) a)"])
+    );
+}
+
+#[test]
+fn define_values_and_receive() {
+    assert_eq!(
+        Ok(String::from("14")),
+        eval_lines(&["(define-values (q r) (floor/ 23 3))", "(* q r)"])
+    );
+    assert_eq!(
+        Ok(String::from("(-4 1)")),
+        eval_lines(&["(receive (q r) (floor/ -7 2) (list q r))"])
+    );
+    assert_eq!(
+        Ok(String::from("(-3 -1)")),
+        eval_lines(&["(receive (q r) (truncate/ -7 2) (list q r))"])
+    );
+}
+
+#[test]
+fn exact_integer_sqrt_returns_root_and_rest() {
+    assert_eq!(
+        Ok(String::from("(4 1)")),
+        eval_lines(&["(receive (s r) (exact-integer-sqrt 17) (list s r))"])
+    );
+    assert_eq!(Ok(String::from("4 1")), eval_lines(&["(exact-integer-sqrt 17)"]));
+}
+
+#[test]
+fn delay_is_forced_once() {
+    let lines = [
+        "(define count 0)",
+        "(define p (delay (begin (set! count (+ count 1)) 10)))",
+        "(+ (force p) (force p) count)",
+    ];
+
+    assert_eq!(Ok(String::from("21")), eval_lines(&lines));
+}
+
+#[test]
+fn make_promise_and_force() {
+    assert_eq!(Ok(String::from("5")), eval_lines(&["(force (make-promise 5))"]));
+    assert_eq!(Ok(String::from("5")), eval_lines(&["(force 5)"]));
+    assert_eq!(
+        Ok(String::from("(#t #f)")),
+        eval_lines(&["(list (promise? (delay 1)) (promise? 1))"])
+    );
+}
+
+#[test]
+fn delay_force_loop_runs_iteratively() {
+    let lines = [
+        "(define (loop n) (delay-force (if (= n 0) (make-promise 'done) (loop (- n 1)))))",
+        "(force (loop 20000))",
+    ];
+
+    assert_eq!(Ok(String::from("done")), eval_lines(&lines));
+}
+
+#[test]
+fn infinite_streams() {
+    let lines = [
+        "(define (from n) (stream-cons n (from (+ n 1))))",
+        "(define evens (stream-filter (lambda (x) (= (% x 2) 0)) (from 0)))",
+        "(stream->list (stream-take 3 (stream-map (lambda (x) (* x x)) evens)))",
+    ];
+
+    assert_eq!(Ok(String::from("(0 4 16)")), eval_lines(&lines));
+    assert_eq!(
+        Ok(String::from("(1 2)")),
+        eval_lines(&[lines[0], "(stream->list 2 (stream-cdr (from 0)))"])
+    );
+}
+
+#[test]
+fn stream_filter_skips_in_bounded_space() {
+    let lines = [
+        "(define (from n) (stream-cons n (from (+ n 1))))",
+        "(stream-car (stream-filter (lambda (x) (= x 5000)) (from 0)))",
+    ];
+
+    assert_eq!(Ok(String::from("5000")), eval_lines(&lines));
+}
+
+#[test]
+fn stream_elements_are_lazy() {
+    let lines = [
+        "(define s (stream-cons (car '()) (stream-cons 2 stream-null)))",
+        "(list (stream-pair? s) (stream-car (stream-cdr s)) (stream-null? (stream-cdr (stream-cdr s))))",
+    ];
+
+    assert_eq!(Ok(String::from("(#t 2 #t)")), eval_lines(&lines));
+}
+
+#[test]
+fn generator_yields_values_in_turn() {
+    let lines = [
+        "(define g (make-generator (lambda (yield) (yield 1) (yield 2) 'ignored)))",
+        "(list (g) (g) (eof-object? (g)) (eof-object? (g)))",
+    ];
+
+    assert_eq!(Ok(String::from("(1 2 #t #t)")), eval_lines(&lines));
+}
+
+#[test]
+fn guard_around_resume_catches_generator_errors() {
+    let lines = [
+        "(define g (make-generator (lambda (yield) (yield 1) (error \"boom\") (yield 2))))",
+        "(list (g) (guard (e (#t 'caught)) (g)) (eof-object? (g)))",
+    ];
+
+    assert_eq!(Ok(String::from("(1 caught #t)")), eval_lines(&lines));
+}
+
+#[test]
+fn generator_sees_parameters_of_each_caller() {
+    let lines = [
+        "(define p (make-parameter 1))",
+        "(define g (make-generator (lambda (yield) (yield (p)) (yield (p)) (parameterize ((p 5)) (yield (p)) (yield (p))))))",
+        "(list (parameterize ((p 2)) (g)) (g) (parameterize ((p 3)) (g)) (parameterize ((p 4)) (g)) (p))",
+    ];
+
+    assert_eq!(Ok(String::from("(2 1 5 5 1)")), eval_lines(&lines));
+}
+
+#[test]
+fn generator_can_be_called_after_an_error() {
+    let lines = [
+        "(define g (make-generator (lambda (yield) (yield 1) (car '()) (yield 2))))",
+        "(g)",
+        "(g)",
+        "(eof-object? (g))",
+    ];
+
+    assert!(eval_lines(&lines[..3]).is_err());
+    assert_eq!(Ok(String::from("#t")), eval_lines(&lines));
+
+    let lines = [
+        "(define escape nil)",
+        "(define e (make-generator (lambda (yield) (escape 'left) (yield 2))))",
+        "(list (call/cc (lambda (k) (set! escape k) (e))) (eof-object? (e)))",
+    ];
+
+    assert_eq!(Ok(String::from("(left #t)")), eval_lines(&lines));
+    assert_eq!(
+        Err(String::from("Generator cannot be resumed while it is running.")),
+        eval_lines(&["(define f (make-generator (lambda (yield) (f))))", "(f)"])
+    );
+}
+
+#[test]
+fn generator_walks_tree() {
+    let lines = [
+        "(define (walk tree yield) (if (atom tree) (yield tree) (for-each (lambda (t) (walk t yield)) tree)))",
+        "(define (leaves tree) (make-generator (lambda (yield) (walk tree yield))))",
+        "(generator->list (leaves '(1 (2 3) ((4)) 5)))",
+    ];
+
+    assert_eq!(Ok(String::from("(1 2 3 4 5)")), eval_lines(&lines));
+}
+
+#[test]
+fn coroutine_receives_values() {
+    let lines = [
+        "(define total (make-generator (lambda (yield) (let loop ((sum 0)) (loop (+ sum (yield sum)))))))",
+        "(total)",
+        "(total 5)",
+        "(total 10)",
+    ];
+
+    assert_eq!(Ok(String::from("15")), eval_lines(&lines));
+}
+
+#[test]
+fn generator_helpers() {
+    assert_eq!(
+        Ok(String::from("(0 2 4 6 8)")),
+        eval_lines(&["(generator->list (make-iota-generator 5 0 2))"])
+    );
+    assert_eq!(
+        Ok(String::from("(1 9 25)")),
+        eval_lines(&[
+            "(generator->list (gmap (lambda (x) (* x x)) (gfilter (lambda (x) (= (% x 2) 1)) (make-iota-generator 6))))"
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("(0 1 0 0)")),
+        eval_lines(&["(generator->list (gtake (make-iota-generator 2) 4 0))"])
+    );
+    assert_eq!(
+        Ok(String::from("(11 23)")),
+        eval_lines(&[
+            "(generator->list (gmap + (make-iota-generator 2 1) (make-iota-generator 5 10 11)))"
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("(0 1 2)")),
+        eval_lines(&["(generator->list (make-iota-generator 100) 3)"])
+    );
+}
+
+#[test]
+fn parameterize_restores_value_on_return() {
+    let lines = [
+        "(define level (make-parameter 1))",
+        "(define (show) (level))",
+        "(list (show) (parameterize ((level 2)) (show)) (show))",
+    ];
+
+    assert_eq!(Ok(String::from("(1 2 1)")), eval_lines(&lines));
+}
+
+#[test]
+fn parameterize_restores_value_on_error() {
+    let lines = [
+        "(define level (make-parameter 'info))",
+        "(guard (e (#t (level))) (parameterize ((level 'debug)) (error \"failed\")))",
+    ];
+    assert_eq!(Ok(String::from("info")), eval_lines(&lines));
+
+    let mut env = Env::new();
+    env.eval("(define level (make-parameter 'info))").unwrap();
+    assert!(env.eval("(parameterize ((level 'debug)) (car 5))").is_err());
+    assert_eq!(Ok(String::from("info")), env.eval("(level)").map(|x| syntax::print_tree(&x, &false)));
+}
+
+#[test]
+fn parameterize_restores_value_on_escape() {
+    let lines = [
+        "(define level (make-parameter 1))",
+        "(define inside (call/cc (lambda (k) (parameterize ((level 2)) (k (level))))))",
+        "(list inside (level))",
+    ];
+
+    assert_eq!(Ok(String::from("(2 1)")), eval_lines(&lines));
+}
+
+#[test]
+fn parameter_converter_applies_to_values() {
+    let lines = [
+        "(define width (make-parameter 10 (lambda (x) (* x 2))))",
+        "(list (width) (parameterize ((width 3)) (width)))",
+    ];
+
+    assert_eq!(Ok(String::from("(20 6)")), eval_lines(&lines));
+}
+
+#[test]
+fn output_port_is_a_parameter() {
+    let lines = [
+        "(define out (open-output-string))",
+        "(parameterize ((current-output-port out)) (print \"hello\") (println \"world\"))",
+        "(get-output-string out)",
+    ];
+
+    assert_eq!(Ok(String::from("\"hello\\nworld\\n\"")), eval_lines(&lines));
+    assert!(eval_lines(&["(parameterize ((current-output-port 5)) 1)"]).is_err());
+}
+
+#[test]
+fn print_and_println_display_any_value() {
+    let lines = [
+        "(define out (open-output-string))",
+        "(parameterize ((current-output-port out)) (print 42) (println '(\"a\" #\\b)) (println 1.5))",
+        "(get-output-string out)",
+    ];
+
+    assert_eq!(Ok(String::from("\"42\\n(a b)\\n1.5\\n\"")), eval_lines(&lines));
+    assert!(eval_lines(&["(print 1 2)"]).is_err());
+}
+
+#[test]
+fn numeric_precision_is_a_parameter() {
+    assert_eq!(
+        Ok(String::from("(0.33333 0.5)")),
+        eval_lines(&["(parameterize ((numeric-precision 5)) (list (/ 1 3) (/ 1 2)))"])
+    );
+    assert_eq!(
+        Ok(String::from("0.6667")),
+        eval_lines(&["(parameterize ((numeric-precision 4)) (/ 2 3))"])
+    );
+    assert!(eval_lines(&["(parameterize ((numeric-precision 0)) 1)"]).is_err());
+}
+
+#[test]
+fn record_type_procedures() {
+    let lines = [
+        "(define-record-type <point> (make-point x y) point? (x point-x set-point-x!) (y point-y))",
+        "(define p (make-point 1 2))",
+        "(set-point-x! p 10)",
+        "(list (point? p) (point? 5) (point-x p) (point-y p))",
+    ];
+
+    assert_eq!(Ok(String::from("(#t #f 10 2)")), eval_lines(&lines));
+}
+
+#[test]
+fn record_prints_fields() {
+    let lines = [
+        "(define-record-type <point> (make-point x y) point? (x point-x) (y point-y))",
+        "(make-point 1 2)",
+    ];
+
+    assert_eq!(Ok(String::from("#<point x: 1 y: 2>")), eval_lines(&lines));
+}
+
+#[test]
+fn records_compare_by_identity() {
+    let lines = [
+        "(define-record-type point (make-point x y) point? (x point-x) (y point-y))",
+        "(define p (make-point 1 2))",
+        "(list (eq? p p) (eq? p (make-point 1 2)))",
+    ];
+
+    assert_eq!(Ok(String::from("(#t #f)")), eval_lines(&lines));
+}
+
+#[test]
+fn record_accessor_checks_type() {
+    let lines = [
+        "(define-record-type <point> (make-point x) point? (x point-x))",
+        "(define-record-type <size> (make-size x) size? (x size-x))",
+        "(point-x (make-size 1))",
+    ];
+
+    assert_eq!(
+        Err(String::from("Procedure 'point-x' expects a record of type 'point', got '#<size x: 1>'.")),
+        eval_lines(&lines)
+    );
+}
+
+#[test]
+fn equals_only_compares_numbers() {
+    assert_eq!(Ok(String::from("#t")), eval_lines(&["(= 2 2.0 2)"]));
+    assert_eq!(Ok(String::from("#f")), eval_lines(&["(= 1 2 1)"]));
+    assert!(eval_lines(&["(= 'a 'a)"]).is_err());
+    assert!(eval_lines(&["(= 1 2 '(3))"]).is_err());
+}
+
+#[test]
+fn eq_eqv_and_equal() {
+    assert_eq!(
+        Ok(String::from("(#t #f #f #t)")),
+        eval_lines(&["(list (eq? 'a 'a) (eq? 2 2.0) (eqv? 2 2.0) (eq? '() '()))"])
+    );
+    assert_eq!(
+        Ok(String::from("(#t #f #t)")),
+        eval_lines(&[
+            "(define f (lambda (x) x))",
+            "(list (eq? f f) (eq? (lambda (x) x) (lambda (x) x)) (equal? '(1 (2 \"a\")) '(1 (2 \"a\"))))"
+        ])
+    );
+}
+
+#[test]
+fn eq_and_eqv_compare_lists_and_strings_by_content() {
+    assert_eq!(
+        Ok(String::from("(#t #t #t #t #t)")),
+        eval_lines(&[
+            "(define x '(1 2))",
+            "(define s \"ab\")",
+            "(list (eq? x x) (eqv? x x) (eq? s s) (eq? (list 1 2) (list 1 2)) (eqv? \"a\" \"a\"))"
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("(#f #f)")),
+        eval_lines(&["(list (eq? '(1 2.0) '(1 2)) (eqv? '(1 2.0) '(1 2)))"])
+    );
+    assert_eq!(
+        Ok(String::from("(((1) 2) (\"b\") (b 2) ((c) 3))")),
+        eval_lines(&[
+            "(define k '(1))",
+            "(list (memq k (list 0 k 2)) (memv \"b\" '(\"a\" \"b\")) (assq 'b '((a 1) (b 2))) (assv '(c) (list (list '(c) 3))))"
+        ])
+    );
+}
+
+#[test]
+fn eqv_tells_exact_from_inexact_numbers() {
+    assert_eq!(
+        Ok(String::from("(#f #f #t #t #f)")),
+        eval_lines(&["(list (eqv? 1.0 1) (equal? 1.0 1) (eqv? 0.5 0.50) (eqv? 100 (* 10 10)) (eq? 0.5 0.50))"])
+    );
+}
+
+#[test]
+fn equal_compares_records_and_stops_on_cycles() {
+    let lines = [
+        "(define-record-type node (make-node value next) node? (value node-value) (next node-next set-node-next!))",
+        "(define a (make-node 1 nil))",
+        "(define b (make-node 1 nil))",
+        "(set-node-next! a a)",
+        "(set-node-next! b b)",
+        "(list (eqv? a b) (equal? a b) (equal? a (make-node 2 a)))",
+    ];
+
+    assert_eq!(Ok(String::from("(#f #t #f)")), eval_lines(&lines));
+}
+
+#[test]
+fn member_and_assoc_families() {
+    assert_eq!(
+        Ok(String::from("((c d) #f ((1) 2) (2 3))")),
+        eval_lines(&["(list (memq 'c '(a b c d)) (memq 'e '(a b)) (member '(1) '(0 (1) 2)) (memv 2 '(1 2 3)))"])
+    );
+    assert_eq!(
+        Ok(String::from("((b 2) (2.0 x) #f)")),
+        eval_lines(&["(list (assq 'b '((a 1) (b 2))) (assv 2.00 '((2 y) (2.0 x))) (assoc \"z\" '((\"a\" 1))))"])
+    );
+    assert_eq!(
+        Ok(String::from("((3 8) (2 b))")),
+        eval_lines(&[
+            "(define (double? a b) (= a (* b 2)))",
+            "(list (member 6 '(5 3 8) double?) (assoc 4 '((1 a) (2 b)) double?))"
+        ])
+    );
+}
+
+#[test]
+fn case_compares_with_eqv() {
+    assert_eq!(
+        Ok(String::from("(small big)")),
+        eval_lines(&["(map (lambda (n) (case n ((1 2 3) 'small) (else 'big))) '(2 2.0))"])
+    );
+}
+
+#[test]
+fn type_predicates() {
+    assert_eq!(
+        Ok(String::from("(#t #t #f #t #t #f #t #t)")),
+        eval_lines(&["(list (number? 1.5) (integer? 2.0) (integer? 2.5) (rational? 1) (real? 1) (string? 'a) (string? \"a\") (symbol? 'a))"])
+    );
+    assert_eq!(
+        Ok(String::from("(#t #f #t #t #t #f #t #f #t #f)")),
+        eval_lines(&["(list (boolean? #f) (boolean? nil) (procedure? car) (procedure? (lambda (x) x)) (null? '()) (null? '(1)) (pair? '(1)) (pair? '()) (list? '()) (vector? '(1)))"])
+    );
+    assert_eq!(
+        Ok(String::from("(#t #f #t)")),
+        eval_lines(&["(list (char? #\\a) (char? \"a\") (procedure? (make-parameter 1)))"])
+    );
+}
+
+#[test]
+fn char_literals() {
+    assert_eq!(
+        Ok(String::from("(#\\a #\\space #\\( #\\newline)")),
+        eval_lines(&["(list #\\a #\\space #\\( #\\newline)"])
+    );
+    assert_eq!(Ok(String::from("#t")), eval_lines(&["(eqv? #\\x #\\x)"]));
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(
+        Ok(String::from("(\"abc\" abc \"hi\" (#\\h #\\i))")),
+        eval_lines(&["(list (symbol->string 'abc) (string->symbol \"abc\") (list->string (list #\\h #\\i)) (string->list \"hi\"))"])
+    );
+    assert_eq!(
+        Ok(String::from("(\"2.5\" \"ff\" \"-101\" \"17\")")),
+        eval_lines(&["(list (number->string 2.5) (number->string 255 16) (number->string -5 2) (number->string 17 10))"])
+    );
+    assert!(eval_lines(&["(number->string 2.5 2)"]).is_err());
+    assert!(eval_lines(&["(list->string '(1 2))"]).is_err());
+}
+
+#[test]
+fn boolean_eq_needs_booleans() {
+    assert_eq!(
+        Ok(String::from("(#t #f)")),
+        eval_lines(&["(list (boolean=? #t #t #t) (boolean=? #f #t))"])
+    );
+    assert!(eval_lines(&["(boolean=? #t 1)"]).is_err());
+}
+
+#[test]
+fn strings_are_indexed_by_character() {
+    assert_eq!(
+        Ok(String::from("(4 #\\é \"éq\" \"ab\" 2)")),
+        eval_lines(&["(list (string-length \"héqé\") (string-ref \"héqé\" 1) (substring \"héqé\" 1 3) (string-copy \"abc\" 0 2) (string-contains \"héllo\" \"ll\"))"])
+    );
+    assert!(eval_lines(&["(string-ref \"abc\" 3)"]).is_err());
+    assert!(eval_lines(&["(substring \"abc\" 2 1)"]).is_err());
+}
+
+#[test]
+fn string_building_and_searching() {
+    assert_eq!(
+        Ok(String::from("(\"abcd\" \"ABC\" \"abc\" 1 #f 2)")),
+        eval_lines(&["(list (string-append \"ab\" \"\" \"cd\") (string-upcase \"abc\") (string-downcase \"ABC\") (string-index \"abc\" #\\b) (string-index \"abc\" #\\z) (string-index \"ab1\" (lambda (c) (eqv? c #\\1))))"])
+    );
+    assert_eq!(
+        Ok(String::from("((\"a\" \"b\" \"c\") (\"a\" \"\" \"b\") \"a-b\" \"x y\")")),
+        eval_lines(&["(list (string-split \" a  b c \") (string-split \"a,,b\" #\\,) (string-join '(\"a\" \"b\") \"-\") (string-join '(\"x\" \"y\")))"])
+    );
+    assert_eq!(
+        Ok(String::from("(\"a \" \" a\" \"a\" \"b\" \"  ab\" \"bc\" \"ab..\" #t #f \"x-y-z\")")),
+        eval_lines(&["(list (string-trim \" a \") (string-trim-right \" a \") (string-trim-both \" a \") (string-trim-both \"xbx\" #\\x) (string-pad \"ab\" 4) (string-pad \"abc\" 2) (string-pad-right \"ab\" 4 #\\.) (string-prefix? \"ab\" \"abc\") (string-suffix? \"ab\" \"abc\") (string-replace \"x.y.z\" \".\" \"-\"))"])
+    );
+}
+
+#[test]
+fn string_map_and_for_each() {
+    assert_eq!(
+        Ok(String::from("(\"ba\" (#\\c #\\b #\\a))")),
+        eval_lines(&[
+            "(define seen '())",
+            "(string-for-each (lambda (c) (set! seen (apply list c seen))) \"abc\")",
+            "(list (string-map (lambda (a b) b) \"xyz\" \"ba\") seen)",
+        ])
+    );
+    assert!(eval_lines(&["(string-map (lambda (c) 1) \"a\")"]).is_err());
+}
+
+#[test]
+fn string_comparisons() {
+    assert_eq!(
+        Ok(String::from("(#t #f #t #t #f #t #t)")),
+        eval_lines(&["(list (string=? \"a\" \"a\" \"a\") (string=? \"a\" \"A\") (string<? \"a\" \"b\" \"c\") (string<=? \"a\" \"a\") (string>? \"a\" \"b\") (string>=? \"b\" \"a\") (string-ci=? \"a\" \"A\"))"])
+    );
+    assert!(eval_lines(&["(string<? \"a\" 1)"]).is_err());
+}
+
+#[test]
+fn mutable_strings() {
+    assert_eq!(
+        Ok(String::from("(\"aza\" \"a--a\" \"xx\" #t #t #f)")),
+        eval_lines(&[
+            "(define s (make-string 3 #\\a))",
+            "(string-set! s 1 #\\z)",
+            "(define t (string-copy \"abca\"))",
+            "(string-fill! t #\\- 1 3)",
+            "(define u (make-string 2 #\\y))",
+            "(string-fill! u #\\x)",
+            "(list s t u (string? s) (equal? s \"aza\") (eqv? s (string-copy s)))",
+        ])
+    );
+    assert!(eval_lines(&["(string-set! \"abc\" 0 #\\x)"]).is_err());
+    assert!(eval_lines(&["(string-set! (make-string 2) 2 #\\x)"]).is_err());
+}
+
+#[test]
+fn string_builder_accumulates_text() {
+    assert_eq!(
+        Ok(String::from("(\"line 0\\nline 1\\nline 2\\n\" 21)")),
+        eval_lines(&[
+            "(define b (make-string-builder))",
+            "(let loop ((i 0)) (unless (= i 3) (string-builder-append! b \"line \" (number->string i) #\\newline) (loop (+ i 1))))",
+            "(list (builder->string b) (string-length (builder->string b)))",
+        ])
+    );
+    assert!(eval_lines(&["(string-builder-append! (make-string-builder) 1)"]).is_err());
+}
+
+#[test]
+fn format_directives() {
+    assert_eq!(
+        Ok(String::from("\"x=hi \\\"hi\\\" (a \\\"b\\\") #\\\\c|c\\n~\"")),
+        eval_lines(&["(format #f \"x=~a ~s ~s ~s|~a~%~~\" \"hi\" \"hi\" '(a \"b\") #\\c #\\c)"])
+    );
+    assert_eq!(
+        Ok(String::from("\"[   42] [3.14] [   -2.50] ff 101 [ab  ]\"")),
+        eval_lines(&["(format #f \"[~5d] [~,2d] [~8,2d] ~x ~b [~4a]\" 42 3.14159 -2.5 255 5 \"ab\")"])
+    );
+    assert_eq!(Ok(String::from("\"1 2\"")), eval_lines(&["(format \"~a ~a\" 1 2)"]));
+}
+
+#[test]
+fn format_writes_to_ports() {
+    assert_eq!(
+        Ok(String::from("(nil \"total: 3\\n\")")),
+        eval_lines(&[
+            "(define out (open-output-string))",
+            "(list (format out \"total: ~a~%\" 3) (get-output-string out))",
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("\"via #t\"")),
+        eval_lines(&[
+            "(define out (open-output-string))",
+            "(parameterize ((current-output-port out)) (format #t \"via #t\"))",
+            "(get-output-string out)",
+        ])
+    );
+}
+
+#[test]
+fn format_rejects_bad_control_strings() {
+    assert!(eval_lines(&["(format #f \"~a ~a\" 1)"]).is_err());
+    assert!(eval_lines(&["(format #f \"~a\" 1 2)"]).is_err());
+    assert!(eval_lines(&["(format #f \"~q\" 1)"]).is_err());
+    assert!(eval_lines(&["(format #f \"~x\" 1.5)"]).is_err());
+    assert!(eval_lines(&["(format #f \"~d\" \"1\")"]).is_err());
+    assert!(eval_lines(&["(format 'out \"x\")"]).is_err());
+}
+
+#[test]
+fn format_rejects_huge_fields() {
+    assert_eq!(
+        Err(String::from("Field width of 'format' is too large.")),
+        eval_lines(&["(format #f \"~99999999999999999999d\" 1)"])
+    );
+    assert_eq!(
+        Err(String::from("Field width of 'format' is too large.")),
+        eval_lines(&["(format #f \"~9999999999d\" 1)"])
+    );
+    assert_eq!(
+        Err(String::from("Precision of 'format' is too large.")),
+        eval_lines(&["(format #f \"~,9999999999d\" 1)"])
+    );
+    assert_eq!(Ok(String::from("\"   7\"")), eval_lines(&["(format #f \"~4d\" 7)"]));
+}
+
+#[test]
+fn regexp_match_and_search() {
+    assert_eq!(
+        Ok(String::from("(#t #f \"2024-01-05\" \"01\" 7 17 #f)")),
+        eval_lines(&[
+            "(define date (regexp \"(\\\\d{4})-(\\\\d\\\\d)-(\\\\d\\\\d)\"))",
+            "(define m (regexp-search date \"logged 2024-01-05 ok\"))",
+            "(list (regexp? date) (regexp-match date \"on 2024-01-05\") (regexp-match-submatch m) (regexp-match-submatch m 2) (regexp-match-submatch-start m 0) (regexp-match-submatch-end m 3) (regexp-search \"x\" \"abc\"))",
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("((\"ab\" \"a\" #f) 2)")),
+        eval_lines(&[
+            "(define m (regexp-match \"(a)b|(c)\" \"ab\"))",
+            "(list (regexp-match->list m) (regexp-match-count m))",
+        ])
+    );
+    assert_eq!(
+        Ok(String::from("(\"é\" 3)")),
+        eval_lines(&["(define m (regexp-search \"[é]\" \"abcé\" 1))", "(list (regexp-match-submatch m) (regexp-match-submatch-start m))"])
+    );
+}
+
+#[test]
+fn regexp_syntax() {
+    let cases = [
+        ("(regexp-match \"a*?b+c?\" \"aabbb\")", "#<regexp-match \"aabbb\">"),
+        ("(regexp-match \"(?:ab){2,3}\" \"ababab\")", "#<regexp-match \"ababab\">"),
+        ("(regexp-match \"(?:ab){2,3}\" \"ab\")", "#f"),
+        ("(regexp-match \"[^a-c]x{2}\" \"dxx\")", "#<regexp-match \"dxx\">"),
+        ("(regexp-match-submatch (regexp-search \"a+?\" \"aaa\"))", "\"a\""),
+        ("(regexp-match-submatch (regexp-search \"\\\\bcat\\\\b\" \"concat cat\"))", "\"cat\""),
+        ("(regexp-match-submatch-start (regexp-search \"\\\\bcat\\\\b\" \"concat cat\"))", "7"),
+        ("(regexp-match \"^\\\\s*\\\\w+\\\\.$\" \" word.\")", "#<regexp-match \" word.\">"),
+        ("(regexp-match \"(a*)*b\" \"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaac\")", "#f"),
+        ("(regexp-match \"x{,2}\" \"x{,2}\")", "#<regexp-match \"x{,2}\">"),
+    ];
+
+    for (code, expected) in cases.iter() {
+        assert_eq!(Ok(expected.to_string()), eval_lines(&[code]), "{}", code);
+    }
+
+    for pattern in ["(a", "a)", "*a", "[a", "a{3,1}", "\\\\q"].iter() {
+        assert!(eval_lines(&[&format!("(regexp \"{}\")", pattern)]).is_err(), "{}", pattern);
+    }
+}
+
+#[test]
+fn regexp_replace_and_split() {
+    assert_eq!(
+        Ok(String::from("(\"b-a c=d\" \"b-a d-c\" \"[]a[]b[]\")")),
+        eval_lines(&["(list (regexp-replace \"(\\\\w)=(\\\\w)\" \"a=b c=d\" \"\\\\2-\\\\1\") (regexp-replace-all \"(\\\\w)=(\\\\w)\" \"a=b c=d\" \"\\\\2-\\\\1\") (regexp-replace-all \"x*\" \"ab\" \"[]\"))"])
+    );
+    assert_eq!(
+        Ok(String::from("((\"a\" \"b\" \"\" \"c\") (\"a\" \"b\" \"c\") (\"1\" \"22\"))")),
+        eval_lines(&["(list (regexp-split \",\" \"a,b,,c\") (regexp-split \"\" \"abc\") (map regexp-match-submatch (regexp-match-all \"\\\\d+\" \"1 and 22\")))"])
+    );
+    assert!(eval_lines(&["(regexp-replace \"a\" \"a\" \"\\\\1\")"]).is_err());
+}
+
+#[test]
+fn display_write_and_newline() {
+    assert_eq!(
+        Ok(String::from("\"hi (a b c) x|\\\"hi\\\" (a \\\"b\\\" #\\\\c) #\\\\x 42\\n\"")),
+        eval_lines(&[
+            "(define out (open-output-string))",
+            "(display \"hi \" out)",
+            "(display (list 'a \"b\" #\\c) out)",
+            "(display #\\space out)",
+            "(display #\\x out)",
+            "(display \"|\" out)",
+            "(parameterize ((current-output-port out)) (write \"hi\") (display \" \") (write (list 'a \"b\" #\\c)) (display \" \") (write #\\x) (display \" \") (write 42) (newline))",
+            "(get-output-string out)",
+        ])
+    );
+    assert!(eval_lines(&["(display 1 2)"]).is_err());
+    assert!(eval_lines(&["(newline 1 2)"]).is_err());
+}
+
+#[test]
+fn write_labels_shared_and_cyclic_records() {
+    let lines = [
+        "(define-record-type node (make-node value next) node? (value node-value) (next node-next set-node-next!))",
+        "(define a (make-node 1 nil))",
+        "(set-node-next! a a)",
+        "(define b (make-node 2 nil))",
+        "(define out (open-output-string))",
+        "(write a out)",
+        "(display \" \" out)",
+        "(write (list b b (make-node \"s\" b)) out)",
+        "(list (get-output-string out) a)",
+    ];
+
+    assert_eq!(
+        Ok(String::from("(\"#0=#<node value: 1 next: #0#> (#0=#<node value: 2 next: nil> #0# #<node value: \\\"s\\\" next: #0#>)\" #0=#<node value: 1 next: #0#>)")),
+        eval_lines(&lines)
+    );
+}
+
+#[test]
+fn spit_append_and_write_lines() {
+    let dir = std::env::temp_dir().join(format!("mini-scheme-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("out.txt").to_string_lossy().replace('\\', "/");
+
+    assert_eq!(
+        Ok(String::from("(\"first\\n42\" \"a\\nb\\n3\\n\")")),
+        eval_lines(&[
+            &format!("(define path \"{}\")", path),
+            "(spit path \"old\")",
+            "(spit path \"first\\n\")",
+            "(append-file path 42)",
+            "(define a (slurp path))",
+            "(write-lines path (list \"a\" \"b\" 3))",
+            "(list a (slurp path))",
+        ])
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn file_output_errors_are_file_errors() {
+    assert_eq!(
+        Ok(String::from("#t")),
+        eval_lines(&["(guard (e (#t (file-error? e))) (spit \"no/such/dir/file.txt\" \"x\"))"])
+    );
+    assert!(eval_lines(&["(write-lines \"x.txt\" \"not a list\")"]).is_err());
+    assert!(eval_lines(&["(spit 1 \"x\")"]).is_err());
+}
@@ -0,0 +1,237 @@
+use crate::env::{Binding, Env, Frame, Scope};
+use crate::machine::{self, Cont, Pending, State};
+use crate::params::Params;
+use crate::syntax::{self, Atom, Expr};
+
+// Formals and initial expression of every binding of 'let-values', in order.
+pub type ValueBindings = Vec<(Params, Expr)>;
+
+pub trait ValuesOps {
+    fn values(&mut self, args: &[Expr]) -> Result<Expr, String>;
+    fn call_with_values(&mut self, args: Vec<Expr>, k: Cont) -> Result<State, String>;
+    fn let_values(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn let_star_values(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn define_values(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+    fn receive(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String>;
+}
+
+impl ValuesOps for Env {
+    // A single value is returned as it is, so only receivers that expect
+    // several values ever see the difference.
+    fn values(&mut self, args: &[Expr]) -> Result<Expr, String> {
+        Ok(multiple(args.to_vec()))
+    }
+
+    fn call_with_values(&mut self, mut args: Vec<Expr>, k: Cont) -> Result<State, String> {
+        if args.len() != 2 {
+            return Err("Incorrect argument count for 'call-with-values' function.".to_string());
+        }
+
+        let consumer = args.pop().unwrap();
+        let producer = args.pop().unwrap();
+
+        Ok(State::Apply(producer, Vec::new(), k.push(Pending::SpreadTo { consumer })))
+    }
+
+    // Formals are written like the parameters of 'lambda', so (a . rest)
+    // takes any number of values.
+    fn let_values(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let (bindings, body) = parse_value_bindings(args, syntax::LET_VALUES_OP)?;
+        let bindings = bindings.into_iter().rev().collect();
+
+        // Initial values can't see any of the new names.
+        resume_let_values(bindings, Vec::new(), body, false, scope, k)
+    }
+
+    fn let_star_values(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        let (bindings, body) = parse_value_bindings(args, syntax::LET_STAR_VALUES_OP)?;
+        let bindings = bindings.into_iter().rev().collect();
+        let scope = Frame::new_scope(Some(&scope));
+
+        resume_let_values(bindings, Vec::new(), body, true, scope, k)
+    }
+
+    fn define_values(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() != 2 {
+            return Err("Incorrect number of arguments for 'define-values' operator.".to_string());
+        }
+
+        let formals = Params::parse(&args[0], false)?;
+        let k = k.push(Pending::DefineValues {
+            formals,
+            scope: scope.clone(),
+        });
+
+        Ok(State::Eval(args[1].to_owned(), scope, k))
+    }
+
+    // (receive formals expr body...) is (let-values ((formals expr)) body...).
+    fn receive(&mut self, args: &[Expr], scope: Scope, k: Cont) -> Result<State, String> {
+        if args.len() < 3 {
+            return Err("Incorrect number of arguments for 'receive' operator.".to_string());
+        }
+
+        let binding = Expr::List(vec![args[0].to_owned(), args[1].to_owned()]);
+        let mut let_args = vec![Expr::List(vec![binding])];
+        let_args.extend_from_slice(&args[2..]);
+
+        self.let_values(&let_args, scope, k)
+    }
+}
+
+/**
+ * Makes the value returned for several values, a single one is returned
+ * as it is.
+ *
+ * @param values Values to return.
+ * @return Value for the continuation.
+ */
+pub fn multiple(mut values: Vec<Expr>) -> Expr {
+    if values.len() == 1 {
+        values.pop().unwrap()
+    } else {
+        Expr::Atom(Box::new(Atom::Values(values)))
+    }
+}
+
+// Values returned to a continuation, one unless 'values' made them.
+pub fn spread(value: Expr) -> Vec<Expr> {
+    match value {
+        Expr::Atom(atom) => match *atom {
+            Atom::Values(values) => values,
+            other => vec![Expr::Atom(Box::new(other))],
+        },
+        list => vec![list],
+    }
+}
+
+/**
+ * Evaluates the next initial expression of 'let-values' or 'let*-values',
+ * or enters the body once all of them are done.
+ *
+ * @param rest Bindings left, in reverse.
+ * @param bound Values of 'let-values' waiting to be bound together.
+ * @param body Body of the form.
+ * @param star Whether each binding is seen by the ones after it.
+ * @param scope Frame the next initial expression is evaluated in.
+ * @param k Continuation of the form.
+ * @return Next step of the evaluator.
+ */
+pub fn resume_let_values(
+    mut rest: ValueBindings,
+    bound: Vec<(Params, Expr)>,
+    body: Vec<Expr>,
+    star: bool,
+    scope: Scope,
+    k: Cont,
+) -> Result<State, String> {
+    match rest.pop() {
+        Some((formals, init)) => {
+            let k = k.push(Pending::LetValues {
+                formals,
+                rest,
+                bound,
+                body,
+                star,
+                scope: scope.clone(),
+            });
+
+            Ok(State::Eval(init, scope, k))
+        }
+        None if star => Ok(machine::sequence(&body, scope, k)),
+        None => {
+            let scope = Frame::new_scope(Some(&scope));
+
+            for (formals, value) in bound {
+                bind_formals(&scope, &formals, value)?;
+            }
+
+            Ok(machine::sequence(&body, scope, k))
+        }
+    }
+}
+
+/**
+ * Takes the value of one initial expression of 'let-values'.
+ *
+ * @param value Value returned to the formals.
+ * @param formals Names the value is bound to.
+ * @param bound Values waiting to be bound together, for 'let-values'.
+ * @param star Whether the names are bound right away.
+ * @param scope Frame the initial expression was evaluated in.
+ * @return Frame to evaluate the next initial expression in.
+ */
+pub fn take_value(
+    value: Expr,
+    formals: Params,
+    bound: &mut Vec<(Params, Expr)>,
+    star: bool,
+    scope: Scope,
+) -> Result<Scope, String> {
+    if star {
+        let scope = Frame::new_scope(Some(&scope));
+        bind_formals(&scope, &formals, value)?;
+
+        Ok(scope)
+    } else {
+        bound.push((formals, value));
+
+        Ok(scope)
+    }
+}
+
+/**
+ * Binds the names of a formals list to the values returned to it.
+ *
+ * @param scope Frame the names are bound in.
+ * @param formals Names to bind, a rest name takes the values left over.
+ * @param value Value returned, possibly made by 'values'.
+ * @return Nothing, or an error if the count of values doesn't fit.
+ */
+pub fn bind_formals(scope: &Scope, formals: &Params, value: Expr) -> Result<(), String> {
+    let values = spread(value);
+
+    if !formals.accepts(values.len()) {
+        return Err(format!(
+            "Expected {} values, got {}.",
+            formals.arity(),
+            values.len()
+        ));
+    }
+
+    let mut values = values.into_iter();
+
+    for name in formals.required.iter() {
+        let value = values.next().unwrap();
+        Frame::insert(scope, name, Binding::Value(value));
+    }
+
+    if let Some(name) = &formals.rest {
+        Frame::insert(scope, name, Binding::Value(Expr::List(values.collect())));
+    }
+
+    Ok(())
+}
+
+fn parse_value_bindings(args: &[Expr], name: &str) -> Result<(ValueBindings, Vec<Expr>), String> {
+    if args.len() < 2 {
+        return Err(format!("Incorrect number of arguments for '{}' operator.", name));
+    }
+
+    let bindings = match &args[0] {
+        Expr::List(list) => list,
+        Expr::Atom(_) => return Err(format!("Bindings of '{}' operator must be a list.", name)),
+    };
+
+    let bindings = bindings
+        .iter()
+        .map(|binding| match binding {
+            Expr::List(pair) if pair.len() == 2 => {
+                Ok((Params::parse(&pair[0], false)?, pair[1].to_owned()))
+            }
+            _ => Err(format!("Invalid binding in '{}' operator.", name)),
+        })
+        .collect::<Result<ValueBindings, String>>()?;
+
+    Ok((bindings, args[1..].to_vec()))
+}